@@ -0,0 +1,45 @@
+use shared::Session;
+use uuid::Uuid;
+use yewtil::fetch::{FetchRequest, Json, MethodBody};
+
+use super::API_PREFIX;
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct DeleteEntryRequest {
+    pub auth: Session,
+    pub id: Uuid,
+}
+
+impl DeleteEntryRequest {
+    pub fn new(auth: Session, id: Uuid) -> Self {
+        DeleteEntryRequest { auth, id }
+    }
+}
+
+impl FetchRequest for DeleteEntryRequest {
+    type RequestBody = ();
+    type ResponseBody = String;
+    type Format = Json;
+
+    fn url(&self) -> String {
+        format!(
+            "{}/users/{}/entries/{}",
+            API_PREFIX, self.auth.name, self.id
+        )
+    }
+
+    fn method(&self) -> MethodBody<Self::RequestBody> {
+        MethodBody::Delete
+    }
+
+    fn headers(&self) -> Vec<(String, String)> {
+        vec![
+            ("Content-Type".to_owned(), "application/json".to_owned()),
+            ("Authorization".to_owned(), self.auth.to_bearer_header()),
+        ]
+    }
+
+    fn use_cors(&self) -> bool {
+        true
+    }
+}
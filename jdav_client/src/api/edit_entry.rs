@@ -1,14 +1,16 @@
-use shared::{KilometerEntry, UserAuth};
+use shared::{KilometerEntry, Session};
 use yewtil::fetch::{FetchRequest, Json, MethodBody};
 
+use super::API_PREFIX;
+
 #[derive(Debug, Clone, PartialEq, Default)]
 pub struct KilometerEditRequest {
-    pub auth: UserAuth,
+    pub auth: Session,
     pub payload: KilometerEntry,
 }
 
 impl KilometerEditRequest {
-    pub fn new(auth: UserAuth, entry: KilometerEntry) -> Self {
+    pub fn new(auth: Session, entry: KilometerEntry) -> Self {
         KilometerEditRequest {
             auth,
             payload: entry,
@@ -18,11 +20,14 @@ impl KilometerEditRequest {
 
 impl FetchRequest for KilometerEditRequest {
     type RequestBody = KilometerEntry;
-    type ResponseBody = String;
+    type ResponseBody = KilometerEntry;
     type Format = Json;
 
     fn url(&self) -> String {
-        format!("/edit/{}", self.auth.name)
+        format!(
+            "{}/users/{}/entries/{}",
+            API_PREFIX, self.auth.name, self.payload.id.id
+        )
     }
 
     fn method(&self) -> MethodBody<Self::RequestBody> {
@@ -32,7 +37,7 @@ impl FetchRequest for KilometerEditRequest {
     fn headers(&self) -> Vec<(String, String)> {
         vec![
             ("Content-Type".to_owned(), "application/json".to_owned()),
-            ("Authorization".to_owned(), self.auth.to_basic_auth_header()),
+            ("Authorization".to_owned(), self.auth.to_bearer_header()),
         ]
     }
 
@@ -1,14 +1,19 @@
-use shared::UserAuth;
+use shared::Session;
 use yewtil::fetch::{FetchRequest, Json, MethodBody};
 
+use super::API_PREFIX;
+
 #[derive(Debug, Clone, PartialEq, Default)]
 pub struct EntriesRequest {
-    pub auth: UserAuth,
+    pub auth: Session,
+    pub cursor: Option<String>,
 }
 
+pub const PAGE_SIZE: usize = 30;
+
 impl EntriesRequest {
-    pub fn new(auth: UserAuth) -> Self {
-        EntriesRequest { auth }
+    pub fn new(auth: Session, cursor: Option<String>) -> Self {
+        EntriesRequest { auth, cursor }
     }
 }
 
@@ -18,7 +23,14 @@ impl FetchRequest for EntriesRequest {
     type Format = Json;
 
     fn url(&self) -> String {
-        format!("/entries/{}", self.auth.name)
+        let mut url = format!(
+            "{}/users/{}/entries?sort=newest&limit={}",
+            API_PREFIX, self.auth.name, PAGE_SIZE
+        );
+        if let Some(cursor) = &self.cursor {
+            url.push_str(&format!("&cursor={}", cursor));
+        }
+        url
     }
 
     fn method(&self) -> MethodBody<Self::RequestBody> {
@@ -28,7 +40,7 @@ impl FetchRequest for EntriesRequest {
     fn headers(&self) -> Vec<(String, String)> {
         vec![
             ("Content-Type".to_owned(), "application/json".to_owned()),
-            ("Authorization".to_owned(), self.auth.to_basic_auth_header()),
+            ("Authorization".to_owned(), self.auth.to_bearer_header()),
         ]
     }
 
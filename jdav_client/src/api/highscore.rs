@@ -1,14 +1,18 @@
-use shared::{Highscore, UserAuth};
+use chrono::SecondsFormat;
+use shared::{Highscore, HighscoreQuery, HighscoreWindow, Session};
 use yewtil::fetch::{FetchRequest, Json, MethodBody};
 
+use super::API_PREFIX;
+
 #[derive(Debug, Clone, PartialEq, Default)]
 pub struct HighscoreRequest {
-    pub auth: UserAuth,
+    pub auth: Session,
+    pub query: HighscoreQuery,
 }
 
 impl HighscoreRequest {
-    pub fn new(auth: UserAuth) -> Self {
-        HighscoreRequest { auth }
+    pub fn new(auth: Session, query: HighscoreQuery) -> Self {
+        HighscoreRequest { auth, query }
     }
 }
 
@@ -18,17 +22,35 @@ impl FetchRequest for HighscoreRequest {
     type Format = Json;
 
     fn url(&self) -> String {
-        "/highscore".to_owned()
+        let window = match self.query.window {
+            HighscoreWindow::All => "all",
+            HighscoreWindow::Week => "week",
+            HighscoreWindow::Month => "month",
+        };
+        let mut url = format!("{}/highscore?window={}", API_PREFIX, window);
+        if let Some(kind) = &self.query.kind {
+            url.push_str(&format!("&kind={}", kind));
+        }
+        for (name, bound) in &[("from", self.query.from), ("to", self.query.to)] {
+            if let Some(bound) = bound {
+                url.push_str(&format!(
+                    "&{}={}",
+                    name,
+                    bound.to_rfc3339_opts(SecondsFormat::Secs, true)
+                ));
+            }
+        }
+        url
     }
 
     fn method(&self) -> MethodBody<Self::RequestBody> {
-        MethodBody::Put(&())
+        MethodBody::Get
     }
 
     fn headers(&self) -> Vec<(String, String)> {
         vec![
             ("Content-Type".to_owned(), "application/json".to_owned()),
-            ("Authorization".to_owned(), self.auth.to_basic_auth_header()),
+            ("Authorization".to_owned(), self.auth.to_bearer_header()),
         ]
     }
 
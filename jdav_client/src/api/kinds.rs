@@ -0,0 +1,29 @@
+use shared::KindInfo;
+use yewtil::fetch::{FetchRequest, Json, MethodBody};
+
+use super::API_PREFIX;
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct KindsRequest;
+
+impl FetchRequest for KindsRequest {
+    type RequestBody = ();
+    type ResponseBody = Vec<KindInfo>;
+    type Format = Json;
+
+    fn url(&self) -> String {
+        format!("{}/kinds", API_PREFIX)
+    }
+
+    fn method(&self) -> MethodBody<Self::RequestBody> {
+        MethodBody::Get
+    }
+
+    fn headers(&self) -> Vec<(String, String)> {
+        vec![("Content-Type".to_owned(), "application/json".to_owned())]
+    }
+
+    fn use_cors(&self) -> bool {
+        true
+    }
+}
@@ -1,6 +1,8 @@
-use shared::UserAuth;
+use shared::{Session, UserAuth};
 use yewtil::fetch::{FetchRequest, Json, MethodBody};
 
+use super::API_PREFIX;
+
 #[derive(Debug, Clone, Default)]
 pub struct LoginRequest {
     pub payload: UserAuth,
@@ -27,15 +29,15 @@ impl LoginRequest {
 
 impl FetchRequest for LoginRequest {
     type RequestBody = UserAuth;
-    type ResponseBody = String;
+    type ResponseBody = Session;
     type Format = Json;
 
     fn url(&self) -> String {
-        "/authenticate".to_string()
+        format!("{}/authenticate", API_PREFIX)
     }
 
     fn method(&self) -> MethodBody<Self::RequestBody> {
-        MethodBody::Put(&self.payload)
+        MethodBody::Post(&self.payload)
     }
 
     fn headers(&self) -> Vec<(String, String)> {
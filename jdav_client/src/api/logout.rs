@@ -0,0 +1,40 @@
+use shared::Session;
+use yewtil::fetch::{FetchRequest, Json, MethodBody};
+
+use super::API_PREFIX;
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct LogoutRequest {
+    pub auth: Session,
+}
+
+impl LogoutRequest {
+    pub fn new(auth: Session) -> Self {
+        LogoutRequest { auth }
+    }
+}
+
+impl FetchRequest for LogoutRequest {
+    type RequestBody = ();
+    type ResponseBody = String;
+    type Format = Json;
+
+    fn url(&self) -> String {
+        format!("{}/logout", API_PREFIX)
+    }
+
+    fn method(&self) -> MethodBody<Self::RequestBody> {
+        MethodBody::Post(&())
+    }
+
+    fn headers(&self) -> Vec<(String, String)> {
+        vec![
+            ("Content-Type".to_owned(), "application/json".to_owned()),
+            ("Authorization".to_owned(), self.auth.to_bearer_header()),
+        ]
+    }
+
+    fn use_cors(&self) -> bool {
+        true
+    }
+}
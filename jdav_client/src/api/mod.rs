@@ -1,6 +1,76 @@
+use shared::error::{ErrorBody, ErrorCode};
+use shared::validation::ValidationErrors;
+use yew::Callback;
+use yewtil::fetch::{FetchAction, FetchError};
+
+pub mod delete_entry;
 pub mod edit_entry;
 pub mod get_entries;
 pub mod highscore;
+pub mod kinds;
 pub mod login;
+pub mod logout;
 pub mod new_entry;
 pub mod register;
+pub mod trash;
+
+pub const API_PREFIX: &str = "/api/v1";
+
+pub fn error_body(error: &FetchError) -> Option<ErrorBody> {
+    match error {
+        FetchError::ResponseError { response_body, .. } => serde_json::from_str(response_body).ok(),
+        _ => None,
+    }
+}
+
+/// Reports a token the server no longer accepts, e.g. because the session
+/// expired or was revoked, so that the user is sent back to the login.
+pub fn check_session<T>(fetch_state: &FetchAction<T>, session_expired: &Callback<()>) {
+    if let FetchAction::Failed(error) = fetch_state {
+        if error_body(error).map(|body| body.code) == Some(ErrorCode::Unauthorized) {
+            session_expired.emit(());
+        }
+    }
+}
+
+pub fn validation_errors(error: &FetchError) -> ValidationErrors {
+    ValidationErrors {
+        errors: error_body(error)
+            .map(|body| body.errors)
+            .unwrap_or_default(),
+    }
+}
+
+pub fn error_message(error: &FetchError) -> String {
+    let body = match error_body(error) {
+        Some(body) => body,
+        None => return "Der Server ist gerade nicht erreichbar.".to_owned(),
+    };
+    match body.code {
+        ErrorCode::Unauthorized => "Du bist nicht (mehr) angemeldet.".to_owned(),
+        ErrorCode::Forbidden => "Dafür fehlt dir die Berechtigung.".to_owned(),
+        ErrorCode::WrongCredentials => "Benutzername oder Passwort ist falsch.".to_owned(),
+        ErrorCode::UserExists => {
+            "Es existiert schon ein Benutzer mit dem gleichen Namen!".to_owned()
+        }
+        ErrorCode::NotFound => "Der Eintrag wurde nicht gefunden.".to_owned(),
+        ErrorCode::ValidationFailed => "Die Eingaben sind ungültig.".to_owned(),
+        ErrorCode::UnknownKind => "Diese Sportart gibt es nicht (mehr).".to_owned(),
+        ErrorCode::TooManyAttempts => match body.retry_after {
+            Some(seconds) => format!(
+                "Zu viele Fehlversuche. Bitte warte {}, bevor du es noch einmal versuchst.",
+                wait_time(seconds)
+            ),
+            None => "Zu viele Fehlversuche. Bitte versuche es später noch einmal.".to_owned(),
+        },
+        _ => format!("Unerwarteter Fehler: {}", body.message),
+    }
+}
+
+fn wait_time(seconds: u64) -> String {
+    match seconds {
+        1 => "1 Sekunde".to_owned(),
+        0..=89 => format!("{} Sekunden", seconds),
+        _ => format!("{} Minuten", (seconds + 59) / 60),
+    }
+}
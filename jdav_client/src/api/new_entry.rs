@@ -1,19 +1,30 @@
-use shared::{Kilometer, UserAuth};
+use chrono::prelude::*;
+use shared::{EntryDetails, Measurement, NewKilometerEntry, Session};
 use yewtil::fetch::{FetchRequest, Json, MethodBody};
 
+use super::API_PREFIX;
+
 #[derive(Debug, Clone, PartialEq, Default)]
 pub struct KilometerRequest {
-    pub auth: UserAuth,
-    pub payload: Kilometer,
+    pub auth: Session,
+    pub payload: NewKilometerEntry,
     pub kind: String,
 }
 
 impl KilometerRequest {
-    pub fn new(distance: f32, auth: UserAuth, kind: String) -> Self {
+    pub fn new(
+        measurement: Measurement,
+        timestamp: Option<DateTime<Utc>>,
+        details: EntryDetails,
+        auth: Session,
+        kind: String,
+    ) -> Self {
         KilometerRequest {
             auth,
-            payload: Kilometer {
-                kilometers: distance,
+            payload: NewKilometerEntry {
+                measurement,
+                timestamp,
+                details,
             },
             kind,
         }
@@ -21,22 +32,25 @@ impl KilometerRequest {
 }
 
 impl FetchRequest for KilometerRequest {
-    type RequestBody = Kilometer;
+    type RequestBody = NewKilometerEntry;
     type ResponseBody = String;
     type Format = Json;
 
     fn url(&self) -> String {
-        format!("/distanz/{}/{}", self.auth.name, self.kind)
+        format!(
+            "{}/users/{}/entries?kind={}",
+            API_PREFIX, self.auth.name, self.kind
+        )
     }
 
     fn method(&self) -> MethodBody<Self::RequestBody> {
-        MethodBody::Put(&self.payload)
+        MethodBody::Post(&self.payload)
     }
 
     fn headers(&self) -> Vec<(String, String)> {
         vec![
             ("Content-Type".to_owned(), "application/json".to_owned()),
-            ("Authorization".to_owned(), self.auth.to_basic_auth_header()),
+            ("Authorization".to_owned(), self.auth.to_bearer_header()),
         ]
     }
 
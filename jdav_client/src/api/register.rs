@@ -1,6 +1,8 @@
 use shared::UserAuth;
 use yewtil::fetch::{FetchRequest, Json, MethodBody};
 
+use super::API_PREFIX;
+
 #[derive(Debug, Clone, Default)]
 pub struct RegisterRequest {
     pub payload: UserAuth,
@@ -31,11 +33,11 @@ impl FetchRequest for RegisterRequest {
     type Format = Json;
 
     fn url(&self) -> String {
-        "/createuser".to_string()
+        format!("{}/users", API_PREFIX)
     }
 
     fn method(&self) -> MethodBody<Self::RequestBody> {
-        MethodBody::Put(&self.payload)
+        MethodBody::Post(&self.payload)
     }
 
     fn headers(&self) -> Vec<(String, String)> {
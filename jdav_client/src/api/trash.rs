@@ -0,0 +1,81 @@
+use shared::Session;
+use uuid::Uuid;
+use yewtil::fetch::{FetchRequest, Json, MethodBody};
+
+use super::API_PREFIX;
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct TrashRequest {
+    pub auth: Session,
+}
+
+impl TrashRequest {
+    pub fn new(auth: Session) -> Self {
+        TrashRequest { auth }
+    }
+}
+
+impl FetchRequest for TrashRequest {
+    type RequestBody = ();
+    type ResponseBody = shared::Trash;
+    type Format = Json;
+
+    fn url(&self) -> String {
+        format!("{}/users/{}/trash", API_PREFIX, self.auth.name)
+    }
+
+    fn method(&self) -> MethodBody<Self::RequestBody> {
+        MethodBody::Get
+    }
+
+    fn headers(&self) -> Vec<(String, String)> {
+        vec![
+            ("Content-Type".to_owned(), "application/json".to_owned()),
+            ("Authorization".to_owned(), self.auth.to_bearer_header()),
+        ]
+    }
+
+    fn use_cors(&self) -> bool {
+        true
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct RestoreEntryRequest {
+    pub auth: Session,
+    pub id: Uuid,
+}
+
+impl RestoreEntryRequest {
+    pub fn new(auth: Session, id: Uuid) -> Self {
+        RestoreEntryRequest { auth, id }
+    }
+}
+
+impl FetchRequest for RestoreEntryRequest {
+    type RequestBody = ();
+    type ResponseBody = String;
+    type Format = Json;
+
+    fn url(&self) -> String {
+        format!(
+            "{}/users/{}/trash/{}/restore",
+            API_PREFIX, self.auth.name, self.id
+        )
+    }
+
+    fn method(&self) -> MethodBody<Self::RequestBody> {
+        MethodBody::Post(&())
+    }
+
+    fn headers(&self) -> Vec<(String, String)> {
+        vec![
+            ("Content-Type".to_owned(), "application/json".to_owned()),
+            ("Authorization".to_owned(), self.auth.to_bearer_header()),
+        ]
+    }
+
+    fn use_cors(&self) -> bool {
+        true
+    }
+}
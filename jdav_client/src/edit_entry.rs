@@ -1,9 +1,14 @@
 use crate::api::edit_entry::KilometerEditRequest;
-use shared::{Kilometer, KilometerEntry, Kind, UserAuth};
+use crate::api::{check_session, error_message, validation_errors};
+use crate::new_entry::{amount_hint, amount_label, kind_options};
+use crate::validation::field_error;
+use shared::validation::{validate_entry, Field, ValidationErrors};
+use shared::{KilometerEntry, KindCatalogue, Locale, Measurement, Session, Unit};
 use yew::{html, ChangeData, Component, ComponentLink, Html, InputData, ShouldRender};
 use yew::{Callback, Properties};
-use yew_styles::forms::form_input::FormInput;
+use yew_styles::forms::form_input::{FormInput, InputType};
 use yew_styles::forms::form_select::FormSelect;
+use yew_styles::forms::form_textarea::FormTextArea;
 use yew_styles::modal::Modal;
 use yew_styles::styles::Palette;
 use yew_styles::styles::Size;
@@ -13,27 +18,35 @@ use yewtil::fetch::{Fetch, FetchAction};
 use yewtil::future::LinkFuture;
 
 pub struct EditEntry {
-    api: Fetch<KilometerEditRequest, String>,
+    api: Fetch<KilometerEditRequest, KilometerEntry>,
     link: ComponentLink<Self>,
     props: NewEntryProps,
-    parsed_distance: Option<f32>,
+    amount: String,
     kind: String,
+    errors: ValidationErrors,
+    failure: Option<String>,
 }
 
 #[derive(Clone, Properties, PartialEq)]
 pub struct NewEntryProps {
     pub entry: KilometerEntry,
-    pub auth: UserAuth,
+    pub auth: Session,
+    pub kinds: KindCatalogue,
     pub close_action: Callback<()>,
+    pub session_expired: Callback<()>,
 }
 
 #[derive(Debug)]
 pub enum Msg {
-    SetApiFetchState(FetchAction<String>),
+    SetApiFetchState(FetchAction<KilometerEntry>),
     SendEdit,
     Nothing,
-    SetDistanceField(String),
+    SetAmountField(String),
     SetKindField(String),
+    SetDurationField(String),
+    SetElevationField(String),
+    SetNoteField(String),
+    SetLocationField(String),
     CloseConfirmationModal,
 }
 
@@ -42,35 +55,50 @@ impl Component for EditEntry {
     type Properties = NewEntryProps;
 
     fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
+        let kind = props
+            .kinds
+            .get(&props.entry.kind)
+            .map(|info| info.path.clone())
+            .unwrap_or_default();
         EditEntry {
             api: Default::default(),
             link,
             props,
-            parsed_distance: None,
-            kind: Kind::Running.get_path(),
+            amount: String::new(),
+            kind,
+            errors: ValidationErrors::default(),
+            failure: None,
         }
     }
 
     fn update(&mut self, message: Self::Message) -> bool {
         match message {
             Msg::SetApiFetchState(fetch_state) => {
+                check_session(&fetch_state, &self.props.session_expired);
                 match fetch_state {
                     FetchAction::Fetched(_) => {
                         self.link.send_message(Msg::CloseConfirmationModal);
                     }
-                    FetchAction::Failed(_) => {}
+                    FetchAction::Failed(ref error) => {
+                        self.errors = validation_errors(error);
+                        self.failure =
+                            Some(error_message(error)).filter(|_| self.errors.errors.is_empty());
+                    }
                     _ => {}
                 }
                 self.api.apply(fetch_state);
                 true
             }
             Msg::SendEdit => {
-                if let Some(parsed_distance) = self.parsed_distance {
-                    self.props.entry.kilometers = Kilometer {
-                        kilometers: parsed_distance,
-                    };
-                    self.props.entry.kind =
-                        Kind::from_string(self.kind.as_str()).unwrap_or(Kind::Running);
+                let kind = self.props.kinds.by_path(&self.kind);
+                if let (Some(measurement), Some(kind)) = (self.measurement(), kind) {
+                    self.props.entry.measurement = measurement;
+                    self.props.entry.kind = kind.kind.clone();
+                    let entry = &self.props.entry;
+                    if let Err(errors) = validate_entry(kind, &entry.measurement, &entry.details) {
+                        self.errors = errors;
+                        return true;
+                    }
                     self.api.set_req(KilometerEditRequest::new(
                         self.props.auth.clone(),
                         self.props.entry.clone(),
@@ -82,14 +110,31 @@ impl Component for EditEntry {
                 false
             }
             Msg::Nothing => false,
-            Msg::SetDistanceField(value) => {
-                self.parsed_distance = value.parse::<f32>().ok();
+            Msg::SetAmountField(value) => {
+                self.amount = value;
                 true
             }
             Msg::SetKindField(value) => {
                 self.kind = value;
                 true
             }
+            Msg::SetDurationField(value) => {
+                self.props.entry.details.duration_minutes = value.trim().parse().ok();
+                true
+            }
+            Msg::SetElevationField(value) => {
+                self.props.entry.details.elevation_gain = value.trim().parse().ok();
+                true
+            }
+            Msg::SetNoteField(value) => {
+                self.props.entry.details.note = Some(value).filter(|note| !note.trim().is_empty());
+                true
+            }
+            Msg::SetLocationField(value) => {
+                self.props.entry.details.location =
+                    Some(value).filter(|location| !location.trim().is_empty());
+                true
+            }
             Msg::CloseConfirmationModal => {
                 self.props.close_action.emit(());
                 true
@@ -104,11 +149,22 @@ impl Component for EditEntry {
             ChangeData::Files(_) => Msg::Nothing,
         };
 
-        let error = if self.parsed_distance.is_none() {
-            "Muss eine gültige Zahl sein, z.B. 1.0"
+        let unit = self.unit();
+        let amount_error = if self.measurement().is_none() {
+            Some(amount_hint(unit).to_owned())
+        } else {
+            field_error(&self.errors, Field::Measurement)
+        };
+        let amount_placeholder = if self.props.entry.measurement.unit() == unit {
+            self.props.entry.measurement.localized(Locale::German)
         } else {
-            ""
+            amount_label(unit).to_owned()
         };
+        let duration_error = field_error(&self.errors, Field::DurationMinutes);
+        let elevation_error = field_error(&self.errors, Field::ElevationGain);
+        let location_error = field_error(&self.errors, Field::Location);
+        let note_error = field_error(&self.errors, Field::Note);
+        let date_error = field_error(&self.errors, Field::Timestamp);
 
         let entry = html! {
         <div class="body-content">
@@ -116,28 +172,55 @@ impl Component for EditEntry {
             <FormSelect
                 select_size=Size::Medium
                 onchange_signal = self.link.callback(select_callback)
-                options=html!{
-                    <>
-                    <option value={Kind::Running.get_path()}>{"Laufen"}</option>
-                    <option value={Kind::Biking.get_path()}>{"Radfahren"}</option>
-                    <option value={Kind::Climbing.get_path()}>{"Klettern"}</option>
-                    <option value={Kind::Hiking.get_path()}>{"Wandern"}</option>
-                    <option value={Kind::Swimming.get_path()}>{"Schwimmen"}</option>
-                    <option value={Kind::Skating.get_path()}>{"Skaten"}</option>
-                    <option value={Kind::NordicWalking.get_path()}>{"Nordic Walking"}</option>
-                    </>
-                }
+                options=kind_options(&self.props.kinds, &self.kind)
+            />
+            <FormInput
+                input_palette=Palette::Standard
+                input_size=Size::Medium
+                error_state=amount_error.is_some()
+                error_message=amount_error.clone().unwrap_or_default()
+                oninput_signal = self.link.callback(|e: InputData| Msg::SetAmountField(e.value))
+                placeholder=amount_placeholder
+                underline=false
             />
             <FormInput
+                input_type=InputType::Number
                 input_palette=Palette::Standard
                 input_size=Size::Medium
-                error_state=self.parsed_distance.is_none()
-                error_message=error
-                oninput_signal = self.link.callback(|e: InputData| Msg::SetDistanceField(e.value))
-                placeholder=self.props.entry.kilometers.kilometers.to_string()
+                error_state=duration_error.is_some()
+                error_message=duration_error.clone().unwrap_or_default()
+                oninput_signal = self.link.callback(|e: InputData| Msg::SetDurationField(e.value))
+                placeholder=self.props.entry.details.duration_minutes.map_or("Dauer (in Minuten)".to_owned(), |duration| duration.to_string())
                 underline=false
             />
+            <FormInput
+                input_type=InputType::Number
+                input_palette=Palette::Standard
+                input_size=Size::Medium
+                error_state=elevation_error.is_some()
+                error_message=elevation_error.clone().unwrap_or_default()
+                oninput_signal = self.link.callback(|e: InputData| Msg::SetElevationField(e.value))
+                placeholder=self.props.entry.details.elevation_gain.map_or("Höhenmeter".to_owned(), |elevation| elevation.to_string())
+                underline=false
+            />
+            <FormInput
+                input_palette=Palette::Standard
+                input_size=Size::Medium
+                error_state=location_error.is_some()
+                error_message=location_error.clone().unwrap_or_default()
+                oninput_signal = self.link.callback(|e: InputData| Msg::SetLocationField(e.value))
+                placeholder=self.props.entry.details.location.clone().unwrap_or_else(|| "Ort".to_owned())
+                underline=false
+            />
+            <FormTextArea
+                textarea_size=Size::Medium
+                error_state=note_error.is_some()
+                error_message=note_error.clone().unwrap_or_default()
+                oninput_signal = self.link.callback(|e: InputData| Msg::SetNoteField(e.value))
+                placeholder=self.props.entry.details.note.clone().unwrap_or_else(|| "Notiz".to_owned())
+            />
         </FormGroup>
+        {date_error.map(|error| html! { <p>{error}</p> }).unwrap_or_default()}
         <Button
             onclick_signal=self.link.callback(move |_| Msg::SendEdit )
             button_palette=Palette::Standard
@@ -178,3 +261,17 @@ impl Component for EditEntry {
         }
     }
 }
+
+impl EditEntry {
+    fn unit(&self) -> Unit {
+        self.props
+            .kinds
+            .by_path(&self.kind)
+            .map(|info| info.unit)
+            .unwrap_or_default()
+    }
+
+    fn measurement(&self) -> Option<Measurement> {
+        self.unit().parse(&self.amount)
+    }
+}
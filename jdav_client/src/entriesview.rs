@@ -1,5 +1,7 @@
 use chrono::prelude::*;
-use shared::{Entries, UserAuth};
+use shared::{Entries, EntryDetails, KindCatalogue, Locale, Session, Unit};
+use wasm_bindgen::JsCast;
+use web_sys::{Element, Event};
 use yew::{html, Component, ComponentLink, Html, ShouldRender};
 use yew::{Callback, Properties};
 use yew_styles::{
@@ -14,37 +16,56 @@ use yew_styles::{layouts::container::Wrap, styles::Palette};
 use yewtil::fetch::{Fetch, FetchAction};
 use yewtil::future::LinkFuture;
 
+use crate::api::delete_entry::DeleteEntryRequest;
+use crate::api::{check_session, error_message};
 use crate::api::get_entries::EntriesRequest;
 use crate::edit_entry::EditEntry;
+use crate::trashview::TrashView;
 
 pub struct EntriesView {
     link: ComponentLink<Self>,
     api: Fetch<EntriesRequest, Entries>,
+    delete_api: Fetch<DeleteEntryRequest, String>,
     props: EntriesProps,
     pub content: Entries,
     edit_entry_idx: usize,
     current_action: CurrentAction,
+    failure: Option<String>,
+    loading: bool,
+    /// Counts the pages requested, so that pages still arriving for a list
+    /// loaded again are dropped.
+    page_request: u32,
 }
 
 #[derive(Clone, Properties, PartialEq)]
 pub struct EntriesProps {
-    pub auth: UserAuth,
+    pub auth: Session,
+    pub kinds: KindCatalogue,
     pub close_action: Callback<()>,
+    pub session_expired: Callback<()>,
 }
 
 #[derive(Debug)]
 pub enum Msg {
-    SetApiFetchState(FetchAction<Entries>),
+    SetApiFetchState(u32, FetchAction<Entries>),
     OpenEditEntry(usize),
     CloseEditEntry,
+    AskDeleteEntry(usize),
+    DeleteEntry,
+    SetDeleteFetchState(FetchAction<String>),
+    OpenTrash,
+    CloseSubView,
     Nothing,
     CloseModal,
     InitList,
+    LoadMore,
 }
 
 enum CurrentAction {
     Nothing,
     EditEntry,
+    ConfirmDelete,
+    Trash,
 }
 
 impl Component for EntriesView {
@@ -56,9 +77,13 @@ impl Component for EntriesView {
             link,
             props,
             api: Default::default(),
+            delete_api: Default::default(),
             content: Default::default(),
             edit_entry_idx: 0,
             current_action: CurrentAction::Nothing,
+            failure: None,
+            loading: false,
+            page_request: 0,
         };
         model.link.send_message(Msg::InitList);
         model
@@ -71,10 +96,19 @@ impl Component for EntriesView {
                 self.props.close_action.emit(());
                 false
             }
-            Msg::SetApiFetchState(fetch_state) => {
+            Msg::SetApiFetchState(page_request, _) if page_request != self.page_request => false,
+            Msg::SetApiFetchState(_, fetch_state) => {
+                check_session(&fetch_state, &self.props.session_expired);
                 match fetch_state {
-                    FetchAction::Fetched(ref response) => self.content = response.clone(),
-                    FetchAction::Failed(_) => {}
+                    FetchAction::Fetched(ref response) => {
+                        self.content.list.extend(response.list.iter().cloned());
+                        self.content.next_cursor = response.next_cursor.clone();
+                        self.loading = false;
+                    }
+                    FetchAction::Failed(ref error) => {
+                        self.failure = Some(error_message(error));
+                        self.loading = false;
+                    }
                     _ => {}
                 }
                 self.api.apply(fetch_state);
@@ -85,46 +119,85 @@ impl Component for EntriesView {
                 self.edit_entry_idx = entry;
                 true
             }
-            Msg::CloseEditEntry => {
+            Msg::CloseEditEntry | Msg::CloseSubView => {
                 self.current_action = CurrentAction::Nothing;
                 self.link.send_message(Msg::InitList);
                 true
             }
+            Msg::AskDeleteEntry(entry) => {
+                self.current_action = CurrentAction::ConfirmDelete;
+                self.failure = None;
+                self.edit_entry_idx = entry;
+                true
+            }
+            Msg::DeleteEntry => {
+                if let Some(entry) = self.content.list.get(self.edit_entry_idx) {
+                    self.delete_api.set_req(DeleteEntryRequest::new(
+                        self.props.auth.clone(),
+                        entry.id.id,
+                    ));
+                    self.link
+                        .send_future(self.delete_api.fetch(Msg::SetDeleteFetchState));
+                    self.link
+                        .send_message(Msg::SetDeleteFetchState(FetchAction::Fetching));
+                }
+                false
+            }
+            Msg::SetDeleteFetchState(fetch_state) => {
+                check_session(&fetch_state, &self.props.session_expired);
+                match fetch_state {
+                    FetchAction::Fetched(_) => {
+                        self.link.send_message(Msg::CloseSubView);
+                    }
+                    FetchAction::Failed(ref error) => {
+                        self.failure = Some(error_message(error));
+                        self.link.send_message(Msg::CloseSubView);
+                    }
+                    _ => {}
+                }
+                self.delete_api.apply(fetch_state);
+                true
+            }
+            Msg::OpenTrash => {
+                self.current_action = CurrentAction::Trash;
+                true
+            }
             Msg::InitList => {
-                self.api
-                    .set_req(EntriesRequest::new(self.props.auth.clone()));
-                self.link.send_future(self.api.fetch(Msg::SetApiFetchState));
-                self.link
-                    .send_message(Msg::SetApiFetchState(FetchAction::Fetching));
+                self.content = Entries::default();
+                self.fetch_page(None);
+                false
+            }
+            Msg::LoadMore => {
+                if let Some(cursor) = self.content.next_cursor.clone().filter(|_| !self.loading) {
+                    self.fetch_page(Some(cursor));
+                }
                 false
             }
         }
     }
 
     fn view(&self) -> Html {
-        let entries = self.content.list.iter().enumerate().rev().map(|(idx, item)| {
-            let card_pal = match item.kind {
-                shared::Kind::Biking => Palette::Primary,
-                shared::Kind::Climbing => Palette::Success,
-                shared::Kind::Running => Palette::Info,
-                shared::Kind::Hiking => Palette::Clean,
-                shared::Kind::Skating => Palette::Danger,
-                shared::Kind::Swimming => Palette::Success,
-                shared::Kind::NordicWalking => Palette::Secondary,
-            };
+        let entries = self.content.list.iter().enumerate().map(|(idx, item)| {
+            let color = self
+                .props
+                .kinds
+                .get(&item.kind)
+                .map(|info| info.color.clone())
+                .unwrap_or_default();
             html! {
                 <Item layouts=vec!(ItemLayout::ItM(3))>
                     <Card
                         card_size=Size::Small
-                        card_palette=card_pal
+                        card_palette=Palette::Clean
                         card_style=Style::Outline
-                        header=html!{<b>{item.kind.clone()}</b>}
+                        header=html!{<b style=format!("color: {}", color)>{self.props.kinds.name(&item.kind)}</b>}
                         body=html!{
                             <div>
                                 {item.timestamp.with_timezone(&FixedOffset::east(2*3600)).format("Vom: %d.%m.%y, %H:%M").to_string()}
                                 <br/>
-                                {format!("Distanz: {}", item.kilometers.clone())}
+                                {format!("Menge: {}", item.measurement.localized(Locale::German))}
                                 <br/>
+                                {details_view(&item.details)}
                                 <Button
                                     onclick_signal=self.link.callback(move |_| Msg::OpenEditEntry(idx) )
                                     button_palette=Palette::Standard
@@ -132,6 +205,13 @@ impl Component for EntriesView {
                                 >
                                 {"Bearbeiten"}
                                 </Button>
+                                <Button
+                                    onclick_signal=self.link.callback(move |_| Msg::AskDeleteEntry(idx) )
+                                    button_palette=Palette::Danger
+                                    button_style=Style::Outline
+                                >
+                                {"Löschen"}
+                                </Button>
                             </div>
                         }
                     />
@@ -139,14 +219,60 @@ impl Component for EntriesView {
             }
         });
 
+        let onscroll = self.link.callback(|event: Event| {
+            match event
+                .target()
+                .and_then(|target| target.dyn_into::<Element>().ok())
+            {
+                Some(list)
+                    if list.scroll_top() + list.client_height() + 100 >= list.scroll_height() =>
+                {
+                    Msg::LoadMore
+                }
+                _ => Msg::Nothing,
+            }
+        });
+        let legend = self.props.kinds.active().map(|info| {
+            html! {
+                <span class="legend-item">
+                    <span style=format!("color: {}", info.color)>{"● "}</span>
+                    {info.name.clone()}
+                </span>
+            }
+        });
+        let load_more = if self.content.next_cursor.is_some() {
+            html! {
+                <Button
+                    onclick_signal=self.link.callback(move |_| Msg::LoadMore )
+                    button_palette=Palette::Standard
+                    button_style=Style::Outline
+                >
+                {"Mehr laden"}
+                </Button>
+            }
+        } else {
+            html! {}
+        };
         let entries_table = html! {
-            <Container direction=Direction::Row wrap=Wrap::Wrap class_name="scrolllist">
-                {entries.collect::<Html>()}
-            </Container>
+            <div class="scrolllist" onscroll=onscroll>
+                <Container direction=Direction::Row wrap=Wrap::Wrap>
+                    {entries.collect::<Html>()}
+                </Container>
+                {load_more}
+            </div>
         };
         let entry = html! {
         <div class="body-content">
+            { self.failure.as_ref().map(|failure| html! { <div>{failure}</div> }).unwrap_or_default() }
+            <div class="legend">{legend.collect::<Html>()}</div>
             {entries_table}
+            <Button
+                onclick_signal=self.link.callback(move |_| Msg::OpenTrash )
+                button_palette=Palette::Standard
+                button_style=Style::Outline
+            >
+            {"Papierkorb"}
+            </Button>
             <Button
                 onclick_signal=self.link.callback(move |_| Msg::CloseModal )
                 button_palette=Palette::Standard
@@ -159,6 +285,25 @@ impl Component for EntriesView {
 
         let close_action = self.link.callback(|_| Msg::CloseEditEntry);
 
+        let confirm_delete = html! {
+        <div class="body-content">
+            <Button
+                onclick_signal=self.link.callback(move |_| Msg::DeleteEntry )
+                button_palette=Palette::Danger
+                button_style=Style::Outline
+            >
+            {"Löschen"}
+            </Button>
+            <Button
+                onclick_signal=self.link.callback(move |_| Msg::CloseSubView )
+                button_palette=Palette::Standard
+                button_style=Style::Outline
+            >
+            {"Abbrechen"}
+            </Button>
+        </div>
+        };
+
         match self.current_action {
             CurrentAction::Nothing => {
                 html! {
@@ -183,8 +328,37 @@ impl Component for EntriesView {
                 html! {
                     <EditEntry
                         auth = {self.props.auth.clone()}
+                        kinds = {self.props.kinds.clone()}
                         entry = {e}
                         close_action={close_action}
+                        session_expired={self.props.session_expired.clone()}
+                    />
+                }
+            }
+            CurrentAction::ConfirmDelete => {
+                html! {
+                    <Modal
+                    header=html!{
+                        <b>{"Eintrag wirklich löschen?"}</b>
+                    }
+                    header_palette=Palette::Danger
+                    body=confirm_delete
+                    body_style=Style::Outline
+                    body_palette=Palette::Danger
+                    is_open=true
+                    onclick_signal= self.link.callback(|_|  Msg::Nothing )
+                    onkeydown_signal= self.link.callback(|_|  Msg::Nothing)
+                    auto_focus=false
+                    class_name = "bg"
+                />
+                }
+            }
+            CurrentAction::Trash => {
+                html! {
+                    <TrashView
+                        auth = {self.props.auth.clone()}
+                        close_action={self.link.callback(|_| Msg::CloseSubView)}
+                        session_expired={self.props.session_expired.clone()}
                     />
                 }
             }
@@ -200,3 +374,43 @@ impl Component for EntriesView {
         }
     }
 }
+
+impl EntriesView {
+    fn fetch_page(&mut self, cursor: Option<String>) {
+        self.loading = true;
+        self.page_request += 1;
+        let page_request = self.page_request;
+        self.api
+            .set_req(EntriesRequest::new(self.props.auth.clone(), cursor));
+        self.link.send_future(
+            self.api
+                .fetch(move |fetch_state| Msg::SetApiFetchState(page_request, fetch_state)),
+        );
+        self.link
+            .send_message(Msg::SetApiFetchState(page_request, FetchAction::Fetching));
+    }
+}
+
+fn details_view(details: &EntryDetails) -> Html {
+    let lines = vec![
+        details
+            .duration_minutes
+            .map(|duration| format!("Dauer: {}:{:02} h", duration / 60, duration % 60)),
+        details.elevation_gain.map(|elevation| {
+            format!(
+                "Höhenmeter: {} m",
+                elevation.localized(Unit::Elevation.decimals(), Locale::German)
+            )
+        }),
+        details
+            .location
+            .clone()
+            .map(|location| format!("Ort: {}", location)),
+        details.note.clone(),
+    ];
+    lines
+        .into_iter()
+        .flatten()
+        .map(|line| html! { <>{line}<br/></> })
+        .collect::<Html>()
+}
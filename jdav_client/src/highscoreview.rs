@@ -1,6 +1,8 @@
-use shared::{Highscore, UserAuth};
-use yew::{html, Component, ComponentLink, Html, ShouldRender};
+use shared::{Highscore, HighscoreQuery, HighscoreWindow, KindCatalogue, Locale, Session};
+use yew::{html, ChangeData, Component, ComponentLink, Html, ShouldRender};
 use yew::{Callback, Properties};
+use yew_styles::forms::form_select::FormSelect;
+use yew_styles::styles::Size;
 use yew_styles::{
     button::Button,
     layouts::item::{AlignSelf, Item, ItemLayout},
@@ -11,19 +13,27 @@ use yew_styles::{layouts::container::Wrap, styles::Palette};
 use yewtil::fetch::{Fetch, FetchAction};
 use yewtil::future::LinkFuture;
 
+use crate::api::{check_session, error_message};
 use crate::api::highscore::HighscoreRequest;
 
+const POINTS_DECIMALS: usize = 1;
+
 pub struct HighscoreView {
     link: ComponentLink<Self>,
     api: Fetch<HighscoreRequest, Highscore>,
     props: HighscoreProps,
+    failure: Option<String>,
+    window: HighscoreWindow,
+    kind: Option<String>,
     pub content: Highscore,
 }
 
 #[derive(Clone, Properties, PartialEq)]
 pub struct HighscoreProps {
-    pub auth: UserAuth,
+    pub auth: Session,
+    pub kinds: KindCatalogue,
     pub close_action: Callback<()>,
+    pub session_expired: Callback<()>,
 }
 
 #[derive(Debug)]
@@ -32,6 +42,8 @@ pub enum Msg {
     Nothing,
     CloseModal,
     InitList,
+    SelectWindow(HighscoreWindow),
+    SelectKind(String),
 }
 
 impl Component for HighscoreView {
@@ -44,6 +56,9 @@ impl Component for HighscoreView {
             props,
             api: Default::default(),
             content: Default::default(),
+            failure: None,
+            window: HighscoreWindow::All,
+            kind: None,
         };
         model.link.send_message(Msg::InitList);
         model
@@ -57,26 +72,52 @@ impl Component for HighscoreView {
                 false
             }
             Msg::SetApiFetchState(fetch_state) => {
+                check_session(&fetch_state, &self.props.session_expired);
                 match fetch_state {
                     FetchAction::Fetched(ref response) => self.content = response.clone(),
-                    FetchAction::Failed(_) => {}
+                    FetchAction::Failed(ref error) => self.failure = Some(error_message(error)),
                     _ => {}
                 }
                 self.api.apply(fetch_state);
                 true
             }
             Msg::InitList => {
+                let query = HighscoreQuery {
+                    window: self.window,
+                    kind: self.kind.clone(),
+                    ..Default::default()
+                };
                 self.api
-                    .set_req(HighscoreRequest::new(self.props.auth.clone()));
+                    .set_req(HighscoreRequest::new(self.props.auth.clone(), query));
                 self.link.send_future(self.api.fetch(Msg::SetApiFetchState));
                 self.link
                     .send_message(Msg::SetApiFetchState(FetchAction::Fetching));
                 false
             }
+            Msg::SelectWindow(window) => {
+                if self.window == window {
+                    return false;
+                }
+                self.window = window;
+                self.failure = None;
+                self.link.send_message(Msg::InitList);
+                true
+            }
+            Msg::SelectKind(value) => {
+                self.kind = Some(value).filter(|path| !path.is_empty());
+                self.failure = None;
+                self.link.send_message(Msg::InitList);
+                true
+            }
         }
     }
 
     fn view(&self) -> Html {
+        let unit = self
+            .kind
+            .as_ref()
+            .and_then(|path| self.props.kinds.by_path(path))
+            .map(|info| info.unit);
         let entries = self.content.list.iter().enumerate().map(|(pos, item)| {
             html! {
                 <Container direction=Direction::Row wrap=Wrap::Wrap class_name="align-item">
@@ -87,7 +128,14 @@ impl Component for HighscoreView {
                     {item.user.clone()}
                 </Item>
                 <Item layouts=vec!(ItemLayout::ItXs(4)) align_self=AlignSelf::FlexStart>
-                    {item.points.clone()}
+                    {match unit {
+                        Some(unit) => format!(
+                            "{} {}",
+                            item.amount.localized(unit.decimals(), Locale::German),
+                            unit.symbol()
+                        ),
+                        None => item.points.localized(POINTS_DECIMALS, Locale::German),
+                    }}
                 </Item>
                 </Container>
             }
@@ -103,14 +151,58 @@ impl Component for HighscoreView {
                         {"Name"}
                     </Item>
                     <Item layouts=vec!(ItemLayout::ItXs(4)) align_self=AlignSelf::FlexStart>
-                        {"Punktzahl"}
+                        {if unit.is_some() { "Menge" } else { "Punktzahl" }}
                     </Item>
                 </Container>
                 {entries.collect::<Html>()}
             </Container>
         };
+        let tabs = [
+            (HighscoreWindow::All, "Gesamt"),
+            (HighscoreWindow::Month, "Monat"),
+            (HighscoreWindow::Week, "Woche"),
+        ]
+        .iter()
+        .map(|(window, label)| {
+            let window = *window;
+            html! {
+                <Button
+                    onclick_signal=self.link.callback(move |_| Msg::SelectWindow(window))
+                    button_palette=if self.window == window { Palette::Primary } else { Palette::Standard }
+                    button_style=Style::Outline
+                >
+                {label}
+                </Button>
+            }
+        })
+        .collect::<Html>();
+
+        let select_callback = |e: ChangeData| match e {
+            ChangeData::Select(v) => Msg::SelectKind(v.value()),
+            _ => Msg::Nothing,
+        };
+        let kinds = self
+            .props
+            .kinds
+            .kinds
+            .iter()
+            .map(|info| html! { <option value={info.path.clone()}>{info.name.clone()}</option> })
+            .collect::<Html>();
+
         let entry = html! {
         <div class="body-content">
+            <div class="tabs">{tabs}</div>
+            <FormSelect
+                select_size=Size::Medium
+                onchange_signal=self.link.callback(select_callback)
+                options=html!{
+                    <>
+                    <option value="">{"Alle Sportarten (Punkte)"}</option>
+                    {kinds}
+                    </>
+                }
+            />
+            { self.failure.as_ref().map(|failure| html! { <div>{failure}</div> }).unwrap_or_default() }
             {highscore_table}
             <Button
                 onclick_signal=self.link.callback(move |_| Msg::CloseModal )
@@ -1,4 +1,4 @@
-use enum_iterator::IntoEnumIterator;
+use shared::{KindCatalogue, Locale};
 use yew::{html, Component, ComponentLink, Html, ShouldRender};
 use yew::{Callback, Properties};
 use yew_styles::{
@@ -16,6 +16,7 @@ pub struct InfoView {
 
 #[derive(Clone, Properties, PartialEq)]
 pub struct InfoProps {
+    pub kinds: KindCatalogue,
     pub close_action: Callback<()>,
 }
 
@@ -44,14 +45,15 @@ impl Component for InfoView {
     }
 
     fn view(&self) -> Html {
-        let entries = shared::Kind::into_enum_iter().map(|item| {
+        let entries = self.props.kinds.active().map(|item| {
             html! {
                 <Container direction=Direction::Row wrap=Wrap::Wrap class_name="align-item">
                 <Item layouts=vec!(ItemLayout::ItXs(4)) align_self=AlignSelf::FlexStart>
-                    {item.to_string()}
+                    <span style=format!("color: {}", item.color)>{"● "}</span>
+                    {item.name.clone()}
                 </Item>
                 <Item layouts=vec!(ItemLayout::ItXs(4)) align_self=AlignSelf::FlexStart>
-                    {item.get_kind_multiplier().to_string()}
+                    {format!("{} / {}", item.multiplier.localized(2, Locale::German), item.unit.symbol())}
                 </Item>
                 </Container>
             }
@@ -64,7 +66,7 @@ impl Component for InfoView {
                         {"Sportart"}
                     </Item>
                     <Item layouts=vec!(ItemLayout::ItXs(4)) align_self=AlignSelf::FlexStart>
-                        {"Multiplikator"}
+                        {"Punkte pro Einheit"}
                     </Item>
                 </Container>
                 {entries.collect::<Html>()}
@@ -1,8 +1,10 @@
-#![recursion_limit = "512"]
+#![recursion_limit = "1024"]
 
+use api::error_message;
 use api::login::LoginRequest;
+use api::logout::LogoutRequest;
 use overview::OverviewRoute;
-use shared::UserAuth;
+use shared::{Session, UserAuth};
 use web_sys::MouseEvent;
 use yew::{html, Component, ComponentLink, Html, ShouldRender};
 use yew::{services::ConsoleService, InputData};
@@ -33,21 +35,27 @@ mod infoview;
 pub mod new_entry;
 pub mod overview;
 pub mod register;
+pub mod trashview;
+pub mod validation;
 
 enum Msg {
     StartLogin,
     SetUserField(String),
     SetPasswordField(String),
     Nothing,
-    SetApiFetchState(FetchAction<String>),
+    SetApiFetchState(FetchAction<Session>),
     FinalizeLogin,
+    Logout,
+    SetLogoutFetchState(FetchAction<String>),
+    SessionExpired,
     Navigate(MainRoute),
 }
 
 #[derive(Clone, Default)]
 struct AppState {
-    logged_in: bool,
+    session: Option<Session>,
     user_auth: UserAuth,
+    login_failure: Option<String>,
 }
 
 #[derive(Switch, Clone, Debug, PartialEq)]
@@ -64,7 +72,8 @@ pub enum MainRoute {
 
 struct Model {
     link: ComponentLink<Self>,
-    api: Fetch<LoginRequest, String>,
+    api: Fetch<LoginRequest, Session>,
+    logout_api: Fetch<LogoutRequest, String>,
     state: AppState,
     router: RouteAgentDispatcher<()>,
 }
@@ -78,6 +87,7 @@ impl Component for Model {
 
         Model {
             api: Default::default(),
+            logout_api: Default::default(),
             link,
             router,
             state: Default::default(),
@@ -95,10 +105,11 @@ impl Component for Model {
                 true
             }
             Msg::StartLogin => {
-                self.api.set_req(LoginRequest {
+                // sent from a request of its own, so the password is not kept
+                let login = Fetch::new(LoginRequest {
                     payload: self.state.user_auth.clone(),
                 });
-                self.link.send_future(self.api.fetch(Msg::SetApiFetchState));
+                self.link.send_future(login.fetch(Msg::SetApiFetchState));
                 self.link
                     .send_message(Msg::SetApiFetchState(FetchAction::Fetching));
                 false
@@ -106,10 +117,14 @@ impl Component for Model {
             Msg::Nothing => false,
             Msg::SetApiFetchState(fetch_state) => {
                 match fetch_state {
-                    FetchAction::Fetched(_) => {
+                    FetchAction::Fetched(ref session) => {
+                        self.state.session = Some(session.clone());
+                        self.state.user_auth.pass.clear();
+                        self.state.login_failure = None;
                         self.link.send_message(Msg::FinalizeLogin);
                     }
-                    FetchAction::Failed(_) => {
+                    FetchAction::Failed(ref error) => {
+                        self.state.login_failure = Some(error_message(error));
                         self.link
                             .send_message(Msg::Navigate(MainRoute::LoginFailed));
                     }
@@ -120,11 +135,30 @@ impl Component for Model {
                 true
             }
             Msg::FinalizeLogin => {
-                self.state.logged_in = true;
                 self.link
                     .send_message(Msg::Navigate(MainRoute::Overview(OverviewRoute::Overview)));
                 true
             }
+            Msg::Logout => {
+                if let Some(session) = self.state.session.take() {
+                    self.logout_api.set_req(LogoutRequest::new(session));
+                    self.link
+                        .send_future(self.logout_api.fetch(Msg::SetLogoutFetchState));
+                }
+                self.link.send_message(Msg::Navigate(MainRoute::Login));
+                true
+            }
+            Msg::SessionExpired => {
+                // The server already rejected the token, so there is nothing
+                // left to revoke.
+                self.state.session = None;
+                self.link.send_message(Msg::Navigate(MainRoute::Login));
+                true
+            }
+            Msg::SetLogoutFetchState(fetch_state) => {
+                self.logout_api.apply(fetch_state);
+                false
+            }
             Msg::Navigate(ref location) => {
                 self.router
                     .send(RouteRequest::ChangeRoute(location.clone().into()));
@@ -145,6 +179,8 @@ impl Component for Model {
         });
 
         let navigate_to = self.link.callback(Msg::Navigate);
+        let logout = self.link.callback(|_| Msg::Logout);
+        let session_expired = self.link.callback(|_| Msg::SessionExpired);
 
         let entry = html! {
         <div class="body-content">
@@ -207,12 +243,15 @@ impl Component for Model {
         };
 
         let login_failed_body = html! {
+            <>
+            <div>{self.state.login_failure.clone().unwrap_or_default()}</div>
             <Button
                 onclick_signal=goto_main_page
                 button_palette=Palette::Standard
                 button_style=Style::Outline
             >{"Schade..."}
             </Button>
+            </>
         };
 
         let login_failed_modal = html! {
@@ -234,8 +273,7 @@ impl Component for Model {
 
         let close_action = self.link.callback(move |_| Msg::Navigate(MainRoute::Login));
 
-        let user_auth = self.state.user_auth.clone();
-        let logged_in = self.state.logged_in;
+        let session = self.state.session.clone();
 
         html! {
             <Router<MainRoute>
@@ -248,12 +286,11 @@ impl Component for Model {
                         }
                         MainRoute::Overview(overview_route) => {
                             let overview_route = overview_route.clone();
-                            if logged_in {
-                                html! {
-                                    <Overview auth={user_auth.clone()} navigate={navigate_to.clone()} route={overview_route}/>
-                                }
-                            } else {
-                                login_failed_modal.clone()
+                            match &session {
+                                Some(session) => html! {
+                                    <Overview auth={session.clone()} navigate={navigate_to.clone()} logout={logout.clone()} session_expired={session_expired.clone()} route={overview_route}/>
+                                },
+                                None => login_failed_modal.clone(),
                             }
 
                         }
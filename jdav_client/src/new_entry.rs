@@ -1,10 +1,14 @@
 use crate::api::new_entry::KilometerRequest;
-use shared::Kind;
-use shared::UserAuth;
+use crate::api::{check_session, error_message, validation_errors};
+use crate::validation::field_error;
+use chrono::prelude::*;
+use shared::validation::{validate_entry, Field, ValidationErrors};
+use shared::{EntryDetails, KindCatalogue, Measurement, Session, Unit};
 use yew::{html, ChangeData, Component, ComponentLink, Html, InputData, ShouldRender};
 use yew::{Callback, Properties};
-use yew_styles::forms::form_input::FormInput;
+use yew_styles::forms::form_input::{FormInput, InputType};
 use yew_styles::forms::form_select::FormSelect;
+use yew_styles::forms::form_textarea::FormTextArea;
 use yew_styles::modal::Modal;
 use yew_styles::styles::Palette;
 use yew_styles::styles::Size;
@@ -18,13 +22,19 @@ pub struct NewEntry {
     link: ComponentLink<Self>,
     kind: String,
     props: NewEntryProps,
-    parsed_distance: Option<f32>,
+    amount: String,
+    activity_date: Option<NaiveDate>,
+    details: EntryDetails,
+    errors: ValidationErrors,
+    failure: Option<String>,
 }
 
 #[derive(Clone, Properties, PartialEq)]
 pub struct NewEntryProps {
-    pub auth: UserAuth,
+    pub auth: Session,
+    pub kinds: KindCatalogue,
     pub close_action: Callback<()>,
+    pub session_expired: Callback<()>,
 }
 
 #[derive(Debug)]
@@ -32,8 +42,13 @@ pub enum Msg {
     SetApiFetchState(FetchAction<String>),
     PutDistance,
     Nothing,
-    SetDistanceField(String),
+    SetAmountField(String),
     SetKindField(String),
+    SetDateField(String),
+    SetDurationField(String),
+    SetElevationField(String),
+    SetNoteField(String),
+    SetLocationField(String),
     CloseConfirmationModal,
 }
 
@@ -45,29 +60,45 @@ impl Component for NewEntry {
         NewEntry {
             api: Default::default(),
             link,
-            kind: "laufen".to_owned(),
+            kind: first_kind(&props.kinds),
             props,
-            parsed_distance: None,
+            amount: String::new(),
+            activity_date: None,
+            details: EntryDetails::default(),
+            errors: ValidationErrors::default(),
+            failure: None,
         }
     }
 
     fn update(&mut self, message: Self::Message) -> bool {
         match message {
             Msg::SetApiFetchState(fetch_state) => {
+                check_session(&fetch_state, &self.props.session_expired);
                 match fetch_state {
                     FetchAction::Fetched(_) => {
                         self.link.send_message(Msg::CloseConfirmationModal);
                     }
-                    FetchAction::Failed(_) => {}
+                    FetchAction::Failed(ref error) => {
+                        self.errors = validation_errors(error);
+                        self.failure =
+                            Some(error_message(error)).filter(|_| self.errors.errors.is_empty());
+                    }
                     _ => {}
                 }
                 self.api.apply(fetch_state);
                 true
             }
             Msg::PutDistance => {
-                if let Some(parsed_distance) = self.parsed_distance {
+                let kind = self.props.kinds.by_path(&self.kind);
+                if let (Some(measurement), Some(kind)) = (self.measurement(), kind) {
+                    if let Err(errors) = validate_entry(kind, &measurement, &self.details) {
+                        self.errors = errors;
+                        return true;
+                    }
                     self.api.set_req(KilometerRequest::new(
-                        parsed_distance,
+                        measurement,
+                        self.activity_timestamp(),
+                        self.details.clone(),
                         self.props.auth.clone(),
                         self.kind.clone(),
                     ));
@@ -78,14 +109,34 @@ impl Component for NewEntry {
                 false
             }
             Msg::Nothing => false,
-            Msg::SetDistanceField(value) => {
-                self.parsed_distance = value.parse::<f32>().ok();
+            Msg::SetAmountField(value) => {
+                self.amount = value;
                 true
             }
             Msg::SetKindField(value) => {
                 self.kind = value;
                 true
             }
+            Msg::SetDateField(value) => {
+                self.activity_date = NaiveDate::parse_from_str(&value, "%Y-%m-%d").ok();
+                true
+            }
+            Msg::SetDurationField(value) => {
+                self.details.duration_minutes = value.trim().parse().ok();
+                true
+            }
+            Msg::SetElevationField(value) => {
+                self.details.elevation_gain = value.trim().parse().ok();
+                true
+            }
+            Msg::SetNoteField(value) => {
+                self.details.note = Some(value).filter(|note| !note.trim().is_empty());
+                true
+            }
+            Msg::SetLocationField(value) => {
+                self.details.location = Some(value).filter(|location| !location.trim().is_empty());
+                true
+            }
             Msg::CloseConfirmationModal => {
                 self.props.close_action.emit(());
                 true
@@ -100,11 +151,17 @@ impl Component for NewEntry {
             ChangeData::Files(_) => Msg::Nothing,
         };
 
-        let error = if self.parsed_distance.is_none() {
-            "Muss eine gültige Zahl sein, z.B. 1.0 (mit Punkt!)"
+        let unit = self.unit();
+        let amount_error = if self.measurement().is_none() {
+            Some(amount_hint(unit).to_owned())
         } else {
-            ""
+            field_error(&self.errors, Field::Measurement)
         };
+        let date_error = field_error(&self.errors, Field::Timestamp);
+        let duration_error = field_error(&self.errors, Field::DurationMinutes);
+        let elevation_error = field_error(&self.errors, Field::ElevationGain);
+        let location_error = field_error(&self.errors, Field::Location);
+        let note_error = field_error(&self.errors, Field::Note);
 
         let entry = html! {
         <div class="body-content">
@@ -112,28 +169,65 @@ impl Component for NewEntry {
             <FormSelect
                 select_size=Size::Medium
                 onchange_signal = self.link.callback(select_callback)
-                options=html!{
-                    <>
-                    <option value={Kind::Running.get_path()}>{"Laufen"}</option>
-                    <option value={Kind::Biking.get_path()}>{"Radfahren"}</option>
-                    <option value={Kind::Climbing.get_path()}>{"Klettern"}</option>
-                    <option value={Kind::Hiking.get_path()}>{"Wandern"}</option>
-                    <option value={Kind::Swimming.get_path()}>{"Schwimmen"}</option>
-                    <option value={Kind::Skating.get_path()}>{"Skaten"}</option>
-                    <option value={Kind::NordicWalking.get_path()}>{"Nordic Walking"}</option>
-                    </>
-                }
+                options=kind_options(&self.props.kinds, &self.kind)
+            />
+            <FormInput
+                input_palette=Palette::Standard
+                input_size=Size::Medium
+                error_state=amount_error.is_some()
+                error_message=amount_error.clone().unwrap_or_default()
+                oninput_signal = self.link.callback(|e: InputData| Msg::SetAmountField(e.value))
+                placeholder=amount_label(unit)
+                underline=false
+            />
+            <FormInput
+                input_type=InputType::Date
+                input_palette=Palette::Standard
+                input_size=Size::Medium
+                error_state=date_error.is_some()
+                error_message=date_error.clone().unwrap_or_default()
+                oninput_signal = self.link.callback(|e: InputData| Msg::SetDateField(e.value))
+                placeholder="Datum (leer für heute)"
+                underline=false
+            />
+            <FormInput
+                input_type=InputType::Number
+                input_palette=Palette::Standard
+                input_size=Size::Medium
+                error_state=duration_error.is_some()
+                error_message=duration_error.clone().unwrap_or_default()
+                oninput_signal = self.link.callback(|e: InputData| Msg::SetDurationField(e.value))
+                placeholder="Dauer (in Minuten, optional)"
+                underline=false
             />
             <FormInput
+                input_type=InputType::Number
                 input_palette=Palette::Standard
                 input_size=Size::Medium
-                error_state=self.parsed_distance.is_none()
-                error_message=error
-                oninput_signal = self.link.callback(|e: InputData| Msg::SetDistanceField(e.value))
-                placeholder="Distanz (in km)"
+                error_state=elevation_error.is_some()
+                error_message=elevation_error.clone().unwrap_or_default()
+                oninput_signal = self.link.callback(|e: InputData| Msg::SetElevationField(e.value))
+                placeholder="Höhenmeter (optional)"
                 underline=false
             />
+            <FormInput
+                input_palette=Palette::Standard
+                input_size=Size::Medium
+                error_state=location_error.is_some()
+                error_message=location_error.clone().unwrap_or_default()
+                oninput_signal = self.link.callback(|e: InputData| Msg::SetLocationField(e.value))
+                placeholder="Ort (optional)"
+                underline=false
+            />
+            <FormTextArea
+                textarea_size=Size::Medium
+                error_state=note_error.is_some()
+                error_message=note_error.clone().unwrap_or_default()
+                oninput_signal = self.link.callback(|e: InputData| Msg::SetNoteField(e.value))
+                placeholder="Notiz (optional)"
+            />
         </FormGroup>
+        { self.failure.as_ref().map(|failure| html! { <div>{failure}</div> }).unwrap_or_default() }
         <Button
             onclick_signal=self.link.callback(move |_| Msg::PutDistance )
             button_palette=Palette::Standard
@@ -167,6 +261,9 @@ impl Component for NewEntry {
 
     fn change(&mut self, props: Self::Properties) -> ShouldRender {
         if self.props != props {
+            if self.props.kinds.by_path(&self.kind).is_none() {
+                self.kind = first_kind(&props.kinds);
+            }
             self.props = props;
             true
         } else {
@@ -174,3 +271,69 @@ impl Component for NewEntry {
         }
     }
 }
+
+impl NewEntry {
+    fn unit(&self) -> Unit {
+        self.props
+            .kinds
+            .by_path(&self.kind)
+            .map(|info| info.unit)
+            .unwrap_or_default()
+    }
+
+    fn measurement(&self) -> Option<Measurement> {
+        self.unit().parse(&self.amount)
+    }
+
+    fn activity_timestamp(&self) -> Option<DateTime<Utc>> {
+        let offset = FixedOffset::east(2 * 3600);
+        let today = Utc::now().with_timezone(&offset).date().naive_local();
+        self.activity_date
+            .filter(|date| *date != today)
+            .and_then(|date| offset.from_local_date(&date).single())
+            .map(|date| date.and_hms(12, 0, 0).with_timezone(&Utc))
+    }
+}
+
+fn first_kind(kinds: &KindCatalogue) -> String {
+    kinds
+        .active()
+        .next()
+        .map(|info| info.path.clone())
+        .unwrap_or_default()
+}
+
+pub fn amount_label(unit: Unit) -> &'static str {
+    match unit {
+        Unit::Distance => "Distanz (in km)",
+        Unit::Elevation => "Höhenmeter (in Hm)",
+        Unit::Duration => "Dauer (in Minuten)",
+        Unit::Count => "Anzahl (z.B. Routen)",
+    }
+}
+
+pub fn amount_hint(unit: Unit) -> &'static str {
+    match unit {
+        Unit::Distance | Unit::Elevation => {
+            "Muss eine Zahl mit höchstens drei Nachkommastellen sein, z.B. 1,5"
+        }
+        Unit::Duration | Unit::Count => "Muss eine ganze Zahl sein, z.B. 12",
+    }
+}
+
+/// The options of a kind dropdown: all active kinds, and the selected one even
+/// when it has been retired.
+pub fn kind_options(kinds: &KindCatalogue, selected: &str) -> Html {
+    kinds
+        .kinds
+        .iter()
+        .filter(|info| !info.retired || info.path == selected)
+        .map(|info| {
+            html! {
+                <option value={info.path.clone()} selected={info.path == selected}>
+                    {info.name.clone()}
+                </option>
+            }
+        })
+        .collect::<Html>()
+}
@@ -1,7 +1,10 @@
-use shared::UserAuth;
+use shared::{KindCatalogue, KindInfo, Session};
 use yew::{html, Component, ComponentLink, Html, ShouldRender};
 use yew::{Callback, Properties};
+use yewtil::fetch::{Fetch, FetchAction};
+use yewtil::future::LinkFuture;
 
+use crate::api::kinds::KindsRequest;
 use crate::highscoreview::HighscoreView;
 use crate::infoview::InfoView;
 use crate::new_entry::NewEntry;
@@ -34,12 +37,16 @@ impl Into<MainRoute> for OverviewRoute {
 
 #[derive(Clone, Properties, PartialEq)]
 pub struct OverviewProps {
-    pub auth: UserAuth,
+    pub auth: Session,
     pub route: OverviewRoute,
     pub navigate: Callback<MainRoute>,
+    pub logout: Callback<()>,
+    pub session_expired: Callback<()>,
 }
 pub enum Msg {
     Navigate(MainRoute),
+    Logout,
+    SetKindsFetchState(FetchAction<Vec<KindInfo>>),
     Nothing,
 }
 
@@ -55,6 +62,8 @@ pub struct Overview {
     link: ComponentLink<Self>,
     props: OverviewProps,
     current_action: CurrentAction,
+    kinds_api: Fetch<KindsRequest, Vec<KindInfo>>,
+    kinds: KindCatalogue,
 }
 
 impl Component for Overview {
@@ -62,11 +71,18 @@ impl Component for Overview {
     type Properties = OverviewProps;
 
     fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
-        Overview {
+        let mut model = Overview {
             link,
             props,
             current_action: CurrentAction::Nothing,
-        }
+            kinds_api: Default::default(),
+            kinds: KindCatalogue { kinds: Vec::new() },
+        };
+        model.kinds_api.set_req(KindsRequest);
+        model
+            .link
+            .send_future(model.kinds_api.fetch(Msg::SetKindsFetchState));
+        model
     }
 
     fn update(&mut self, message: Self::Message) -> bool {
@@ -75,6 +91,19 @@ impl Component for Overview {
                 self.props.navigate.emit(location.into());
                 false
             }
+            Msg::Logout => {
+                self.props.logout.emit(());
+                false
+            }
+            Msg::SetKindsFetchState(fetch_state) => {
+                if let FetchAction::Fetched(ref kinds) = fetch_state {
+                    self.kinds = KindCatalogue {
+                        kinds: kinds.clone(),
+                    };
+                }
+                self.kinds_api.apply(fetch_state);
+                true
+            }
             Msg::Nothing => false,
         }
     }
@@ -117,6 +146,13 @@ impl Component for Overview {
             body=html!{<h1>{"Highscore"}</h1>}
             onclick_signal={navigate_to(OverviewRoute::HighScore.into())}
         />
+        <Card
+            card_size=Size::Small
+            card_palette=Palette::Danger
+            card_style=Style::Outline
+            body=html!{<h1>{"Abmelden"}</h1>}
+            onclick_signal={self.link.callback(|_| Msg::Logout)}
+        />
 
         </div>
         };
@@ -145,7 +181,9 @@ impl Component for Overview {
                     <NewEntry
                       auth={
                           self.props.auth.clone()}
+                      kinds={self.kinds.clone()}
                       close_action={close_action}
+                      session_expired={self.props.session_expired.clone()}
                     />
                 }
             }
@@ -153,7 +191,9 @@ impl Component for Overview {
                 html! {
                     <HighscoreView
                       auth={self.props.auth.clone()}
+                      kinds={self.kinds.clone()}
                       close_action={close_action}
+                      session_expired={self.props.session_expired.clone()}
                     />
                 }
             }
@@ -161,13 +201,16 @@ impl Component for Overview {
                 html! {
                     <EntriesView
                       auth={self.props.auth.clone()}
+                      kinds={self.kinds.clone()}
                       close_action={close_action}
+                      session_expired={self.props.session_expired.clone()}
                     />
                 }
             }
             OverviewRoute::Info => {
                 html! {
                     <InfoView
+                        kinds={self.kinds.clone()}
                         close_action={close_action}
                     />
                 }
@@ -1,3 +1,4 @@
+use crate::api::error_message;
 use crate::api::register::RegisterRequest;
 use yew::{html, Component, ComponentLink, Html, InputData, ShouldRender};
 use yew::{Callback, Properties};
@@ -18,7 +19,7 @@ pub struct Register {
     username: String,
     password: String,
     password_confirmation: String,
-    register_failed: bool,
+    register_failed: Option<String>,
 }
 
 #[derive(Clone, Properties, PartialEq)]
@@ -35,7 +36,7 @@ pub enum Msg {
     SetPasswordField(String),
     SetPasswordConfirmationField(String),
     CloseModal,
-    RegisterFailed,
+    RegisterFailed(String),
     CloseRegisterFailedModal,
 }
 
@@ -51,7 +52,7 @@ impl Component for Register {
             username: Default::default(),
             password: Default::default(),
             password_confirmation: Default::default(),
-            register_failed: false,
+            register_failed: None,
         }
     }
 
@@ -62,8 +63,9 @@ impl Component for Register {
                     FetchAction::Fetched(_) => {
                         self.link.send_message(Msg::CloseModal);
                     }
-                    FetchAction::Failed(_) => {
-                        self.link.send_message(Msg::RegisterFailed);
+                    FetchAction::Failed(ref error) => {
+                        self.link
+                            .send_message(Msg::RegisterFailed(error_message(error)));
                     }
                     _ => {}
                 }
@@ -98,12 +100,12 @@ impl Component for Register {
                 self.props.close_action.emit(());
                 true
             }
-            Msg::RegisterFailed => {
-                self.register_failed = true;
+            Msg::RegisterFailed(message) => {
+                self.register_failed = Some(message);
                 true
             }
             Msg::CloseRegisterFailedModal => {
-                self.register_failed = false;
+                self.register_failed = None;
                 self.username = "".to_string();
                 self.password = "".to_string();
                 self.password_confirmation = "".to_string();
@@ -173,7 +175,7 @@ impl Component for Register {
 
         let register_failed_entry = html! {
             <>
-            <div>{"Wahrscheinlich existiert schon ein Benutzer mit dem gleichen Namen!"}</div>
+            <div>{self.register_failed.clone().unwrap_or_default()}</div>
             <Button
                 onclick_signal=self.link.callback(move |_| Msg::CloseRegisterFailedModal )
                 button_palette=Palette::Standard
@@ -183,7 +185,7 @@ impl Component for Register {
             </>
         };
 
-        if self.register_failed {
+        if self.register_failed.is_some() {
             html! {
             <Modal
                 header=html!{
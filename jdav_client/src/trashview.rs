@@ -0,0 +1,190 @@
+use chrono::prelude::*;
+use shared::{Locale, Session, Trash};
+use yew::{html, Component, ComponentLink, Html, ShouldRender};
+use yew::{Callback, Properties};
+use yew_styles::{
+    button::Button,
+    card::Card,
+    layouts::item::{Item, ItemLayout},
+    styles::Size,
+};
+use yew_styles::{layouts::container::Container, styles::Style};
+use yew_styles::{layouts::container::Direction, modal::Modal};
+use yew_styles::{layouts::container::Wrap, styles::Palette};
+use yewtil::fetch::{Fetch, FetchAction};
+use yewtil::future::LinkFuture;
+
+use crate::api::{check_session, error_message};
+use crate::api::trash::{RestoreEntryRequest, TrashRequest};
+
+pub struct TrashView {
+    link: ComponentLink<Self>,
+    api: Fetch<TrashRequest, Trash>,
+    restore_api: Fetch<RestoreEntryRequest, String>,
+    props: TrashProps,
+    failure: Option<String>,
+    pub content: Trash,
+}
+
+#[derive(Clone, Properties, PartialEq)]
+pub struct TrashProps {
+    pub auth: Session,
+    pub close_action: Callback<()>,
+    pub session_expired: Callback<()>,
+}
+
+#[derive(Debug)]
+pub enum Msg {
+    SetApiFetchState(FetchAction<Trash>),
+    SetRestoreFetchState(FetchAction<String>),
+    Restore(usize),
+    Nothing,
+    CloseModal,
+    InitList,
+}
+
+impl Component for TrashView {
+    type Message = Msg;
+    type Properties = TrashProps;
+
+    fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
+        let model = TrashView {
+            link,
+            props,
+            api: Default::default(),
+            restore_api: Default::default(),
+            content: Default::default(),
+            failure: None,
+        };
+        model.link.send_message(Msg::InitList);
+        model
+    }
+
+    fn update(&mut self, message: Self::Message) -> bool {
+        match message {
+            Msg::Nothing => false,
+            Msg::CloseModal => {
+                self.props.close_action.emit(());
+                false
+            }
+            Msg::SetApiFetchState(fetch_state) => {
+                check_session(&fetch_state, &self.props.session_expired);
+                match fetch_state {
+                    FetchAction::Fetched(ref response) => self.content = response.clone(),
+                    FetchAction::Failed(ref error) => self.failure = Some(error_message(error)),
+                    _ => {}
+                }
+                self.api.apply(fetch_state);
+                true
+            }
+            Msg::SetRestoreFetchState(fetch_state) => {
+                check_session(&fetch_state, &self.props.session_expired);
+                match fetch_state {
+                    FetchAction::Fetched(_) => self.link.send_message(Msg::InitList),
+                    FetchAction::Failed(ref error) => self.failure = Some(error_message(error)),
+                    _ => {}
+                }
+                self.restore_api.apply(fetch_state);
+                true
+            }
+            Msg::Restore(idx) => {
+                if let Some(trashed) = self.content.list.get(idx) {
+                    self.restore_api.set_req(RestoreEntryRequest::new(
+                        self.props.auth.clone(),
+                        trashed.entry.id.id,
+                    ));
+                    self.link
+                        .send_future(self.restore_api.fetch(Msg::SetRestoreFetchState));
+                    self.link
+                        .send_message(Msg::SetRestoreFetchState(FetchAction::Fetching));
+                }
+                false
+            }
+            Msg::InitList => {
+                self.api.set_req(TrashRequest::new(self.props.auth.clone()));
+                self.link.send_future(self.api.fetch(Msg::SetApiFetchState));
+                self.link
+                    .send_message(Msg::SetApiFetchState(FetchAction::Fetching));
+                false
+            }
+        }
+    }
+
+    fn view(&self) -> Html {
+        let entries = self.content.list.iter().enumerate().rev().map(|(idx, trashed)| {
+            let item = &trashed.entry;
+            html! {
+                <Item layouts=vec!(ItemLayout::ItM(3))>
+                    <Card
+                        card_size=Size::Small
+                        card_palette=Palette::Clean
+                        card_style=Style::Outline
+                        header=html!{<b>{item.kind.clone()}</b>}
+                        body=html!{
+                            <div>
+                                {item.timestamp.with_timezone(&FixedOffset::east(2*3600)).format("Vom: %d.%m.%y, %H:%M").to_string()}
+                                <br/>
+                                {format!("Menge: {}", item.measurement.localized(Locale::German))}
+                                <br/>
+                                {trashed.deleted_at.with_timezone(&FixedOffset::east(2*3600)).format("Gelöscht: %d.%m.%y, %H:%M").to_string()}
+                                <br/>
+                                <Button
+                                    onclick_signal=self.link.callback(move |_| Msg::Restore(idx) )
+                                    button_palette=Palette::Standard
+                                    button_style=Style::Outline
+                                >
+                                {"Wiederherstellen"}
+                                </Button>
+                            </div>
+                        }
+                    />
+               </Item>
+            }
+        });
+
+        let entries_table = html! {
+            <Container direction=Direction::Row wrap=Wrap::Wrap class_name="scrolllist">
+                {entries.collect::<Html>()}
+            </Container>
+        };
+        let entry = html! {
+        <div class="body-content">
+            { self.failure.as_ref().map(|failure| html! { <div>{failure}</div> }).unwrap_or_default() }
+            {entries_table}
+            <Button
+                onclick_signal=self.link.callback(move |_| Msg::CloseModal )
+                button_palette=Palette::Standard
+                button_style=Style::Outline
+            >
+            {"Schließen"}
+            </Button>
+        </div>
+        };
+
+        html! {
+            <Modal
+                header=html!{
+                    <b>{"Papierkorb"}</b>
+                }
+                header_palette=Palette::Link
+                body=entry
+                body_style=Style::Outline
+                body_palette=Palette::Link
+                is_open=true
+                onclick_signal= self.link.callback(|_|  Msg::Nothing )
+                onkeydown_signal= self.link.callback(|_|  Msg::Nothing)
+                auto_focus=false
+                class_name = "bg"
+            />
+        }
+    }
+
+    fn change(&mut self, props: Self::Properties) -> ShouldRender {
+        if self.props != props {
+            self.props = props;
+            true
+        } else {
+            false
+        }
+    }
+}
@@ -0,0 +1,33 @@
+use shared::validation::{Field, Problem, ValidationErrors};
+use shared::Unit;
+
+pub fn problem_message(problem: &Problem) -> String {
+    match problem {
+        Problem::NotANumber => "Muss eine gültige Zahl sein".to_owned(),
+        Problem::NotPositive => "Muss größer als 0 sein".to_owned(),
+        Problem::Negative => "Darf nicht negativ sein".to_owned(),
+        Problem::TooLarge { max } => format!("Darf höchstens {} sein", max),
+        Problem::TooLong { max } => format!("Darf höchstens {} Zeichen lang sein", max),
+        Problem::InFuture => "Darf nicht in der Zukunft liegen".to_owned(),
+        Problem::BeforeChallenge => "Liegt vor dem Beginn der Challenge".to_owned(),
+        Problem::AfterChallenge => "Liegt nach dem Ende der Challenge".to_owned(),
+        Problem::WrongUnit { expected } => {
+            format!("Muss in {} angegeben werden", unit_name(*expected))
+        }
+    }
+}
+
+fn unit_name(unit: Unit) -> &'static str {
+    match unit {
+        Unit::Distance => "Kilometern",
+        Unit::Elevation => "Höhenmetern",
+        Unit::Duration => "Minuten",
+        Unit::Count => "Anzahl",
+    }
+}
+
+pub fn field_error(errors: &ValidationErrors, field: Field) -> Option<String> {
+    errors
+        .for_field(field)
+        .map(|error| problem_message(&error.problem))
+}
@@ -0,0 +1,74 @@
+use chrono::prelude::*;
+use shared::validation::{Field, Problem, ValidationError};
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct ChallengePeriod {
+    pub start: Option<DateTime<Utc>>,
+    pub end: Option<DateTime<Utc>>,
+}
+
+impl ChallengePeriod {
+    pub fn from_dates(start: Option<NaiveDate>, end: Option<NaiveDate>) -> Self {
+        ChallengePeriod {
+            start: start.map(|start| Utc.from_utc_date(&start).and_hms(0, 0, 0)),
+            end: end.map(|end| Utc.from_utc_date(&end.succ()).and_hms(0, 0, 0)),
+        }
+    }
+
+    pub fn check(
+        &self,
+        activity: DateTime<Utc>,
+        now: DateTime<Utc>,
+    ) -> Result<(), ValidationError> {
+        let problem = if activity > now {
+            Problem::InFuture
+        } else if self.start.is_some_and(|start| activity < start) {
+            Problem::BeforeChallenge
+        } else if self.end.is_some_and(|end| activity >= end) {
+            Problem::AfterChallenge
+        } else {
+            return Ok(());
+        };
+        Err(ValidationError {
+            field: Field::Timestamp,
+            problem,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::prelude::*;
+    use shared::validation::Problem;
+
+    use super::ChallengePeriod;
+
+    #[test]
+    pub fn checks_activity_dates() {
+        let period = ChallengePeriod::from_dates(
+            Some(NaiveDate::from_ymd(2021, 5, 1)),
+            Some(NaiveDate::from_ymd(2021, 5, 31)),
+        );
+        let now = Utc.ymd(2021, 5, 20).and_hms(12, 0, 0);
+        let problem = |activity, now| period.check(activity, now).map_err(|error| error.problem);
+
+        assert_eq!(problem(Utc.ymd(2021, 5, 1).and_hms(0, 0, 0), now), Ok(()));
+        assert_eq!(
+            problem(Utc.ymd(2021, 5, 21).and_hms(0, 0, 0), now),
+            Err(Problem::InFuture)
+        );
+        assert_eq!(
+            problem(Utc.ymd(2021, 4, 30).and_hms(23, 59, 59), now),
+            Err(Problem::BeforeChallenge)
+        );
+        let later = Utc.ymd(2021, 7, 1).and_hms(0, 0, 0);
+        assert_eq!(
+            problem(Utc.ymd(2021, 5, 31).and_hms(23, 0, 0), later),
+            Ok(())
+        );
+        assert_eq!(
+            problem(Utc.ymd(2021, 6, 1).and_hms(0, 0, 0), later),
+            Err(Problem::AfterChallenge)
+        );
+    }
+}
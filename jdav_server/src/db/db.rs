@@ -1,174 +1,512 @@
 use chrono::prelude::*;
-use rand::prelude::*;
-use serde_json::to_writer;
-use sha2::{Digest, Sha256};
-use shared::{Entries, Highscore, HighscoreEntry, Kilometer, UserAuth};
+use shared::validation::{validate_entry, ValidationErrors};
+use shared::{
+    Decimal, Entries, Highscore, HighscoreEntry, KindCatalogue, KindInfo, NewKilometerEntry,
+    ScoringRules, Trash, UserAuth, UserStats,
+};
+use std::cmp::Reverse;
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use tokio::fs::File;
-use tokio::sync::RwLock;
 use uuid::Uuid;
 
-use crate::models::{DatabaseModel, User};
+use crate::challenge::ChallengePeriod;
+use crate::error::ApiError;
+use crate::highscore::HighscoreFilter;
+use crate::kinds::KindStore;
+use crate::models::{EntryDatabaseModel, UserKey};
+use crate::pagination::EntryFilter;
+use crate::password;
+use crate::sessions::SessionStore;
+use crate::stats;
+use crate::throttle::Throttle;
 use shared::{Id, KilometerEntry};
 
-pub static DATABASE_FILENAME: &str = "./database.json";
+use super::event_log::EventLogStorage;
+use super::json::{JsonStorage, DATABASE_FILENAME};
+use super::persistence::BackupConfig;
+use super::sqlite::{SqliteStorage, SQLITE_FILENAME};
+use super::storage::{Storage, StorageError, StorageKind};
 
 #[derive(Clone)]
 pub struct Database {
-    pub database: Arc<RwLock<DatabaseModel>>,
+    storage: Arc<dyn Storage>,
+    challenge: ChallengePeriod,
+    scoring: Arc<ScoringRules>,
+    kinds: Arc<KindStore>,
+    sessions: Arc<SessionStore>,
+    throttle: Arc<Throttle>,
+    admins: Arc<Vec<String>>,
 }
 
 impl Database {
-    pub async fn create_user(&self, new_user: UserAuth) -> bool {
-        let mut db = self.database.write().await;
+    pub fn new<S: Storage + 'static>(
+        storage: S,
+        challenge: ChallengePeriod,
+        scoring: ScoringRules,
+        kinds: KindStore,
+        sessions: SessionStore,
+        throttle: Throttle,
+        admins: Vec<String>,
+    ) -> Self {
+        Database {
+            storage: Arc::new(storage),
+            challenge,
+            scoring: Arc::new(scoring),
+            kinds: Arc::new(kinds),
+            sessions: Arc::new(sessions),
+            throttle: Arc::new(throttle),
+            admins: Arc::new(admins),
+        }
+    }
 
-        let mut salt_bytes: [u8; 8] = [0; 8];
-        rand::thread_rng().fill_bytes(&mut salt_bytes);
-        let salt = base64::encode(salt_bytes);
+    pub fn scoring(&self) -> &ScoringRules {
+        &self.scoring
+    }
 
-        let mut hasher = Sha256::new();
-        hasher.update(new_user.pass + &salt);
-        let hash = hasher.finalize();
-        let hash_b64 = base64::encode(hash);
+    pub fn kinds(&self) -> &KindStore {
+        &self.kinds
+    }
 
-        let user = User {
-            hash: hash_b64,
-            salt,
-        };
+    pub fn sessions(&self) -> &SessionStore {
+        &self.sessions
+    }
 
-        if !db.users.contains_key(&new_user.name) {
-            db.users.insert(new_user.name, user);
-            self.save_database(&db).await;
-            return true;
-        }
-        false
+    pub fn throttle(&self) -> &Throttle {
+        &self.throttle
+    }
+
+    pub fn is_admin(&self, user: &str) -> bool {
+        self.admins.iter().any(|admin| admin == user)
     }
 
-    pub async fn authenticate_user(&self, user_auth: &UserAuth) -> bool {
-        let db = self.database.read().await;
+    /// Stores a new user, returning `false` when the name is taken.
+    pub async fn create_user(&self, new_user: UserAuth) -> Result<bool, ApiError> {
+        let pass = new_user.pass;
+        let user =
+            tokio::task::spawn_blocking(move || password::hash(&pass, password::CURRENT_PARAMS))
+                .await
+                .map_err(|error| ApiError::Internal(error.to_string()))?
+                .map_err(|error| {
+                    ApiError::Internal(format!("could not hash password: {}", error))
+                })?;
+
+        Ok(self.storage.insert_user(new_user.name, user).await?)
+    }
 
+    /// Checks the password and rehashes it when it was stored with older
+    /// parameters, e.g. as SHA-256. Unknown users are checked against a dummy
+    /// hash, so that the time taken does not tell whether a user exists.
+    pub async fn authenticate_user(&self, user_auth: &UserAuth) -> Result<bool, ApiError> {
         if user_auth.name.is_empty() {
-            return false;
+            return Ok(false);
         }
+        let user = self.storage.get_user(&user_auth.name).await?;
 
-        if db.users.contains_key(&user_auth.name) {
-            let user = db.users.get(&user_auth.name).unwrap();
-
-            let mut hasher = Sha256::new();
-            hasher.update(user_auth.pass.clone() + &user.salt);
-            let hash = base64::encode(hasher.finalize());
-            if hash == user.hash {
-                return true;
+        let pass = user_auth.pass.clone();
+        let (valid, rehashed) = tokio::task::spawn_blocking(move || match user {
+            Some(user) => {
+                let valid = password::verify(&pass, &user);
+                let rehashed = (valid && user.params != password::CURRENT_PARAMS)
+                    .then(|| password::hash(&pass, password::CURRENT_PARAMS));
+                (valid, rehashed)
             }
+            None => (password::reject_unknown(&pass), None),
+        })
+        .await
+        .map_err(|error| ApiError::Internal(error.to_string()))?;
+
+        if let Some(rehashed) = rehashed {
+            let user = rehashed.map_err(|error| {
+                ApiError::Internal(format!("could not rehash password: {}", error))
+            })?;
+            self.storage.update_user(&user_auth.name, user).await?;
         }
-        false
+        Ok(valid)
     }
 
     pub async fn create_kilometer_entry(
         &self,
-        kilometer: Kilometer,
+        new_entry: NewKilometerEntry,
         user: String,
-        kind: shared::Kind,
-    ) -> Uuid {
-        let mut db = self.database.write().await;
+        kind: &KindInfo,
+    ) -> Result<Uuid, ApiError> {
+        let now = Utc::now();
+        let timestamp = new_entry.timestamp.unwrap_or(now);
+
         let new_id = Uuid::new_v4();
-        let new_entry: KilometerEntry = KilometerEntry {
+        let mut new_entry: KilometerEntry = KilometerEntry {
             id: Id { id: new_id },
-            kilometers: kilometer,
-            kind,
-            timestamp: Utc::now(),
+            measurement: new_entry.measurement,
+            kind: kind.kind.clone(),
+            timestamp,
+            recorded_at: now,
+            points: Decimal::ZERO,
+            details: new_entry.details,
         };
+        self.validate(&new_entry, kind, now, None)?;
+        new_entry.points = self.scoring.points_of_kind(&new_entry, kind);
 
-        let entries_for_user = db.entries.get_mut(&user);
-        match entries_for_user {
-            Some(entries_for_user) => {
-                entries_for_user.push(new_entry);
-            }
-            None => {
-                let mut map = Vec::new();
-                map.push(new_entry);
-                db.entries.insert(user, map);
+        self.storage.insert_entry(&user, new_entry).await?;
+        Ok(new_id)
+    }
+
+    /// Checks the entry, leaving out the challenge period when an edited entry
+    /// keeps its date, so that entries from before the challenge, or migrated
+    /// ones without a date, can still be corrected.
+    fn validate(
+        &self,
+        entry: &KilometerEntry,
+        kind: &KindInfo,
+        now: DateTime<Utc>,
+        previous_timestamp: Option<DateTime<Utc>>,
+    ) -> Result<(), ValidationErrors> {
+        let mut errors = match validate_entry(kind, &entry.measurement, &entry.details) {
+            Ok(()) => ValidationErrors::default(),
+            Err(errors) => errors,
+        };
+        if previous_timestamp != Some(entry.timestamp) {
+            if let Err(error) = self.challenge.check(entry.timestamp, now) {
+                errors.errors.push(error);
             }
         }
-        self.save_database(&db).await;
-        new_id
+        if errors.errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    pub async fn get_kilometer_entry(&self, id: Uuid) -> Option<(UserKey, KilometerEntry)> {
+        self.storage.get_entry(&Id { id }).await
     }
 
+    /// Stores the changed entry and returns it with its new score, or `None`
+    /// when the user has no such entry.
     pub async fn edit_kilometer_entry(
         &self,
         user: String,
-        new_kilometer_entry: KilometerEntry,
-    ) -> bool {
-        let mut db = self.database.write().await;
-
-        let entries = db.entries.get_mut(&user).unwrap();
-        for entry in entries.iter_mut() {
-            if entry.id == new_kilometer_entry.id {
-                *entry = new_kilometer_entry;
-                self.save_database(&db).await;
-                return true;
+        mut new_kilometer_entry: KilometerEntry,
+        kind: &KindInfo,
+    ) -> Result<Option<KilometerEntry>, ApiError> {
+        let previous_timestamp = self
+            .storage
+            .get_entry(&new_kilometer_entry.id)
+            .await
+            .map(|(_, entry)| entry.timestamp);
+        self.validate(&new_kilometer_entry, kind, Utc::now(), previous_timestamp)?;
+        new_kilometer_entry.points = self.scoring.points_of_kind(&new_kilometer_entry, kind);
+        let updated = self
+            .storage
+            .update_entry(&user, new_kilometer_entry.clone())
+            .await?;
+        Ok(Some(new_kilometer_entry).filter(|_| updated))
+    }
+
+    pub async fn delete_kilometer_entry(&self, user: String, id: Uuid) -> Result<bool, ApiError> {
+        Ok(self
+            .storage
+            .delete_entry(&user, &Id { id }, Utc::now())
+            .await?)
+    }
+
+    pub async fn restore_kilometer_entry(&self, user: String, id: Uuid) -> Result<bool, ApiError> {
+        if !self.storage.restore_entry(&user, &Id { id }).await? {
+            return Ok(false);
+        }
+        if let Some((_, entry)) = self.storage.get_entry(&Id { id }).await {
+            let catalogue = self.kinds.catalogue().await;
+            self.score_unscored_entry(&user, entry, &catalogue).await?;
+        }
+        Ok(true)
+    }
+
+    pub async fn get_trash_for_user(&self, user: String) -> Trash {
+        Trash {
+            list: self.storage.get_trash(&user).await,
+        }
+    }
+
+    pub async fn purge_trash(&self, before: DateTime<Utc>) -> Result<usize, StorageError> {
+        self.storage.purge_trash(before).await
+    }
+
+    pub async fn get_entries_for_user(&self, user: String, filter: &EntryFilter) -> Entries {
+        filter.apply(self.storage.get_entries(&user).await)
+    }
+
+    pub async fn get_stats_for_user(&self, user: String) -> UserStats {
+        let entries = self.storage.get_entries(&user).await;
+        stats::user_stats(&entries, Utc::now().date().naive_utc())
+    }
+
+    pub async fn get_highscore(&self, filter: &HighscoreFilter) -> Highscore {
+        self.storage.get_highscore(filter).await
+    }
+
+    /// Scores the entries that have no points yet, i.e. those stored before
+    /// points were kept per entry, and returns how many of them changed.
+    pub async fn score_unscored(&self) -> Result<usize, StorageError> {
+        let catalogue = self.kinds.catalogue().await;
+        let mut scored = 0;
+        for (user, entries) in self.storage.get_all_entries().await {
+            for entry in entries {
+                if self.score_unscored_entry(&user, entry, &catalogue).await? {
+                    scored += 1;
+                }
             }
         }
-        false
+        Ok(scored)
     }
 
-    pub async fn get_entries_for_user(&self, user: String) -> Entries {
-        let db = self.database.read().await;
-        Entries {
-            list: db.entries.get(&user).unwrap_or(&Vec::new()).clone(),
+    async fn score_unscored_entry(
+        &self,
+        user: &str,
+        mut entry: KilometerEntry,
+        catalogue: &KindCatalogue,
+    ) -> Result<bool, StorageError> {
+        if entry.points != Decimal::ZERO {
+            return Ok(false);
         }
+        entry.points = self.scoring.points(&entry, catalogue);
+        if entry.points == Decimal::ZERO {
+            return Ok(false);
+        }
+        self.storage.update_entry(user, entry).await
     }
 
-    async fn save_database(&self, db: &DatabaseModel) {
-        let file = File::create(DATABASE_FILENAME).await;
-        if let Ok(json) = file {
-            to_writer(json.into_std().await, &db.clone()).expect("error writing to file");
+    /// Scores all entries again with the configured rules and returns how
+    /// many of them changed.
+    pub async fn rescore(&self, dry_run: bool) -> Result<usize, StorageError> {
+        let catalogue = self.kinds.catalogue().await;
+        let mut changed = 0;
+        for (user, entries) in self.storage.get_all_entries().await {
+            for mut entry in entries {
+                let points = self.scoring.points(&entry, &catalogue);
+                if entry.points == points {
+                    continue;
+                }
+                if dry_run {
+                    changed += 1;
+                    continue;
+                }
+                entry.points = points;
+                if self.storage.update_entry(&user, entry).await? {
+                    changed += 1;
+                }
+            }
         }
+        Ok(changed)
     }
 
-    pub async fn get_highscore(&self) -> Highscore {
-        let db = self.database.read().await;
-        get_highscore(&db)
+    pub async fn flush(&self) -> Result<(), StorageError> {
+        self.storage.flush().await
     }
 }
 
-impl Default for Database {
-    fn default() -> Self {
-        Database {
-            database: Arc::new(RwLock::new(DatabaseModel::default())),
+#[derive(Debug)]
+pub enum LoadError {
+    Missing {
+        path: PathBuf,
+    },
+    Unreadable {
+        path: PathBuf,
+        source: io::Error,
+    },
+    Corrupt {
+        path: PathBuf,
+        line: usize,
+        column: usize,
+        message: String,
+        quarantined: Option<PathBuf>,
+    },
+    Migration {
+        path: PathBuf,
+        message: String,
+    },
+    /// Valid JSON that does not match the database model.
+    Invalid {
+        path: PathBuf,
+        message: String,
+    },
+    Sqlite {
+        path: PathBuf,
+        source: rusqlite::Error,
+    },
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoadError::Missing { path } => write!(
+                f,
+                "{} does not exist, pass --init-empty to start with an empty database",
+                path.display()
+            ),
+            LoadError::Unreadable { path, source } => {
+                write!(f, "could not read {}: {}", path.display(), source)
+            }
+            LoadError::Corrupt {
+                path,
+                line,
+                column,
+                message,
+                quarantined,
+            } => {
+                write!(
+                    f,
+                    "{} is corrupt at line {}, column {}: {}",
+                    path.display(),
+                    line,
+                    column,
+                    message
+                )?;
+                if let Some(quarantined) = quarantined {
+                    write!(f, " (moved to {})", quarantined.display())?;
+                }
+                Ok(())
+            }
+            LoadError::Migration { path, message } => {
+                write!(f, "could not migrate {}: {}", path.display(), message)
+            }
+            LoadError::Invalid { path, message } => write!(
+                f,
+                "{} does not match the database format: {}",
+                path.display(),
+                message
+            ),
+            LoadError::Sqlite { path, source } => {
+                write!(f, "could not open {}: {}", path.display(), source)
+            }
         }
     }
 }
 
-pub async fn init_db() -> Database {
-    crate::db::migration::migrate().await.unwrap_or_default()
+pub struct DatabaseConfig {
+    pub storage: StorageKind,
+    pub backups: BackupConfig,
+    pub event_log_directory: PathBuf,
+    pub snapshot_every: u64,
+    pub init_empty: bool,
+    pub challenge: ChallengePeriod,
+    pub scoring: ScoringRules,
+    pub kinds: KindStore,
+    pub sessions: SessionStore,
+    pub throttle: Throttle,
+    pub admins: Vec<String>,
+}
+
+pub async fn init_db(config: DatabaseConfig) -> Result<Database, LoadError> {
+    match config.storage {
+        StorageKind::Json => {
+            let path = Path::new(DATABASE_FILENAME);
+            let model = match crate::db::migration::migrate(path, &config.backups).await {
+                Err(LoadError::Missing { .. }) if config.init_empty => Default::default(),
+                model => model?,
+            };
+            Ok(Database::new(
+                JsonStorage::new(model, PathBuf::from(DATABASE_FILENAME), config.backups),
+                config.challenge,
+                config.scoring,
+                config.kinds,
+                config.sessions,
+                config.throttle,
+                config.admins,
+            ))
+        }
+        StorageKind::Sqlite => {
+            let path = PathBuf::from(SQLITE_FILENAME);
+            if !config.init_empty && !path.exists() {
+                return Err(LoadError::Missing { path });
+            }
+            let storage =
+                SqliteStorage::open(&path).map_err(|source| LoadError::Sqlite { path, source })?;
+            Ok(Database::new(
+                storage,
+                config.challenge,
+                config.scoring,
+                config.kinds,
+                config.sessions,
+                config.throttle,
+                config.admins,
+            ))
+        }
+        StorageKind::EventLog => {
+            let storage = EventLogStorage::open(
+                config.event_log_directory,
+                config.snapshot_every,
+                config.init_empty,
+            )
+            .await?;
+            Ok(Database::new(
+                storage,
+                config.challenge,
+                config.scoring,
+                config.kinds,
+                config.sessions,
+                config.throttle,
+                config.admins,
+            ))
+        }
+    }
 }
 
-fn get_highscore(database: &DatabaseModel) -> Highscore {
-    let mut list = database
-        .entries
+pub fn get_highscore(entries: &EntryDatabaseModel, filter: &HighscoreFilter) -> Highscore {
+    let mut list = entries
         .iter()
-        .map(|(key, value)| HighscoreEntry {
-            user: key.clone(),
-            points: value.iter().fold(0.0, |acc, entry: &KilometerEntry| {
-                acc + entry.kilometers.kilometers * entry.kind.get_kind_multiplier()
-            }),
+        .filter_map(|(key, value)| {
+            let matching = value
+                .iter()
+                .filter(|entry| filter.matches(entry))
+                .collect::<Vec<_>>();
+            if matching.is_empty() {
+                return None;
+            }
+            Some(HighscoreEntry {
+                user: key.clone(),
+                points: matching.iter().map(|entry| entry.points).sum(),
+                kilometers: matching
+                    .iter()
+                    .map(|entry| entry.measurement.kilometers())
+                    .sum(),
+                amount: matching.iter().map(|entry| entry.measurement.value()).sum(),
+            })
         })
         .collect::<Vec<_>>();
-    list.sort_by(|entry1, entry2| entry2.points.partial_cmp(&entry1.points).unwrap());
+    if filter.kind().is_some() {
+        list.sort_by_key(|entry| Reverse(entry.amount));
+    } else {
+        list.sort_by_key(|entry| Reverse(entry.points));
+    }
     Highscore { list }
 }
 
 #[cfg(test)]
 mod test {
-    use shared::Kilometer;
+    use shared::Measurement;
     use uuid::Uuid;
 
-    use super::get_highscore;
+    use std::path::Path;
+    use std::sync::Arc;
+
+    use super::{get_highscore, Database};
+    use crate::challenge::ChallengePeriod;
+    use crate::db::json::JsonStorage;
+    use crate::db::migration::run_migrations;
+    use crate::db::persistence::BackupConfig;
+    use crate::error::ApiError;
+    use crate::highscore::HighscoreFilter;
+    use crate::kinds::KindStore;
     use crate::models::DatabaseModel;
-    use shared::{Id, KilometerEntry};
+    use crate::sessions::SessionStore;
+    use crate::test_util::TestDirectory;
+    use crate::throttle::{SystemClock, Throttle};
+    use shared::validation::Problem;
+    use shared::{Decimal, HighscoreQuery, Id, KilometerEntry, Kind, KindCatalogue, ScoringRules};
 
     use chrono::prelude::*;
+    use chrono::Duration;
 
     #[test]
     pub fn can_process_one_kilometer_entry() {
@@ -176,21 +514,31 @@ mod test {
         let id1 = Id { id: Uuid::new_v4() };
         let id2 = Id { id: Uuid::new_v4() };
 
-        let kilometer1 = Kilometer { kilometers: 2.0 };
-        let kilometer2 = Kilometer { kilometers: 1.0 };
+        let kilometer1 = Measurement::Distance {
+            kilometers: Decimal::from(2),
+        };
+        let kilometer2 = Measurement::Distance {
+            kilometers: Decimal::from(1),
+        };
 
         let kilometer_entry = KilometerEntry {
             id: id1,
-            kilometers: kilometer1,
-            kind: shared::Kind::Running,
+            measurement: kilometer1,
+            kind: shared::Kind::new("Running"),
             timestamp: Utc::now(),
+            recorded_at: Utc::now(),
+            points: Decimal::from(2),
+            details: Default::default(),
         };
 
         let kilometer_entry2 = KilometerEntry {
             id: id2,
-            kilometers: kilometer2,
-            kind: shared::Kind::Running,
+            measurement: kilometer2,
+            kind: shared::Kind::new("Running"),
             timestamp: Utc::now(),
+            recorded_at: Utc::now(),
+            points: Decimal::from(1),
+            details: Default::default(),
         };
         database
             .entries
@@ -200,10 +548,150 @@ mod test {
             .entries
             .insert("user2".to_owned(), vec![kilometer_entry2]);
 
-        let score = get_highscore(&database);
-        let first = score.list.get(0).unwrap();
+        let score = get_highscore(&database.entries, &Default::default());
+        let first = score.list.first().unwrap();
         let second = score.list.get(1).unwrap();
         assert_eq!(first.user, "user1");
         assert_eq!(second.user, "user2");
     }
+
+    #[test]
+    pub fn sums_distances_exactly() {
+        let mut database: DatabaseModel = Default::default();
+        for _ in 0..1000 {
+            database.insert_entry(
+                "user1",
+                KilometerEntry {
+                    measurement: Measurement::Distance {
+                        kilometers: Decimal::from_thousandths(100),
+                    },
+                    points: Decimal::from_thousandths(25),
+                    ..Default::default()
+                },
+            );
+        }
+
+        let score = get_highscore(&database.entries, &Default::default());
+
+        assert_eq!(score.list[0].kilometers, Decimal::from(100));
+        assert_eq!(score.list[0].points, Decimal::from(25));
+    }
+
+    #[test]
+    pub fn ranks_a_single_kind_by_amount() {
+        let mut database: DatabaseModel = Default::default();
+        for (user, kind, kilometers) in &[
+            ("cyclist", "Biking", 100),
+            ("climber", "Climbing", 1),
+            ("climber", "Biking", 10),
+            ("hiker", "Hiking", 20),
+            ("hiker", "Climbing", 2),
+        ] {
+            database.insert_entry(
+                user,
+                KilometerEntry {
+                    kind: shared::Kind::new(kind),
+                    measurement: Measurement::Distance {
+                        kilometers: Decimal::from(*kilometers),
+                    },
+                    ..Default::default()
+                },
+            );
+        }
+        let filter = HighscoreFilter::from_query(
+            HighscoreQuery {
+                kind: Some("klettern".to_owned()),
+                ..Default::default()
+            },
+            Utc::now(),
+            &shared::KindCatalogue::default(),
+        )
+        .unwrap();
+
+        let score = get_highscore(&database.entries, &filter);
+
+        let ranking = score
+            .list
+            .iter()
+            .map(|entry| (entry.user.as_str(), entry.amount))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            ranking,
+            vec![("hiker", Decimal::from(2)), ("climber", Decimal::from(1))]
+        );
+    }
+
+    fn migrated_fixture() -> DatabaseModel {
+        let v5: serde_json::Value =
+            serde_json::from_str(include_str!("migration/fixtures/v5.json")).unwrap();
+        let (migrated, _) = run_migrations(v5).unwrap();
+        serde_json::from_value(migrated).unwrap()
+    }
+
+    fn database(directory: &Path, model: DatabaseModel, challenge: ChallengePeriod) -> Database {
+        Database::new(
+            JsonStorage::new(
+                model,
+                directory.join("database.json"),
+                BackupConfig {
+                    directory: directory.join("backups"),
+                    count: 1,
+                    ..BackupConfig::default()
+                },
+            ),
+            challenge,
+            ScoringRules::default(),
+            KindStore::load(&directory.join("kinds.json")).unwrap(),
+            SessionStore::load(&directory.join("sessions.json"), Duration::hours(1)).unwrap(),
+            Throttle::new(Default::default(), Arc::new(SystemClock)),
+            Vec::new(),
+        )
+    }
+
+    #[tokio::test]
+    pub async fn scores_entries_stored_without_points() {
+        let directory = TestDirectory::create();
+        let database = database(&directory, migrated_fixture(), ChallengePeriod::default());
+
+        assert_eq!(database.score_unscored().await.unwrap(), 2);
+        assert_eq!(database.score_unscored().await.unwrap(), 0);
+
+        // 2.5 km running and 12 km biking, as ranked before points were stored
+        let highscore = database.get_highscore(&Default::default()).await;
+        assert_eq!(highscore.list[0].user, "hase");
+        assert_eq!(highscore.list[0].points, Decimal::from_thousandths(5_500));
+    }
+
+    #[tokio::test]
+    pub async fn edits_entries_dated_before_the_challenge() {
+        let directory = TestDirectory::create();
+        let challenge = ChallengePeriod::from_dates(Some(NaiveDate::from_ymd(2021, 5, 1)), None);
+        let database = database(&directory, migrated_fixture(), challenge);
+        let catalogue = KindCatalogue::default();
+        let running = catalogue.get(&Kind::new("Running")).unwrap();
+        // migrated entries without a date are dated 1970
+        let mut entry = database.storage.get_entries("hase").await.remove(0);
+        assert_eq!(entry.timestamp.year(), 1970);
+
+        entry.measurement = Measurement::Distance {
+            kilometers: Decimal::from(3),
+        };
+        let edited = database
+            .edit_kilometer_entry("hase".to_owned(), entry.clone(), running)
+            .await
+            .unwrap();
+        assert_eq!(edited.unwrap().points, Decimal::from(3));
+
+        entry.timestamp = entry.timestamp + Duration::days(1);
+        let error = database
+            .edit_kilometer_entry("hase".to_owned(), entry, running)
+            .await
+            .unwrap_err();
+        match error {
+            ApiError::Validation(errors) => {
+                assert_eq!(errors.errors[0].problem, Problem::BeforeChallenge)
+            }
+            error => panic!("unexpected error: {:?}", error),
+        }
+    }
 }
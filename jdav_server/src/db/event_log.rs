@@ -0,0 +1,674 @@
+use std::io;
+use std::path::{Path, PathBuf};
+
+use async_trait::async_trait;
+use chrono::prelude::*;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use shared::{Highscore, Id, KilometerEntry, TrashedEntry};
+use tokio::fs::{self, File, OpenOptions};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::RwLock;
+
+use crate::highscore::HighscoreFilter;
+use crate::models::{DatabaseModel, EntryDatabaseModel, User, UserKey};
+
+use super::{
+    db::{get_highscore, LoadError},
+    migration::{migration_to_v5, migration_to_v7, run_migrations},
+    persistence::write_atomically,
+    storage::{Storage, StorageError},
+};
+
+pub static EVENT_LOG_DIRECTORY: &str = "./eventlog";
+
+static LOG_FILENAME: &str = "events.log";
+static SNAPSHOT_FILENAME: &str = "snapshot.json";
+static ARCHIVE_DIRECTORY: &str = "archive";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum Event {
+    UserCreated {
+        name: UserKey,
+        user: User,
+    },
+    UserUpdated {
+        name: UserKey,
+        user: User,
+    },
+    EntryCreated {
+        user: UserKey,
+        entry: KilometerEntry,
+    },
+    EntryEdited {
+        user: UserKey,
+        entry: KilometerEntry,
+    },
+    EntryDeleted {
+        user: UserKey,
+        id: Id,
+        deleted_at: DateTime<Utc>,
+    },
+    EntryRestored {
+        user: UserKey,
+        id: Id,
+    },
+    TrashPurged {
+        before: DateTime<Utc>,
+    },
+}
+
+impl Event {
+    pub fn apply(self, database: &mut DatabaseModel) -> bool {
+        match self {
+            Event::UserCreated { name, user } => database.insert_user(name, user),
+            Event::UserUpdated { name, user } => database.update_user(&name, user),
+            Event::EntryCreated { user, entry } => {
+                database.insert_entry(&user, entry);
+                true
+            }
+            Event::EntryEdited { user, entry } => database.update_entry(&user, entry),
+            Event::EntryDeleted {
+                user,
+                id,
+                deleted_at,
+            } => database.delete_entry(&user, &id, deleted_at),
+            Event::EntryRestored { user, id } => database.restore_entry(&user, &id),
+            Event::TrashPurged { before } => database.purge_trash(before) > 0,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct LoggedEvent {
+    sequence: u64,
+    timestamp: DateTime<Utc>,
+    event: Event,
+}
+
+#[derive(Debug, Serialize)]
+struct Snapshot<'a> {
+    sequence: u64,
+    database: &'a DatabaseModel,
+}
+
+struct EventLogState {
+    database: DatabaseModel,
+    log: File,
+    sequence: u64,
+    first_in_log: u64,
+}
+
+pub struct EventLogStorage {
+    state: RwLock<EventLogState>,
+    directory: PathBuf,
+    snapshot_every: u64,
+}
+
+impl EventLogStorage {
+    pub async fn open(
+        directory: PathBuf,
+        snapshot_every: u64,
+        init_empty: bool,
+    ) -> Result<Self, LoadError> {
+        let log_path = directory.join(LOG_FILENAME);
+        let snapshot_path = directory.join(SNAPSHOT_FILENAME);
+        if !init_empty && !log_path.exists() && !snapshot_path.exists() {
+            return Err(LoadError::Missing { path: directory });
+        }
+        let unreadable = |path: &Path| {
+            let path = path.to_owned();
+            move |source| LoadError::Unreadable { path, source }
+        };
+        fs::create_dir_all(&directory)
+            .await
+            .map_err(unreadable(&directory))?;
+
+        let (mut database, mut sequence) = if snapshot_path.exists() {
+            load_snapshot(&snapshot_path).await?
+        } else {
+            (DatabaseModel::default(), 0)
+        };
+
+        let content = match fs::read(&log_path).await {
+            Ok(content) => content,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Vec::new(),
+            Err(error) => return Err(unreadable(&log_path)(error)),
+        };
+        let (events, valid_length) = parse_log(&log_path, &content)?;
+        let first_in_log = events.first().map_or(sequence + 1, |event| event.sequence);
+        for logged in events {
+            if logged.sequence > sequence {
+                logged.event.apply(&mut database);
+                sequence = logged.sequence;
+            }
+        }
+
+        let log = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&log_path)
+            .await
+            .map_err(unreadable(&log_path))?;
+        if valid_length < content.len() {
+            eprintln!("Discarding incomplete last event in {}", log_path.display());
+            log.set_len(valid_length as u64)
+                .await
+                .map_err(unreadable(&log_path))?;
+        }
+
+        Ok(EventLogStorage {
+            state: RwLock::new(EventLogState {
+                database,
+                log,
+                sequence,
+                first_in_log,
+            }),
+            directory,
+            snapshot_every,
+        })
+    }
+
+    /// Appends the event and applies it, failing when it could not be written.
+    async fn record(&self, state: &mut EventLogState, event: Event) -> Result<bool, StorageError> {
+        let logged = LoggedEvent {
+            sequence: state.sequence + 1,
+            timestamp: Utc::now(),
+            event,
+        };
+        append(&mut state.log, &logged).await?;
+        state.sequence = logged.sequence;
+        let applied = logged.event.apply(&mut state.database);
+
+        if self.snapshot_every > 0 && state.sequence - state.first_in_log + 1 >= self.snapshot_every
+        {
+            if let Err(error) = self.compact(state).await {
+                eprintln!("Error compacting event log: {}", error);
+            }
+        }
+        Ok(applied)
+    }
+
+    async fn compact(&self, state: &mut EventLogState) -> io::Result<()> {
+        let snapshot = serde_json::to_vec(&Snapshot {
+            sequence: state.sequence,
+            database: &state.database,
+        })?;
+        let snapshot_path = self.directory.join(SNAPSHOT_FILENAME);
+        tokio::task::spawn_blocking(move || write_atomically(&snapshot_path, &snapshot))
+            .await
+            .map_err(io::Error::other)??;
+
+        let archive = self.directory.join(ARCHIVE_DIRECTORY);
+        fs::create_dir_all(&archive).await?;
+        let log_path = self.directory.join(LOG_FILENAME);
+        fs::rename(
+            &log_path,
+            archive.join(format!(
+                "events-{:010}-{:010}.log",
+                state.first_in_log, state.sequence
+            )),
+        )
+        .await?;
+        state.log = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&log_path)
+            .await?;
+        state.first_in_log = state.sequence + 1;
+        Ok(())
+    }
+}
+
+/// Appends the event as one line. When the line could not be written
+/// completely, the log is cut back to its previous length, so that the next
+/// event does not continue the torn line.
+async fn append(log: &mut File, logged: &LoggedEvent) -> io::Result<()> {
+    let mut line = serde_json::to_vec(logged)?;
+    line.push(b'\n');
+    let length = log.metadata().await?.len();
+    let written = match log.write_all(&line).await {
+        Ok(()) => log.sync_data().await,
+        Err(error) => Err(error),
+    };
+    if let Err(error) = written {
+        if let Err(truncate_error) = log.set_len(length).await {
+            eprintln!(
+                "Could not remove the incomplete event from the log: {}",
+                truncate_error
+            );
+        }
+        return Err(error);
+    }
+    Ok(())
+}
+
+async fn load_snapshot(path: &Path) -> Result<(DatabaseModel, u64), LoadError> {
+    let content = fs::read(path)
+        .await
+        .map_err(|source| LoadError::Unreadable {
+            path: path.to_owned(),
+            source,
+        })?;
+    let corrupt = |error: serde_json::Error| LoadError::Corrupt {
+        path: path.to_owned(),
+        line: error.line(),
+        column: error.column(),
+        message: error.to_string(),
+        quarantined: None,
+    };
+    let mut snapshot = serde_json::from_slice::<Value>(&content).map_err(corrupt)?;
+    let sequence = snapshot
+        .get("sequence")
+        .and_then(Value::as_u64)
+        .unwrap_or_default();
+    let (database, _) =
+        run_migrations(snapshot["database"].take()).map_err(|error| LoadError::Migration {
+            path: path.to_owned(),
+            message: error.to_string(),
+        })?;
+    let database = serde_json::from_value(database).map_err(corrupt)?;
+    Ok((database, sequence))
+}
+
+fn upgrade_event(logged: &mut Value) {
+    let timestamp = logged["timestamp"].clone();
+    for kind in &["EntryCreated", "EntryEdited"] {
+        let entry = logged.pointer_mut(&format!("/event/{}/entry", kind));
+        if let Some(entry) = entry {
+            if let Some(fields) = entry.as_object_mut() {
+                fields.entry("recorded_at").or_insert(timestamp.clone());
+                migration_to_v5::rename_kilometers(fields);
+            }
+            migration_to_v7::climb_in_metres(entry);
+        }
+    }
+}
+
+fn parse_log(path: &Path, content: &[u8]) -> Result<(Vec<LoggedEvent>, usize), LoadError> {
+    let mut events = Vec::new();
+    let mut offset = 0;
+    for (index, line) in content.split_inclusive(|byte| *byte == b'\n').enumerate() {
+        if !line.ends_with(b"\n") {
+            break;
+        }
+        let logged = serde_json::from_slice::<Value>(line).and_then(|mut logged| {
+            upgrade_event(&mut logged);
+            serde_json::from_value::<LoggedEvent>(logged)
+        });
+        match logged {
+            Ok(logged) => events.push(logged),
+            Err(error) => {
+                return Err(LoadError::Corrupt {
+                    path: path.to_owned(),
+                    line: index + 1,
+                    column: error.column(),
+                    message: error.to_string(),
+                    quarantined: None,
+                })
+            }
+        }
+        offset += line.len();
+    }
+    Ok((events, offset))
+}
+
+#[async_trait]
+impl Storage for EventLogStorage {
+    async fn insert_user(&self, name: UserKey, user: User) -> Result<bool, StorageError> {
+        let mut state = self.state.write().await;
+        if state.database.users.contains_key(&name) {
+            return Ok(false);
+        }
+        self.record(&mut state, Event::UserCreated { name, user })
+            .await
+    }
+
+    async fn get_user(&self, name: &str) -> Result<Option<User>, StorageError> {
+        let state = self.state.read().await;
+        Ok(state.database.users.get(name).cloned())
+    }
+
+    async fn update_user(&self, name: &str, user: User) -> Result<bool, StorageError> {
+        let mut state = self.state.write().await;
+        if !state.database.users.contains_key(name) {
+            return Ok(false);
+        }
+        let name = name.to_owned();
+        self.record(&mut state, Event::UserUpdated { name, user })
+            .await
+    }
+
+    async fn insert_entry(&self, user: &str, entry: KilometerEntry) -> Result<(), StorageError> {
+        let mut state = self.state.write().await;
+        let event = Event::EntryCreated {
+            user: user.to_owned(),
+            entry,
+        };
+        self.record(&mut state, event).await?;
+        Ok(())
+    }
+
+    async fn get_entry(&self, id: &Id) -> Option<(UserKey, KilometerEntry)> {
+        let state = self.state.read().await;
+        state.database.find_entry(id)
+    }
+
+    async fn update_entry(&self, user: &str, entry: KilometerEntry) -> Result<bool, StorageError> {
+        let mut state = self.state.write().await;
+        let exists = state
+            .database
+            .entries
+            .get(user)
+            .is_some_and(|entries| entries.iter().any(|known| known.id == entry.id));
+        if !exists {
+            return Ok(false);
+        }
+        let event = Event::EntryEdited {
+            user: user.to_owned(),
+            entry,
+        };
+        self.record(&mut state, event).await
+    }
+
+    async fn get_entries(&self, user: &str) -> Vec<KilometerEntry> {
+        let state = self.state.read().await;
+        state
+            .database
+            .entries
+            .get(user)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    async fn delete_entry(
+        &self,
+        user: &str,
+        id: &Id,
+        deleted_at: DateTime<Utc>,
+    ) -> Result<bool, StorageError> {
+        let mut state = self.state.write().await;
+        let exists = state
+            .database
+            .entries
+            .get(user)
+            .is_some_and(|entries| entries.iter().any(|known| known.id == *id));
+        if !exists {
+            return Ok(false);
+        }
+        let event = Event::EntryDeleted {
+            user: user.to_owned(),
+            id: *id,
+            deleted_at,
+        };
+        self.record(&mut state, event).await
+    }
+
+    async fn restore_entry(&self, user: &str, id: &Id) -> Result<bool, StorageError> {
+        let mut state = self.state.write().await;
+        let exists = state
+            .database
+            .trash
+            .get(user)
+            .is_some_and(|trash| trash.iter().any(|trashed| trashed.entry.id == *id));
+        if !exists {
+            return Ok(false);
+        }
+        let event = Event::EntryRestored {
+            user: user.to_owned(),
+            id: *id,
+        };
+        self.record(&mut state, event).await
+    }
+
+    async fn get_trash(&self, user: &str) -> Vec<TrashedEntry> {
+        let state = self.state.read().await;
+        state.database.trash.get(user).cloned().unwrap_or_default()
+    }
+
+    async fn purge_trash(&self, before: DateTime<Utc>) -> Result<usize, StorageError> {
+        let mut state = self.state.write().await;
+        let expired = state
+            .database
+            .trash
+            .values()
+            .flatten()
+            .filter(|trashed| trashed.deleted_at < before)
+            .count();
+        if expired > 0 {
+            self.record(&mut state, Event::TrashPurged { before })
+                .await?;
+        }
+        Ok(expired)
+    }
+
+    async fn get_all_entries(&self) -> EntryDatabaseModel {
+        let state = self.state.read().await;
+        state.database.entries.clone()
+    }
+
+    async fn get_highscore(&self, filter: &HighscoreFilter) -> Highscore {
+        let state = self.state.read().await;
+        get_highscore(&state.database.entries, filter)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use chrono::prelude::*;
+    use shared::{Decimal, Id, KilometerEntry, Kind, Measurement};
+    use uuid::Uuid;
+
+    use super::{EventLogStorage, LOG_FILENAME, SNAPSHOT_FILENAME};
+    use crate::db::db::LoadError;
+    use crate::db::storage::Storage;
+    use crate::models::User;
+    use crate::test_util::TestDirectory;
+
+    fn entry(kilometers: u32) -> KilometerEntry {
+        let kilometers = Decimal::from(kilometers);
+        KilometerEntry {
+            id: Id { id: Uuid::new_v4() },
+            measurement: Measurement::Distance { kilometers },
+            kind: Kind::new("Running"),
+            timestamp: Utc.ymd(2021, 5, 1).and_hms(10, 0, 0),
+            recorded_at: Utc.ymd(2021, 5, 2).and_hms(10, 0, 0),
+            points: kilometers,
+            details: Default::default(),
+        }
+    }
+
+    fn user() -> User {
+        User {
+            hash: "hash".to_owned(),
+            salt: "salt".to_owned(),
+            params: Default::default(),
+        }
+    }
+
+    #[tokio::test]
+    pub async fn requires_init_empty_for_new_log() {
+        let directory = TestDirectory::create();
+        let log_directory = directory.join("eventlog");
+
+        let result = EventLogStorage::open(log_directory.clone(), 0, false).await;
+
+        assert!(matches!(result, Err(LoadError::Missing { .. })));
+        assert!(!log_directory.exists());
+    }
+
+    #[tokio::test]
+    pub async fn replays_events_on_open() {
+        let directory = TestDirectory::create();
+        let mut first = entry(2);
+        {
+            let storage = EventLogStorage::open(directory.to_path_buf(), 0, true)
+                .await
+                .unwrap();
+            assert!(storage
+                .insert_user("hase".to_owned(), user())
+                .await
+                .unwrap());
+            let rehashed = User {
+                hash: "rehashed".to_owned(),
+                ..user()
+            };
+            assert!(storage.update_user("hase", rehashed).await.unwrap());
+            assert!(!storage.update_user("igel", user()).await.unwrap());
+            storage.insert_entry("hase", first.clone()).await.unwrap();
+            storage.insert_entry("hase", entry(1)).await.unwrap();
+            first.measurement = Measurement::Distance {
+                kilometers: Decimal::from(5),
+            };
+            assert!(storage.update_entry("hase", first.clone()).await.unwrap());
+            assert!(!storage.update_entry("igel", first.clone()).await.unwrap());
+        }
+
+        let storage = EventLogStorage::open(directory.to_path_buf(), 0, false)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            storage.get_user("hase").await.unwrap().unwrap().hash,
+            "rehashed"
+        );
+        let entries = storage.get_entries("hase").await;
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0], first);
+        let log = fs::read_to_string(directory.join(LOG_FILENAME)).unwrap();
+        assert_eq!(log.lines().count(), 5);
+    }
+
+    #[tokio::test]
+    pub async fn replays_trash_on_open() {
+        let directory = TestDirectory::create();
+        let kept = entry(1);
+        let purged = entry(2);
+        {
+            let storage = EventLogStorage::open(directory.to_path_buf(), 0, true)
+                .await
+                .unwrap();
+            storage.insert_entry("hase", kept.clone()).await.unwrap();
+            storage.insert_entry("hase", purged.clone()).await.unwrap();
+            let yesterday = Utc::now() - chrono::Duration::days(1);
+            assert!(storage
+                .delete_entry("hase", &kept.id, yesterday)
+                .await
+                .unwrap());
+            assert!(storage.restore_entry("hase", &kept.id).await.unwrap());
+            assert!(storage
+                .delete_entry("hase", &purged.id, yesterday)
+                .await
+                .unwrap());
+            assert!(!storage
+                .delete_entry("hase", &purged.id, Utc::now())
+                .await
+                .unwrap());
+            assert_eq!(storage.purge_trash(Utc::now()).await.unwrap(), 1);
+        }
+
+        let storage = EventLogStorage::open(directory.to_path_buf(), 0, false)
+            .await
+            .unwrap();
+
+        assert_eq!(storage.get_entries("hase").await, vec![kept]);
+        assert!(storage.get_trash("hase").await.is_empty());
+    }
+
+    #[tokio::test]
+    pub async fn compacts_into_snapshot() {
+        let directory = TestDirectory::create();
+        {
+            let storage = EventLogStorage::open(directory.to_path_buf(), 3, true)
+                .await
+                .unwrap();
+            for kilometers in 1..=4 {
+                storage
+                    .insert_entry("hase", entry(kilometers))
+                    .await
+                    .unwrap();
+            }
+        }
+
+        let storage = EventLogStorage::open(directory.to_path_buf(), 3, false)
+            .await
+            .unwrap();
+
+        assert_eq!(storage.get_entries("hase").await.len(), 4);
+        assert!(directory.join(SNAPSHOT_FILENAME).exists());
+        let log = fs::read_to_string(directory.join(LOG_FILENAME)).unwrap();
+        assert_eq!(log.lines().count(), 1);
+        assert_eq!(fs::read_dir(directory.join("archive")).unwrap().count(), 1);
+    }
+
+    #[tokio::test]
+    pub async fn discards_torn_last_event() {
+        let directory = TestDirectory::create();
+        {
+            let storage = EventLogStorage::open(directory.to_path_buf(), 0, true)
+                .await
+                .unwrap();
+            storage.insert_entry("hase", entry(1)).await.unwrap();
+        }
+        let mut log = fs::read(directory.join(LOG_FILENAME)).unwrap();
+        log.extend_from_slice(b"{\"sequence\": 2, \"times");
+        fs::write(directory.join(LOG_FILENAME), log).unwrap();
+
+        let storage = EventLogStorage::open(directory.to_path_buf(), 0, false)
+            .await
+            .unwrap();
+        storage.insert_entry("hase", entry(2)).await.unwrap();
+        drop(storage);
+        let storage = EventLogStorage::open(directory.to_path_buf(), 0, false)
+            .await
+            .unwrap();
+
+        assert_eq!(storage.get_entries("hase").await.len(), 2);
+    }
+
+    #[tokio::test]
+    pub async fn reads_events_of_older_versions() {
+        let directory = TestDirectory::create();
+        let event = r#"{"sequence":1,"timestamp":"2021-05-02T10:00:00Z","event":{"EntryCreated":{"user":"hase","entry":{"id":{"id":"2f1c1b7e-4e7b-4a43-9d4e-2d3c3f1e0a01"},"kilometers":{"kilometers":2.5},"kind":"Running","timestamp":"2021-05-01T10:00:00Z"}}}}"#;
+        let climbing = r#"{"sequence":2,"timestamp":"2021-05-02T10:00:00Z","event":{"EntryCreated":{"user":"hase","entry":{"id":{"id":"2f1c1b7e-4e7b-4a43-9d4e-2d3c3f1e0a02"},"measurement":{"kilometers":0.8},"kind":"Climbing","timestamp":"2021-05-01T10:00:00Z","recorded_at":"2021-05-01T10:00:00Z","points":80.0}}}}"#;
+        fs::write(
+            directory.join(LOG_FILENAME),
+            format!("{}\n{}\n", event, climbing),
+        )
+        .unwrap();
+
+        let storage = EventLogStorage::open(directory.to_path_buf(), 0, false)
+            .await
+            .unwrap();
+
+        let entries = storage.get_entries("hase").await;
+        assert_eq!(
+            entries[0].recorded_at,
+            Utc.ymd(2021, 5, 2).and_hms(10, 0, 0)
+        );
+        assert_eq!(
+            entries[0].measurement,
+            Measurement::Distance {
+                kilometers: Decimal::from_thousandths(2_500)
+            }
+        );
+        assert_eq!(
+            entries[1].measurement,
+            Measurement::Elevation {
+                metres: Decimal::from(800)
+            }
+        );
+    }
+
+    #[tokio::test]
+    pub async fn refuses_corrupt_log() {
+        let directory = TestDirectory::create();
+        fs::write(directory.join(LOG_FILENAME), b"garbage\n{}\n").unwrap();
+
+        let result = EventLogStorage::open(directory.to_path_buf(), 0, false).await;
+
+        assert!(matches!(result, Err(LoadError::Corrupt { line: 1, .. })));
+    }
+}
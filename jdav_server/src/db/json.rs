@@ -0,0 +1,350 @@
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use chrono::prelude::*;
+use shared::{Highscore, Id, KilometerEntry, TrashedEntry};
+use tokio::sync::{mpsc, oneshot, RwLock};
+use tokio::time::{timeout_at, Instant};
+
+use crate::highscore::HighscoreFilter;
+use crate::models::{DatabaseModel, EntryDatabaseModel, User, UserKey};
+
+use super::{
+    db::get_highscore,
+    persistence::{backup, write_atomically, BackupConfig},
+    storage::{Storage, StorageError},
+};
+
+pub static DATABASE_FILENAME: &str = "./database.json";
+
+const WRITE_DELAY: Duration = Duration::from_millis(500);
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(60);
+
+enum PersistCommand {
+    Changed,
+    Flush(oneshot::Sender<io::Result<()>>),
+}
+
+pub struct JsonStorage {
+    database: Arc<RwLock<DatabaseModel>>,
+    persister: mpsc::UnboundedSender<PersistCommand>,
+}
+
+impl JsonStorage {
+    pub fn new(database: DatabaseModel, path: PathBuf, backups: BackupConfig) -> Self {
+        let database = Arc::new(RwLock::new(database));
+        let (persister, commands) = mpsc::unbounded_channel();
+        tokio::spawn(persist(database.clone(), path, backups, commands));
+        JsonStorage {
+            database,
+            persister,
+        }
+    }
+
+    fn changed(&self) {
+        if self.persister.send(PersistCommand::Changed).is_err() {
+            eprintln!("Database persister is gone, changes will not be saved");
+        }
+    }
+}
+
+async fn persist(
+    database: Arc<RwLock<DatabaseModel>>,
+    path: PathBuf,
+    backups: BackupConfig,
+    mut commands: mpsc::UnboundedReceiver<PersistCommand>,
+) {
+    let mut deadline: Option<Instant> = None;
+    let mut last_backup: Option<Instant> = None;
+    let mut retry_delay = WRITE_DELAY;
+    loop {
+        let command = match deadline {
+            Some(deadline) => match timeout_at(deadline, commands.recv()).await {
+                Ok(command) => command,
+                Err(_) => Some(PersistCommand::Flush(oneshot::channel().0)),
+            },
+            None => commands.recv().await,
+        };
+
+        match command {
+            Some(PersistCommand::Changed) => {
+                deadline.get_or_insert_with(|| Instant::now() + WRITE_DELAY);
+            }
+            Some(PersistCommand::Flush(done)) => {
+                let result = match deadline {
+                    Some(_) => save_database(&database, &path, &backups, &mut last_backup).await,
+                    None => Ok(()),
+                };
+                // failed writes stay pending and are retried with growing delays
+                deadline = match &result {
+                    Ok(()) => {
+                        retry_delay = WRITE_DELAY;
+                        None
+                    }
+                    Err(error) => {
+                        eprintln!(
+                            "Error writing {}, retrying in {} s: {}",
+                            path.display(),
+                            retry_delay.as_secs_f32(),
+                            error
+                        );
+                        let retry = Instant::now() + retry_delay;
+                        retry_delay = (retry_delay * 2).min(MAX_RETRY_DELAY);
+                        Some(retry)
+                    }
+                };
+                done.send(result).ok();
+            }
+            None => {
+                if deadline.is_some() {
+                    if let Err(error) =
+                        save_database(&database, &path, &backups, &mut last_backup).await
+                    {
+                        eprintln!(
+                            "Error writing {}, changes are lost: {}",
+                            path.display(),
+                            error
+                        );
+                    }
+                }
+                break;
+            }
+        }
+    }
+}
+
+/// Writes the database, backing up the previous version first when the last
+/// backup of this run is older than the backup interval.
+async fn save_database(
+    database: &RwLock<DatabaseModel>,
+    path: &Path,
+    backups: &BackupConfig,
+    last_backup: &mut Option<Instant>,
+) -> io::Result<()> {
+    let snapshot = database.read().await.clone();
+    let target = path.to_owned();
+    let backup_due = match last_backup {
+        Some(last_backup) => last_backup.elapsed() >= backups.interval,
+        None => true,
+    };
+    let backups = backups.clone();
+    tokio::task::spawn_blocking(move || -> io::Result<()> {
+        let json = serde_json::to_vec(&snapshot)?;
+        if backup_due {
+            backup(&target, &backups)?;
+        }
+        write_atomically(&target, &json)
+    })
+    .await
+    .map_err(io::Error::other)??;
+
+    if backup_due {
+        *last_backup = Some(Instant::now());
+    }
+    Ok(())
+}
+
+#[async_trait]
+impl Storage for JsonStorage {
+    async fn insert_user(&self, name: UserKey, user: User) -> Result<bool, StorageError> {
+        let mut db = self.database.write().await;
+
+        let inserted = db.insert_user(name, user);
+        if inserted {
+            self.changed();
+        }
+        Ok(inserted)
+    }
+
+    async fn get_user(&self, name: &str) -> Result<Option<User>, StorageError> {
+        let db = self.database.read().await;
+        Ok(db.users.get(name).cloned())
+    }
+
+    async fn update_user(&self, name: &str, user: User) -> Result<bool, StorageError> {
+        let mut db = self.database.write().await;
+
+        let updated = db.update_user(name, user);
+        if updated {
+            self.changed();
+        }
+        Ok(updated)
+    }
+
+    async fn insert_entry(&self, user: &str, entry: KilometerEntry) -> Result<(), StorageError> {
+        let mut db = self.database.write().await;
+        db.insert_entry(user, entry);
+        self.changed();
+        Ok(())
+    }
+
+    async fn get_entry(&self, id: &Id) -> Option<(UserKey, KilometerEntry)> {
+        let db = self.database.read().await;
+        db.find_entry(id)
+    }
+
+    async fn update_entry(
+        &self,
+        user: &str,
+        new_entry: KilometerEntry,
+    ) -> Result<bool, StorageError> {
+        let mut db = self.database.write().await;
+
+        let updated = db.update_entry(user, new_entry);
+        if updated {
+            self.changed();
+        }
+        Ok(updated)
+    }
+
+    async fn delete_entry(
+        &self,
+        user: &str,
+        id: &Id,
+        deleted_at: DateTime<Utc>,
+    ) -> Result<bool, StorageError> {
+        let mut db = self.database.write().await;
+
+        let deleted = db.delete_entry(user, id, deleted_at);
+        if deleted {
+            self.changed();
+        }
+        Ok(deleted)
+    }
+
+    async fn restore_entry(&self, user: &str, id: &Id) -> Result<bool, StorageError> {
+        let mut db = self.database.write().await;
+
+        let restored = db.restore_entry(user, id);
+        if restored {
+            self.changed();
+        }
+        Ok(restored)
+    }
+
+    async fn get_entries(&self, user: &str) -> Vec<KilometerEntry> {
+        let db = self.database.read().await;
+        db.entries.get(user).cloned().unwrap_or_default()
+    }
+
+    async fn get_trash(&self, user: &str) -> Vec<TrashedEntry> {
+        let db = self.database.read().await;
+        db.trash.get(user).cloned().unwrap_or_default()
+    }
+
+    async fn purge_trash(&self, before: DateTime<Utc>) -> Result<usize, StorageError> {
+        let mut db = self.database.write().await;
+
+        let purged = db.purge_trash(before);
+        if purged > 0 {
+            self.changed();
+        }
+        Ok(purged)
+    }
+
+    async fn get_all_entries(&self) -> EntryDatabaseModel {
+        let db = self.database.read().await;
+        db.entries.clone()
+    }
+
+    async fn get_highscore(&self, filter: &HighscoreFilter) -> Highscore {
+        let db = self.database.read().await;
+        get_highscore(&db.entries, filter)
+    }
+
+    async fn flush(&self) -> Result<(), StorageError> {
+        let (done, flushed) = oneshot::channel();
+        let gone = || io::Error::other("database persister is gone");
+        self.persister
+            .send(PersistCommand::Flush(done))
+            .map_err(|_| gone())?;
+        flushed.await.map_err(|_| gone())??;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+    use std::path::Path;
+
+    use super::JsonStorage;
+    use crate::db::persistence::{list_backups, BackupConfig};
+    use crate::db::storage::Storage;
+    use crate::models::{DatabaseModel, User};
+    use crate::test_util::TestDirectory;
+
+    fn backups(directory: &Path) -> BackupConfig {
+        BackupConfig {
+            directory: directory.join("backups"),
+            ..BackupConfig::default()
+        }
+    }
+
+    fn user() -> User {
+        User {
+            hash: "hash".to_owned(),
+            salt: "salt".to_owned(),
+            params: Default::default(),
+        }
+    }
+
+    #[tokio::test]
+    pub async fn coalesces_writes_until_flush() {
+        let directory = TestDirectory::create();
+        let path = directory.join("database.json");
+        let backups = backups(&directory);
+        let storage = JsonStorage::new(DatabaseModel::default(), path.clone(), backups.clone());
+
+        for name in &["hase", "igel", "dachs"] {
+            assert!(storage.insert_user(name.to_string(), user()).await.unwrap());
+        }
+        assert!(!path.exists());
+
+        storage.flush().await.unwrap();
+
+        let saved: DatabaseModel = serde_json::from_slice(&fs::read(&path).unwrap()).unwrap();
+        assert_eq!(saved.users.len(), 3);
+        assert!(list_backups(&backups).unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    pub async fn keeps_failed_writes_pending() {
+        let directory = TestDirectory::create();
+        let path = directory.join("missing").join("database.json");
+        let backups = backups(&directory);
+        let storage = JsonStorage::new(DatabaseModel::default(), path.clone(), backups);
+        assert!(storage
+            .insert_user("hase".to_owned(), user())
+            .await
+            .unwrap());
+
+        assert!(storage.flush().await.is_err());
+        fs::create_dir_all(directory.join("missing")).unwrap();
+        storage.flush().await.unwrap();
+
+        let saved: DatabaseModel = serde_json::from_slice(&fs::read(&path).unwrap()).unwrap();
+        assert_eq!(saved.users.len(), 1);
+    }
+
+    #[tokio::test]
+    pub async fn backs_up_once_per_interval() {
+        let directory = TestDirectory::create();
+        let path = directory.join("database.json");
+        fs::write(&path, b"{}").unwrap();
+        let backups = backups(&directory);
+        let storage = JsonStorage::new(DatabaseModel::default(), path.clone(), backups.clone());
+
+        for name in &["hase", "igel", "dachs"] {
+            assert!(storage.insert_user(name.to_string(), user()).await.unwrap());
+            storage.flush().await.unwrap();
+        }
+
+        let saved = list_backups(&backups).unwrap();
+        assert_eq!(saved.len(), 1);
+        assert_eq!(fs::read_to_string(&saved[0]).unwrap(), "{}");
+    }
+}
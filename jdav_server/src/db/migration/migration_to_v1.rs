@@ -1,9 +1,8 @@
 use chrono::prelude::*;
 use serde::{Deserialize, Serialize};
-use shared::KilometerEntry;
+use serde_json::Value;
 use std::collections::HashMap;
-
-use super::DatabaseVersion;
+use uuid::Uuid;
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct User {
@@ -11,66 +10,100 @@ pub struct User {
     pub salt: String,
 }
 
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct Id {
+    pub id: Uuid,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct Kilometer {
+    pub kilometers: f64,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct KilometerEntry {
+    pub id: Id,
+    pub kilometers: Kilometer,
+    #[serde(default)]
+    pub kind: Option<String>,
+    #[serde(default)]
+    pub timestamp: Option<DateTime<Utc>>,
+}
+
 pub type UserKey = String;
 pub type EntryDatabaseModel = HashMap<UserKey, Vec<KilometerEntry>>;
 pub type UserDatabaseModel = HashMap<UserKey, User>;
 
-#[derive(Debug, Deserialize, Serialize, Clone)]
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
 pub struct DatabaseModel {
     pub entries: EntryDatabaseModel,
     pub users: UserDatabaseModel,
 }
 
-impl Default for DatabaseModel {
-    fn default() -> Self {
-        DatabaseModel {
-            entries: HashMap::new(),
-            users: HashMap::new(),
-        }
+pub mod v1 {
+    use chrono::prelude::*;
+    use serde::{Deserialize, Serialize};
+    use std::collections::HashMap;
+
+    use super::{Id, Kilometer, User, UserKey};
+
+    #[derive(Debug, Deserialize, Serialize, Clone)]
+    pub struct KilometerEntry {
+        pub id: Id,
+        pub kilometers: Kilometer,
+        pub kind: String,
+        pub timestamp: DateTime<Utc>,
+    }
+
+    #[derive(Debug, Deserialize, Serialize, Clone)]
+    pub struct DatabaseModel {
+        pub entries: HashMap<UserKey, Vec<KilometerEntry>>,
+        pub users: HashMap<UserKey, User>,
+        pub database_version: String,
     }
 }
 
 impl DatabaseModel {
-    pub fn to_v1(self) -> crate::models::DatabaseModel {
-        crate::models::DatabaseModel {
+    pub fn into_v1(self) -> v1::DatabaseModel {
+        v1::DatabaseModel {
             entries: self
                 .entries
-                .iter()
-                .map(|(user, entry)| (user.to_owned(), map_entry(entry)))
+                .into_iter()
+                .map(|(user, entries)| (user, entries.into_iter().map(map_entry).collect()))
                 .collect::<HashMap<_, _>>(),
-            users: map_users(self.users),
-            database_version: DatabaseVersion::V1,
+            users: self.users,
+            database_version: "V1".to_owned(),
         }
     }
 }
 
-fn map_entry(entry: &[KilometerEntry]) -> Vec<shared::KilometerEntry> {
-    entry
-        .iter()
-        .map(|entry| shared::KilometerEntry {
-            id: shared::Id { id: entry.id.id },
-            kilometers: shared::Kilometer {
-                kilometers: entry.kilometers.kilometers,
-            },
-            kind: shared::Kind::Running,
-            timestamp: Utc::now(),
-        })
-        .collect::<Vec<_>>()
+fn map_entry(entry: KilometerEntry) -> v1::KilometerEntry {
+    v1::KilometerEntry {
+        id: entry.id,
+        kilometers: entry.kilometers,
+        kind: entry.kind.unwrap_or_else(|| "Running".to_owned()),
+        timestamp: entry
+            .timestamp
+            .unwrap_or_else(|| Utc.ymd(1970, 1, 1).and_hms(0, 0, 0)),
+    }
 }
 
-fn map_users(
-    users: HashMap<UserKey, User>,
-) -> HashMap<crate::models::UserKey, crate::models::User> {
-    users
-        .iter()
-        .map(|(user_key, user)| {
-            (
-                user_key.to_owned(),
-                crate::models::User {
-                    hash: user.hash.clone(),
-                    salt: user.salt.clone(),
-                },
-            )
-        })
-        .collect::<HashMap<_, _>>()
+pub fn migrate(database: Value) -> serde_json::Result<Value> {
+    let database: DatabaseModel = serde_json::from_value(database)?;
+    serde_json::to_value(database.into_v1())
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::Value;
+
+    use super::migrate;
+
+    #[test]
+    pub fn migrates_fixture() {
+        let v0: Value = serde_json::from_str(include_str!("fixtures/v0.json")).unwrap();
+        let v1: Value = serde_json::from_str(include_str!("fixtures/v1.json")).unwrap();
+
+        assert_eq!(migrate(v0).unwrap(), v1);
+    }
 }
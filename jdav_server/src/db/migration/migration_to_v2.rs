@@ -0,0 +1,28 @@
+use serde_json::{Map, Value};
+
+pub fn migrate(mut database: Value) -> serde_json::Result<Value> {
+    let object = match database.as_object_mut() {
+        Some(object) => object,
+        None => return Err(serde::de::Error::custom("database is not an object")),
+    };
+    object
+        .entry("trash")
+        .or_insert_with(|| Value::Object(Map::new()));
+    object.insert("database_version".to_owned(), Value::from("V2"));
+    Ok(database)
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::Value;
+
+    use super::migrate;
+
+    #[test]
+    pub fn migrates_fixture() {
+        let v1: Value = serde_json::from_str(include_str!("fixtures/v1.json")).unwrap();
+        let v2: Value = serde_json::from_str(include_str!("fixtures/v2.json")).unwrap();
+
+        assert_eq!(migrate(v1).unwrap(), v2);
+    }
+}
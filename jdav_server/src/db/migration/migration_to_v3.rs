@@ -0,0 +1,62 @@
+use serde_json::Value;
+
+fn add_recorded_at(entry: &mut Value) {
+    if let Some(entry) = entry.as_object_mut() {
+        let timestamp = entry.get("timestamp").cloned().unwrap_or(Value::Null);
+        entry.entry("recorded_at").or_insert(timestamp);
+    }
+}
+
+pub fn migrate(mut database: Value) -> serde_json::Result<Value> {
+    if let Some(entries) = database["entries"].as_object_mut() {
+        for entry in entries
+            .values_mut()
+            .filter_map(Value::as_array_mut)
+            .flatten()
+        {
+            add_recorded_at(entry);
+        }
+    }
+    if let Some(trash) = database["trash"].as_object_mut() {
+        for trashed in trash.values_mut().filter_map(Value::as_array_mut).flatten() {
+            add_recorded_at(&mut trashed["entry"]);
+        }
+    }
+    database["database_version"] = Value::from("V3");
+    Ok(database)
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::Value;
+
+    use super::migrate;
+
+    #[test]
+    pub fn migrates_fixture() {
+        let v2: Value = serde_json::from_str(include_str!("fixtures/v2.json")).unwrap();
+        let v3: Value = serde_json::from_str(include_str!("fixtures/v3.json")).unwrap();
+
+        assert_eq!(migrate(v2).unwrap(), v3);
+    }
+
+    #[test]
+    pub fn migrates_trashed_entries() {
+        let v2 = serde_json::json!({
+            "entries": {},
+            "users": {},
+            "trash": { "hase": [{
+                "entry": { "timestamp": "2021-05-01T10:00:00Z" },
+                "deleted_at": "2021-05-02T10:00:00Z"
+            }] },
+            "database_version": "V2"
+        });
+
+        let v3 = migrate(v2).unwrap();
+
+        assert_eq!(
+            v3["trash"]["hase"][0]["entry"]["recorded_at"],
+            "2021-05-01T10:00:00Z"
+        );
+    }
+}
@@ -0,0 +1,21 @@
+use serde_json::Value;
+
+pub fn migrate(mut database: Value) -> serde_json::Result<Value> {
+    database["database_version"] = Value::from("V4");
+    Ok(database)
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::Value;
+
+    use super::migrate;
+
+    #[test]
+    pub fn migrates_fixture() {
+        let v3: Value = serde_json::from_str(include_str!("fixtures/v3.json")).unwrap();
+        let v4: Value = serde_json::from_str(include_str!("fixtures/v4.json")).unwrap();
+
+        assert_eq!(migrate(v3).unwrap(), v4);
+    }
+}
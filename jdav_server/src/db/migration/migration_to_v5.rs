@@ -0,0 +1,46 @@
+use serde_json::{Map, Value};
+
+/// Kilometers were stored as `{"kilometers": 2.5}`, which is also how a
+/// distance measurement is written, so only the field name changes.
+pub fn rename_kilometers(entry: &mut Map<String, Value>) {
+    if let Some(kilometers) = entry.remove("kilometers") {
+        entry.entry("measurement").or_insert(kilometers);
+    }
+}
+
+pub fn migrate(mut database: Value) -> serde_json::Result<Value> {
+    if let Some(entries) = database["entries"].as_object_mut() {
+        for entry in entries
+            .values_mut()
+            .filter_map(Value::as_array_mut)
+            .flatten()
+            .filter_map(Value::as_object_mut)
+        {
+            rename_kilometers(entry);
+        }
+    }
+    if let Some(trash) = database["trash"].as_object_mut() {
+        for trashed in trash.values_mut().filter_map(Value::as_array_mut).flatten() {
+            if let Some(entry) = trashed["entry"].as_object_mut() {
+                rename_kilometers(entry);
+            }
+        }
+    }
+    database["database_version"] = Value::from("V5");
+    Ok(database)
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::Value;
+
+    use super::migrate;
+
+    #[test]
+    pub fn migrates_fixture() {
+        let v4: Value = serde_json::from_str(include_str!("fixtures/v4.json")).unwrap();
+        let v5: Value = serde_json::from_str(include_str!("fixtures/v5.json")).unwrap();
+
+        assert_eq!(migrate(v4).unwrap(), v5);
+    }
+}
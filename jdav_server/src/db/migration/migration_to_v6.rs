@@ -0,0 +1,66 @@
+use serde_json::Value;
+use shared::Decimal;
+
+fn round(entry: &mut Value, pointer: &str) {
+    if let Some(value) = entry.pointer_mut(pointer) {
+        if let Some(decimal) = value.as_f64().and_then(Decimal::from_f64) {
+            *value = Value::from(decimal.to_f64());
+        }
+    }
+}
+
+/// Distances and points used to be `f32`, so stored values like `2.3000002`
+/// are rounded to the thousandths they are kept in now.
+fn round_entry(entry: &mut Value) {
+    round(entry, "/measurement/kilometers");
+    round(entry, "/measurement/metres");
+    round(entry, "/points");
+}
+
+pub fn migrate(mut database: Value) -> serde_json::Result<Value> {
+    if let Some(entries) = database["entries"].as_object_mut() {
+        for entry in entries
+            .values_mut()
+            .filter_map(Value::as_array_mut)
+            .flatten()
+        {
+            round_entry(entry);
+        }
+    }
+    if let Some(trash) = database["trash"].as_object_mut() {
+        for trashed in trash.values_mut().filter_map(Value::as_array_mut).flatten() {
+            round_entry(&mut trashed["entry"]);
+        }
+    }
+    database["database_version"] = Value::from("V6");
+    Ok(database)
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::{json, Value};
+
+    use super::migrate;
+
+    #[test]
+    pub fn migrates_fixture() {
+        let v5: Value = serde_json::from_str(include_str!("fixtures/v5.json")).unwrap();
+        let v6: Value = serde_json::from_str(include_str!("fixtures/v6.json")).unwrap();
+
+        assert_eq!(migrate(v5).unwrap(), v6);
+    }
+
+    #[test]
+    pub fn rounds_float_noise() {
+        let entry = json!({ "measurement": { "kilometers": 2.3000002 }, "points": 0.57500005 });
+        let database = json!({ "entries": { "hase": [entry.clone()] }, "trash": {
+            "hase": [{ "entry": entry, "deleted_at": "2021-05-01T10:00:00Z" }]
+        } });
+
+        let migrated = migrate(database).unwrap();
+
+        let expected = json!({ "measurement": { "kilometers": 2.3 }, "points": 0.575 });
+        assert_eq!(migrated["entries"]["hase"][0], expected);
+        assert_eq!(migrated["trash"]["hase"][0]["entry"], expected);
+    }
+}
@@ -0,0 +1,67 @@
+use serde_json::{json, Value};
+use shared::Decimal;
+
+/// Climbing used to be measured in kilometers with 100 points each, now in
+/// metres of elevation with 0.1 points each, so the points stay the same.
+pub fn climb_in_metres(entry: &mut Value) {
+    if entry["kind"] != "Climbing" {
+        return;
+    }
+    let metres = entry
+        .pointer("/measurement/kilometers")
+        .and_then(Value::as_f64)
+        .and_then(Decimal::from_f64)
+        .and_then(|kilometers| kilometers.checked_mul(Decimal::from(1000)));
+    if let Some(metres) = metres {
+        entry["measurement"] = json!({ "metres": metres.to_f64() });
+    }
+}
+
+pub fn migrate(mut database: Value) -> serde_json::Result<Value> {
+    if let Some(entries) = database["entries"].as_object_mut() {
+        for entry in entries
+            .values_mut()
+            .filter_map(Value::as_array_mut)
+            .flatten()
+        {
+            climb_in_metres(entry);
+        }
+    }
+    if let Some(trash) = database["trash"].as_object_mut() {
+        for trashed in trash.values_mut().filter_map(Value::as_array_mut).flatten() {
+            climb_in_metres(&mut trashed["entry"]);
+        }
+    }
+    database["database_version"] = Value::from("V7");
+    Ok(database)
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::{json, Value};
+
+    use super::migrate;
+
+    #[test]
+    pub fn migrates_fixture() {
+        let v6: Value = serde_json::from_str(include_str!("fixtures/v6.json")).unwrap();
+        let v7: Value = serde_json::from_str(include_str!("fixtures/v7.json")).unwrap();
+
+        assert_eq!(migrate(v6).unwrap(), v7);
+    }
+
+    #[test]
+    pub fn measures_climbing_in_metres() {
+        let climbing = json!({ "kind": "Climbing", "measurement": { "kilometers": 1.25 } });
+        let running = json!({ "kind": "Running", "measurement": { "kilometers": 1.25 } });
+        let database = json!({ "entries": { "hase": [climbing.clone(), running.clone()] },
+            "trash": { "hase": [{ "entry": climbing, "deleted_at": "2021-05-01T10:00:00Z" }] } });
+
+        let migrated = migrate(database).unwrap();
+
+        let expected = json!({ "kind": "Climbing", "measurement": { "metres": 1250.0 } });
+        assert_eq!(migrated["entries"]["hase"][0], expected);
+        assert_eq!(migrated["entries"]["hase"][1], running);
+        assert_eq!(migrated["trash"]["hase"][0]["entry"], expected);
+    }
+}
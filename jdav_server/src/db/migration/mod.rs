@@ -1,45 +1,450 @@
-use std::sync::Arc;
+use std::fmt;
+use std::path::Path;
 
 use serde::{Deserialize, Serialize};
-use serde_json::from_reader;
-use tokio::{fs::File, sync::RwLock};
+use serde_json::Value;
+use tokio::fs;
 
 use crate::models::DatabaseModel;
 
 use super::{
-    db::{Database, DATABASE_FILENAME},
-    DatabaseWithVersion,
+    db::LoadError,
+    persistence::{backup, quarantine, write_atomically, BackupConfig},
 };
 
 pub mod migration_to_v1;
+pub mod migration_to_v2;
+pub mod migration_to_v3;
+pub mod migration_to_v4;
+pub mod migration_to_v5;
+pub mod migration_to_v6;
+pub mod migration_to_v7;
 
-pub async fn migrate() -> Result<Database, ()> {
-    let file = File::open(DATABASE_FILENAME).await.map_err(|_| ())?;
+#[derive(Deserialize, Debug, Serialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum DatabaseVersion {
+    V0,
+    V1,
+    V2,
+    V3,
+    V4,
+    V5,
+    V6,
+    V7,
+}
+
+impl DatabaseVersion {
+    pub const CURRENT: DatabaseVersion = DatabaseVersion::V7;
+}
+
+impl fmt::Display for DatabaseVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+pub struct Migration {
+    pub from: DatabaseVersion,
+    pub to: DatabaseVersion,
+    pub description: &'static str,
+    pub apply: fn(Value) -> serde_json::Result<Value>,
+}
 
-    let data = from_reader::<_, DatabaseWithVersion>(file.into_std().await).map_err(|_| ())?;
+pub static MIGRATIONS: &[Migration] = &[
+    Migration {
+        from: DatabaseVersion::V0,
+        to: DatabaseVersion::V1,
+        description: "add database version, sport kind and timestamp to entries",
+        apply: migration_to_v1::migrate,
+    },
+    Migration {
+        from: DatabaseVersion::V1,
+        to: DatabaseVersion::V2,
+        description: "add trash for deleted entries",
+        apply: migration_to_v2::migrate,
+    },
+    Migration {
+        from: DatabaseVersion::V2,
+        to: DatabaseVersion::V3,
+        description: "record when entries were submitted separately from the activity date",
+        apply: migration_to_v3::migrate,
+    },
+    Migration {
+        from: DatabaseVersion::V3,
+        to: DatabaseVersion::V4,
+        description: "allow duration, elevation gain, note and location on entries",
+        apply: migration_to_v4::migrate,
+    },
+    Migration {
+        from: DatabaseVersion::V4,
+        to: DatabaseVersion::V5,
+        description: "store a measurement in the unit of the kind instead of kilometers",
+        apply: migration_to_v5::migrate,
+    },
+    Migration {
+        from: DatabaseVersion::V5,
+        to: DatabaseVersion::V6,
+        description: "round distances and points to the thousandths they are kept in",
+        apply: migration_to_v6::migrate,
+    },
+    Migration {
+        from: DatabaseVersion::V6,
+        to: DatabaseVersion::V7,
+        description: "measure climbing in metres of elevation instead of kilometers",
+        apply: migration_to_v7::migrate,
+    },
+];
 
-    match data.database_version {
-        Some(super::DatabaseVersion::V1) => {
-            let file = File::open(DATABASE_FILENAME).await.map_err(|_| ())?;
+#[derive(Debug)]
+pub struct MigrationStep {
+    pub from: DatabaseVersion,
+    pub to: DatabaseVersion,
+    pub description: &'static str,
+    pub changes: Vec<String>,
+}
 
-            let data = from_reader::<_, DatabaseModel>(file.into_std().await).map_err(|_| ())?;
+#[derive(Debug)]
+pub struct MigrationError {
+    pub version: Option<DatabaseVersion>,
+    pub message: String,
+}
+
+impl fmt::Display for MigrationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.version {
+            Some(version) => write!(f, "migration from {} failed: {}", version, self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+pub fn detect_version(database: &Value) -> Result<DatabaseVersion, MigrationError> {
+    match database.get("database_version") {
+        None | Some(Value::Null) => Ok(DatabaseVersion::V0),
+        Some(version) => serde_json::from_value(version.clone()).map_err(|_| MigrationError {
+            version: None,
+            message: format!("unknown database version {}", version),
+        }),
+    }
+}
+
+pub fn run_migrations(mut database: Value) -> Result<(Value, Vec<MigrationStep>), MigrationError> {
+    let start = detect_version(&database)?;
+    let mut version = start;
+    let mut steps = Vec::new();
+
+    for migration in MIGRATIONS
+        .iter()
+        .filter(|migration| migration.from >= start)
+    {
+        let migrated = (migration.apply)(database.clone()).map_err(|error| MigrationError {
+            version: Some(version),
+            message: error.to_string(),
+        })?;
+        let mut changes = Vec::new();
+        diff("", &database, &migrated, &mut changes);
+        steps.push(MigrationStep {
+            from: migration.from,
+            to: migration.to,
+            description: migration.description,
+            changes,
+        });
+        database = migrated;
+        version = migration.to;
+    }
+    Ok((database, steps))
+}
 
-            Ok(Database {
-                database: Arc::new(RwLock::new(data)),
-            })
+fn diff(path: &str, before: &Value, after: &Value, changes: &mut Vec<String>) {
+    match (before, after) {
+        (Value::Object(before), Value::Object(after)) => {
+            for (key, old) in before {
+                let child = format!("{}/{}", path, key);
+                match after.get(key) {
+                    Some(new) => diff(&child, old, new, changes),
+                    None => changes.push(format!("removed {}", child)),
+                }
+            }
+            for (key, new) in after {
+                if !before.contains_key(key) {
+                    changes.push(format!("added {}/{} = {}", path, key, new));
+                }
+            }
         }
-        None => {
-            let file = File::open(DATABASE_FILENAME).await.map_err(|_| ())?;
-            let data = from_reader::<_, migration_to_v1::DatabaseModel>(file.into_std().await)
-                .map_err(|_| ())?;
-            Ok(Database {
-                database: Arc::new(RwLock::new(data.to_v1())),
-            })
+        (Value::Array(before), Value::Array(after)) => {
+            for (index, old) in before.iter().enumerate() {
+                let child = format!("{}/{}", path, index);
+                match after.get(index) {
+                    Some(new) => diff(&child, old, new, changes),
+                    None => changes.push(format!("removed {}", child)),
+                }
+            }
+            for (index, new) in after.iter().enumerate().skip(before.len()) {
+                changes.push(format!("added {}/{} = {}", path, index, new));
+            }
         }
+        (before, after) if before != after => {
+            changes.push(format!("changed {}: {} -> {}", path, before, after));
+        }
+        _ => {}
     }
 }
 
-#[derive(Deserialize, Debug, Serialize, Clone)]
-pub enum DatabaseVersion {
-    V1,
+/// Loads the database for the server, writing it back right away when it had
+/// to be migrated, so that the migration does not run again on every start.
+pub async fn migrate(path: &Path, backups: &BackupConfig) -> Result<DatabaseModel, LoadError> {
+    match load(path).await {
+        Ok((model, steps)) => {
+            if !steps.is_empty() {
+                save_migrated(path, &model, backups)?;
+                println!(
+                    "Migrated {} to version {}",
+                    path.display(),
+                    DatabaseVersion::CURRENT
+                );
+            }
+            Ok(model)
+        }
+        Err(LoadError::Corrupt {
+            path,
+            line,
+            column,
+            message,
+            ..
+        }) => Err(LoadError::Corrupt {
+            quarantined: quarantine(&path).ok(),
+            path,
+            line,
+            column,
+            message,
+        }),
+        Err(error) => Err(error),
+    }
+}
+
+pub async fn load(path: &Path) -> Result<(DatabaseModel, Vec<MigrationStep>), LoadError> {
+    let content = fs::read(path).await.map_err(|source| {
+        if source.kind() == std::io::ErrorKind::NotFound {
+            LoadError::Missing {
+                path: path.to_owned(),
+            }
+        } else {
+            LoadError::Unreadable {
+                path: path.to_owned(),
+                source,
+            }
+        }
+    })?;
+
+    let database =
+        serde_json::from_slice::<Value>(&content).map_err(|error| corrupt(path, error))?;
+    let (database, steps) = run_migrations(database).map_err(|error| LoadError::Migration {
+        path: path.to_owned(),
+        message: error.to_string(),
+    })?;
+
+    let model = if steps.is_empty() {
+        serde_json::from_slice::<DatabaseModel>(&content)
+    } else {
+        serde_json::from_value::<DatabaseModel>(database)
+    }
+    .map_err(|error| LoadError::Invalid {
+        path: path.to_owned(),
+        message: error.to_string(),
+    })?;
+    Ok((model, steps))
+}
+
+pub async fn migrate_file(
+    path: &Path,
+    backups: &BackupConfig,
+    dry_run: bool,
+) -> Result<(), LoadError> {
+    let (model, steps) = load(path).await?;
+
+    if steps.is_empty() {
+        println!(
+            "{} is already at version {}",
+            path.display(),
+            DatabaseVersion::CURRENT
+        );
+        return Ok(());
+    }
+    for step in &steps {
+        println!("{} -> {}: {}", step.from, step.to, step.description);
+        for change in &step.changes {
+            println!("  {}", change);
+        }
+    }
+    if dry_run {
+        println!("Dry run, {} was not changed", path.display());
+        return Ok(());
+    }
+
+    save_migrated(path, &model, backups)?;
+    println!(
+        "Migrated {} to version {}",
+        path.display(),
+        DatabaseVersion::CURRENT
+    );
+    Ok(())
+}
+
+fn save_migrated(
+    path: &Path,
+    model: &DatabaseModel,
+    backups: &BackupConfig,
+) -> Result<(), LoadError> {
+    let json = serde_json::to_vec(model).expect("error serializing database");
+    backup(path, backups)
+        .and_then(|_| write_atomically(path, &json))
+        .map_err(|source| LoadError::Unreadable {
+            path: path.to_owned(),
+            source,
+        })
+}
+
+fn corrupt(path: &Path, error: serde_json::Error) -> LoadError {
+    LoadError::Corrupt {
+        path: path.to_owned(),
+        line: error.line(),
+        column: error.column(),
+        message: error.to_string(),
+        quarantined: None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+    use std::path::Path;
+
+    use serde_json::Value;
+
+    use super::{detect_version, migrate, run_migrations, DatabaseVersion, MIGRATIONS};
+    use crate::db::db::LoadError;
+    use crate::db::persistence::{list_backups, BackupConfig};
+    use crate::models::DatabaseModel;
+    use crate::test_util::TestDirectory;
+
+    fn backups(directory: &Path) -> BackupConfig {
+        BackupConfig {
+            directory: directory.join("backups"),
+            ..BackupConfig::default()
+        }
+    }
+
+    #[test]
+    pub fn migrations_form_a_chain_to_the_current_version() {
+        let mut version = DatabaseVersion::V0;
+        for migration in MIGRATIONS {
+            assert_eq!(migration.from, version);
+            assert!(migration.to > migration.from);
+            version = migration.to;
+        }
+        assert_eq!(version, DatabaseVersion::CURRENT);
+    }
+
+    #[test]
+    pub fn migrates_oldest_fixture_to_current_model() {
+        let database: Value = serde_json::from_str(include_str!("fixtures/v0.json")).unwrap();
+
+        let (database, steps) = run_migrations(database).unwrap();
+
+        assert_eq!(steps.len(), MIGRATIONS.len());
+        assert_eq!(detect_version(&database).unwrap(), DatabaseVersion::CURRENT);
+        let model: DatabaseModel = serde_json::from_value(database).unwrap();
+        assert_eq!(model.entries["hase"].len(), 2);
+    }
+
+    #[test]
+    pub fn current_fixture_needs_no_migration() {
+        let database: Value = serde_json::from_str(include_str!("fixtures/v7.json")).unwrap();
+
+        let (migrated, steps) = run_migrations(database.clone()).unwrap();
+
+        assert!(steps.is_empty());
+        assert_eq!(migrated, database);
+    }
+
+    #[test]
+    pub fn reports_changes() {
+        let database: Value = serde_json::from_str(include_str!("fixtures/v0.json")).unwrap();
+
+        let (_, steps) = run_migrations(database).unwrap();
+
+        assert!(steps[0]
+            .changes
+            .contains(&"added /database_version = \"V1\"".to_owned()));
+    }
+
+    #[test]
+    pub fn rejects_unknown_version() {
+        let database = serde_json::json!({ "database_version": "V99" });
+
+        assert!(run_migrations(database).is_err());
+    }
+
+    #[tokio::test]
+    pub async fn reports_missing_database() {
+        let directory = TestDirectory::create();
+
+        let result = migrate(&directory.join("database.json"), &backups(&directory)).await;
+
+        assert!(matches!(result, Err(LoadError::Missing { .. })));
+    }
+
+    #[tokio::test]
+    pub async fn quarantines_corrupt_database() {
+        let directory = TestDirectory::create();
+        let path = directory.join("database.json");
+        fs::write(&path, "{\n  \"entries\": {},\n  \"users\": [\n").unwrap();
+
+        let result = migrate(&path, &backups(&directory)).await;
+
+        match result {
+            Err(LoadError::Corrupt {
+                line, quarantined, ..
+            }) => {
+                assert_eq!(line, 4);
+                assert!(quarantined.unwrap().exists());
+                assert!(!path.exists());
+            }
+            _ => panic!("expected a corrupt database"),
+        }
+    }
+
+    #[tokio::test]
+    pub async fn saves_migrated_database() {
+        let directory = TestDirectory::create();
+        let path = directory.join("database.json");
+        fs::write(&path, include_str!("fixtures/v0.json")).unwrap();
+
+        let model = migrate(&path, &backups(&directory)).await.unwrap();
+
+        let saved: Value = serde_json::from_slice(&fs::read(&path).unwrap()).unwrap();
+        assert_eq!(detect_version(&saved).unwrap(), DatabaseVersion::CURRENT);
+        let saved: DatabaseModel = serde_json::from_value(saved).unwrap();
+        assert_eq!(saved.entries, model.entries);
+        let saved_backups = list_backups(&backups(&directory)).unwrap();
+        assert_eq!(
+            fs::read_to_string(&saved_backups[0]).unwrap(),
+            include_str!("fixtures/v0.json")
+        );
+    }
+
+    #[tokio::test]
+    pub async fn keeps_valid_json_of_another_shape() {
+        let directory = TestDirectory::create();
+        let path = directory.join("database.json");
+        let database = format!(
+            r#"{{"entries": [], "database_version": "{}"}}"#,
+            DatabaseVersion::CURRENT
+        );
+        fs::write(&path, database).unwrap();
+
+        let result = migrate(&path, &backups(&directory)).await;
+
+        assert!(matches!(result, Err(LoadError::Invalid { .. })));
+        assert!(path.exists());
+    }
 }
@@ -1,11 +1,8 @@
-use serde::Deserialize;
-
-use self::migration::DatabaseVersion;
-
+#[allow(clippy::module_inception)]
 pub mod db;
+pub mod event_log;
+pub mod json;
 pub mod migration;
-
-#[derive(Deserialize, Debug)]
-pub struct DatabaseWithVersion {
-    database_version: Option<DatabaseVersion>,
-}
+pub mod persistence;
+pub mod sqlite;
+pub mod storage;
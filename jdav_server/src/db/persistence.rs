@@ -0,0 +1,218 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use chrono::prelude::*;
+
+pub static BACKUP_DIRECTORY: &str = "./backups";
+
+#[derive(Debug, Clone)]
+pub struct BackupConfig {
+    pub directory: PathBuf,
+    pub count: usize,
+    /// The running server backs up at most once per interval, so that busy
+    /// hours do not push older backups out.
+    pub interval: Duration,
+}
+
+impl Default for BackupConfig {
+    fn default() -> Self {
+        BackupConfig {
+            directory: PathBuf::from(BACKUP_DIRECTORY),
+            count: 5,
+            interval: Duration::from_secs(60 * 60),
+        }
+    }
+}
+
+pub fn write_atomically(path: &Path, content: &[u8]) -> io::Result<()> {
+    let mut temp_name = path.as_os_str().to_owned();
+    temp_name.push(".tmp");
+    let temp_path = PathBuf::from(temp_name);
+
+    let mut file = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(&temp_path)?;
+    file.write_all(content)?;
+    file.sync_all()?;
+    fs::rename(&temp_path, path)?;
+
+    if let Some(parent) = path
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+    {
+        File::open(parent)?.sync_all()?;
+    }
+    Ok(())
+}
+
+/// [`write_atomically`] on the blocking thread pool, for callers on the async
+/// threads.
+pub async fn write_atomically_async(path: &Path, content: Vec<u8>) -> io::Result<()> {
+    let path = path.to_owned();
+    tokio::task::spawn_blocking(move || write_atomically(&path, &content))
+        .await
+        .map_err(io::Error::other)?
+}
+
+pub fn backup(path: &Path, config: &BackupConfig) -> io::Result<Option<PathBuf>> {
+    if config.count == 0 || !path.exists() {
+        return Ok(None);
+    }
+    fs::create_dir_all(&config.directory)?;
+
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "database".to_owned());
+    let backup_path = config.directory.join(format!(
+        "{}-{}.json",
+        stem,
+        Utc::now().format("%Y%m%dT%H%M%S%.6fZ")
+    ));
+    if fs::hard_link(path, &backup_path).is_err() {
+        fs::copy(path, &backup_path)?;
+    }
+
+    let backups = list_backups(config)?;
+    let obsolete = backups.len().saturating_sub(config.count);
+    for old_backup in backups.iter().take(obsolete) {
+        fs::remove_file(old_backup)?;
+    }
+    Ok(Some(backup_path))
+}
+
+pub fn quarantine(path: &Path) -> io::Result<PathBuf> {
+    let mut quarantine_name = path.as_os_str().to_owned();
+    quarantine_name.push(format!(
+        ".corrupt-{}",
+        Utc::now().format("%Y%m%dT%H%M%S%.6fZ")
+    ));
+    let quarantine_path = PathBuf::from(quarantine_name);
+    fs::rename(path, &quarantine_path)?;
+    Ok(quarantine_path)
+}
+
+pub fn list_backups(config: &BackupConfig) -> io::Result<Vec<PathBuf>> {
+    if !config.directory.exists() {
+        return Ok(Vec::new());
+    }
+    let mut backups = fs::read_dir(&config.directory)?
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| path.extension() == Some("json".as_ref()))
+        .collect::<Vec<_>>();
+    backups.sort();
+    Ok(backups)
+}
+
+fn resolve_backup(name: &Path, config: &BackupConfig) -> Option<PathBuf> {
+    if name.is_file() {
+        Some(name.to_owned())
+    } else {
+        let in_directory = config.directory.join(name);
+        if in_directory.is_file() {
+            Some(in_directory)
+        } else {
+            None
+        }
+    }
+}
+
+pub fn restore(name: &Path, target: &Path, config: &BackupConfig) -> io::Result<PathBuf> {
+    let source = resolve_backup(name, config).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("backup {} not found", name.display()),
+        )
+    })?;
+    let content = fs::read(&source)?;
+    serde_json::from_slice::<serde_json::Value>(&content)
+        .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+
+    backup(target, config)?;
+    write_atomically(target, &content)?;
+    Ok(source)
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use super::{backup, list_backups, restore, write_atomically, BackupConfig};
+    use crate::test_util::TestDirectory;
+
+    #[test]
+    pub fn replaces_file_contents() {
+        let directory = TestDirectory::create();
+        let path = directory.join("database.json");
+
+        write_atomically(&path, b"{\"old\": true}").unwrap();
+        write_atomically(&path, b"{\"new\": true}").unwrap();
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "{\"new\": true}");
+        assert!(!directory.join("database.json.tmp").exists());
+    }
+
+    #[test]
+    pub fn keeps_configured_number_of_backups() {
+        let directory = TestDirectory::create();
+        let path = directory.join("database.json");
+        let config = BackupConfig {
+            directory: directory.join("backups"),
+            count: 2,
+            ..BackupConfig::default()
+        };
+
+        for generation in 0..4 {
+            write_atomically(&path, format!("{}", generation).as_bytes()).unwrap();
+            backup(&path, &config).unwrap();
+        }
+
+        let backups = list_backups(&config).unwrap();
+        assert_eq!(backups.len(), 2);
+        assert_eq!(fs::read_to_string(&backups[0]).unwrap(), "2");
+        assert_eq!(fs::read_to_string(&backups[1]).unwrap(), "3");
+    }
+
+    #[test]
+    pub fn restores_backup_by_name() {
+        let directory = TestDirectory::create();
+        let path = directory.join("database.json");
+        let config = BackupConfig {
+            directory: directory.join("backups"),
+            count: 5,
+            ..BackupConfig::default()
+        };
+
+        write_atomically(&path, b"{\"generation\": 1}").unwrap();
+        let first = backup(&path, &config).unwrap().unwrap();
+        write_atomically(&path, b"{\"generation\": 2}").unwrap();
+
+        restore(first.file_name().unwrap().as_ref(), &path, &config).unwrap();
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "{\"generation\": 1}");
+        assert_eq!(list_backups(&config).unwrap().len(), 2);
+    }
+
+    #[test]
+    pub fn refuses_to_restore_invalid_json() {
+        let directory = TestDirectory::create();
+        let path = directory.join("database.json");
+        let config = BackupConfig {
+            directory: directory.join("backups"),
+            count: 5,
+            ..BackupConfig::default()
+        };
+        let broken = directory.join("broken.json");
+
+        write_atomically(&path, b"{}").unwrap();
+        fs::write(&broken, b"{\"entries\": ").unwrap();
+
+        assert!(restore(&broken, &path, &config).is_err());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "{}");
+    }
+}
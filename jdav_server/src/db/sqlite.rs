@@ -0,0 +1,736 @@
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use chrono::prelude::*;
+use rusqlite::types::Type;
+use rusqlite::{params, Connection, OptionalExtension, Row, ToSql};
+use shared::{Decimal, EntryDetails, Id, KilometerEntry, Kind, TrashedEntry, Unit};
+use uuid::Uuid;
+
+use crate::models::{EntryDatabaseModel, PasswordParams, User, UserKey};
+
+use super::storage::{Storage, StorageError};
+
+pub static SQLITE_FILENAME: &str = "./database.sqlite";
+
+static SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS users (
+        name TEXT PRIMARY KEY NOT NULL,
+        hash TEXT NOT NULL,
+        salt TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS entries (
+        seq INTEGER PRIMARY KEY AUTOINCREMENT,
+        id TEXT NOT NULL UNIQUE,
+        user TEXT NOT NULL,
+        kilometers REAL NOT NULL,
+        kind TEXT NOT NULL,
+        timestamp TEXT NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS entries_by_user ON entries (user);
+    CREATE TABLE IF NOT EXISTS trash (
+        seq INTEGER PRIMARY KEY AUTOINCREMENT,
+        id TEXT NOT NULL UNIQUE,
+        user TEXT NOT NULL,
+        kilometers REAL NOT NULL,
+        kind TEXT NOT NULL,
+        timestamp TEXT NOT NULL,
+        deleted_at TEXT NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS trash_by_user ON trash (user);
+";
+
+static SCHEMA_UPGRADES: &[&str] = &[
+    "
+    ALTER TABLE entries ADD COLUMN recorded_at TEXT;
+    UPDATE entries SET recorded_at = timestamp;
+    ALTER TABLE trash ADD COLUMN recorded_at TEXT;
+    UPDATE trash SET recorded_at = timestamp;
+",
+    "
+    ALTER TABLE entries ADD COLUMN duration_minutes INTEGER;
+    ALTER TABLE entries ADD COLUMN elevation_gain REAL;
+    ALTER TABLE entries ADD COLUMN note TEXT;
+    ALTER TABLE entries ADD COLUMN location TEXT;
+    ALTER TABLE trash ADD COLUMN duration_minutes INTEGER;
+    ALTER TABLE trash ADD COLUMN elevation_gain REAL;
+    ALTER TABLE trash ADD COLUMN note TEXT;
+    ALTER TABLE trash ADD COLUMN location TEXT;
+",
+    "
+    ALTER TABLE entries ADD COLUMN points REAL NOT NULL DEFAULT 0;
+    ALTER TABLE trash ADD COLUMN points REAL NOT NULL DEFAULT 0;
+",
+    // kinds used to be stored by their path, now by the id of the catalogue
+    "
+    UPDATE entries SET kind = CASE kind
+        WHEN 'laufen' THEN 'Running'
+        WHEN 'radfahren' THEN 'Biking'
+        WHEN 'klettern' THEN 'Climbing'
+        WHEN 'skaten' THEN 'Skating'
+        WHEN 'wandern' THEN 'Hiking'
+        WHEN 'schwimmen' THEN 'Swimming'
+        WHEN 'nordic_walking' THEN 'NordicWalking'
+        ELSE kind END;
+    UPDATE trash SET kind = CASE kind
+        WHEN 'laufen' THEN 'Running'
+        WHEN 'radfahren' THEN 'Biking'
+        WHEN 'klettern' THEN 'Climbing'
+        WHEN 'skaten' THEN 'Skating'
+        WHEN 'wandern' THEN 'Hiking'
+        WHEN 'schwimmen' THEN 'Swimming'
+        WHEN 'nordic_walking' THEN 'NordicWalking'
+        ELSE kind END;
+",
+    "
+    ALTER TABLE entries RENAME COLUMN kilometers TO amount;
+    ALTER TABLE entries ADD COLUMN unit TEXT NOT NULL DEFAULT 'distance';
+    ALTER TABLE trash RENAME COLUMN kilometers TO amount;
+    ALTER TABLE trash ADD COLUMN unit TEXT NOT NULL DEFAULT 'distance';
+",
+    // amounts and points are kept as integer thousandths instead of floats
+    "
+    CREATE TABLE entries_exact (
+        seq INTEGER PRIMARY KEY AUTOINCREMENT,
+        id TEXT NOT NULL UNIQUE,
+        user TEXT NOT NULL,
+        amount INTEGER NOT NULL,
+        unit TEXT NOT NULL DEFAULT 'distance',
+        kind TEXT NOT NULL,
+        timestamp TEXT NOT NULL,
+        recorded_at TEXT,
+        duration_minutes INTEGER,
+        elevation_gain REAL,
+        note TEXT,
+        location TEXT,
+        points INTEGER NOT NULL DEFAULT 0
+    );
+    INSERT INTO entries_exact SELECT seq, id, user, CAST(ROUND(amount * 1000) AS INTEGER),
+        unit, kind, timestamp, recorded_at, duration_minutes, elevation_gain, note, location,
+        CAST(ROUND(points * 1000) AS INTEGER) FROM entries;
+    DROP TABLE entries;
+    ALTER TABLE entries_exact RENAME TO entries;
+    CREATE INDEX entries_by_user ON entries (user);
+    CREATE TABLE trash_exact (
+        seq INTEGER PRIMARY KEY AUTOINCREMENT,
+        id TEXT NOT NULL UNIQUE,
+        user TEXT NOT NULL,
+        amount INTEGER NOT NULL,
+        unit TEXT NOT NULL DEFAULT 'distance',
+        kind TEXT NOT NULL,
+        timestamp TEXT NOT NULL,
+        recorded_at TEXT,
+        duration_minutes INTEGER,
+        elevation_gain REAL,
+        note TEXT,
+        location TEXT,
+        points INTEGER NOT NULL DEFAULT 0,
+        deleted_at TEXT NOT NULL
+    );
+    INSERT INTO trash_exact SELECT seq, id, user, CAST(ROUND(amount * 1000) AS INTEGER),
+        unit, kind, timestamp, recorded_at, duration_minutes, elevation_gain, note, location,
+        CAST(ROUND(points * 1000) AS INTEGER), deleted_at FROM trash;
+    DROP TABLE trash;
+    ALTER TABLE trash_exact RENAME TO trash;
+    CREATE INDEX trash_by_user ON trash (user);
+",
+    // users without parameters keep their SHA-256 hash until they log in
+    "
+    ALTER TABLE users ADD COLUMN algorithm TEXT NOT NULL DEFAULT 'sha256';
+    ALTER TABLE users ADD COLUMN memory_kib INTEGER;
+    ALTER TABLE users ADD COLUMN iterations INTEGER;
+    ALTER TABLE users ADD COLUMN lanes INTEGER;
+",
+    // climbing is measured in metres of elevation instead of kilometers
+    "
+    UPDATE entries SET unit = 'elevation', amount = amount * 1000
+        WHERE kind = 'Climbing' AND unit = 'distance';
+    UPDATE trash SET unit = 'elevation', amount = amount * 1000
+        WHERE kind = 'Climbing' AND unit = 'distance';
+",
+];
+
+static ENTRY_COLUMNS: &str = "id, user, amount, unit, kind, timestamp, recorded_at, \
+    duration_minutes, elevation_gain, note, location, points";
+
+pub struct SqliteStorage {
+    connection: Arc<Mutex<Connection>>,
+}
+
+impl SqliteStorage {
+    pub fn open<P: AsRef<Path>>(path: P) -> rusqlite::Result<Self> {
+        Self::with_connection(Connection::open(path)?)
+    }
+
+    #[cfg(test)]
+    pub fn open_in_memory() -> rusqlite::Result<Self> {
+        Self::with_connection(Connection::open_in_memory()?)
+    }
+
+    fn with_connection(mut connection: Connection) -> rusqlite::Result<Self> {
+        connection.execute_batch(SCHEMA)?;
+        let version: i64 =
+            connection.query_row("PRAGMA user_version", params![], |row| row.get(0))?;
+        for (index, upgrade) in SCHEMA_UPGRADES.iter().enumerate().skip(version as usize) {
+            let transaction = connection.transaction()?;
+            transaction.execute_batch(upgrade)?;
+            transaction.pragma_update(None, "user_version", &(index as i64 + 1))?;
+            transaction.commit()?;
+        }
+        Ok(SqliteStorage {
+            connection: Arc::new(Mutex::new(connection)),
+        })
+    }
+
+    /// Runs a query on the blocking thread pool, so that waiting for the disk
+    /// does not stall the other requests.
+    async fn run<T, F>(&self, query: F) -> T
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut Connection) -> T + Send + 'static,
+    {
+        let connection = self.connection.clone();
+        tokio::task::spawn_blocking(move || query(&mut connection.lock().unwrap()))
+            .await
+            .expect("database query panicked")
+    }
+}
+
+fn entry_from_row(row: &Row) -> rusqlite::Result<(String, KilometerEntry)> {
+    let id: String = row.get("id")?;
+    let id =
+        Uuid::parse_str(&id).map_err(|error| conversion_error(row, "id", Type::Text, error))?;
+    let unit: String = row.get("unit")?;
+    let unit = unit
+        .parse::<Unit>()
+        .map_err(|error| conversion_error(row, "unit", Type::Text, error))?;
+    let elevation_gain = match row.get::<_, Option<f64>>("elevation_gain")? {
+        Some(elevation) => Some(Decimal::from_f64(elevation).ok_or_else(|| {
+            conversion_error(
+                row,
+                "elevation_gain",
+                Type::Real,
+                format!("{} is out of range", elevation),
+            )
+        })?),
+        None => None,
+    };
+    Ok((
+        row.get("user")?,
+        KilometerEntry {
+            id: Id { id },
+            measurement: unit.measure(Decimal::from_thousandths(row.get("amount")?)),
+            kind: Kind(row.get("kind")?),
+            timestamp: row.get::<_, DateTime<Utc>>("timestamp")?,
+            recorded_at: row.get::<_, DateTime<Utc>>("recorded_at")?,
+            points: Decimal::from_thousandths(row.get("points")?),
+            details: EntryDetails {
+                duration_minutes: row.get("duration_minutes")?,
+                elevation_gain,
+                note: row.get("note")?,
+                location: row.get("location")?,
+            },
+        },
+    ))
+}
+
+fn conversion_error<E>(row: &Row, column: &str, kind: Type, error: E) -> rusqlite::Error
+where
+    E: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    let index = row.column_index(column).unwrap_or_default();
+    rusqlite::Error::FromSqlConversionFailure(index, kind, error.into())
+}
+
+fn trashed_entry_from_row(row: &Row) -> rusqlite::Result<TrashedEntry> {
+    let (_, entry) = entry_from_row(row)?;
+    Ok(TrashedEntry {
+        entry,
+        deleted_at: row.get("deleted_at")?,
+    })
+}
+
+/// Reads all rows of a query. Rows that cannot be read are logged with their
+/// sequence number and left out, instead of being made up or failing every
+/// read of the table.
+fn read_rows<T, P, F>(connection: &Connection, sql: &str, params: P, read: F) -> Vec<T>
+where
+    P: IntoIterator,
+    P::Item: ToSql,
+    F: Fn(&Row) -> rusqlite::Result<T>,
+{
+    let result = connection.prepare(sql).and_then(|mut statement| {
+        let mut rows = statement.query(params)?;
+        let mut read_rows = Vec::new();
+        while let Some(row) = rows.next()? {
+            match read(row) {
+                Ok(value) => read_rows.push(value),
+                Err(error) => eprintln!(
+                    "Skipping unreadable row {} of \"{}\": {}",
+                    row.get::<_, i64>("seq").unwrap_or_default(),
+                    sql,
+                    error
+                ),
+            }
+        }
+        Ok(read_rows)
+    });
+    result.unwrap_or_else(|error| {
+        eprintln!("Error reading from database: {}", error);
+        Vec::new()
+    })
+}
+
+/// Moves an entry between the entries and the trash table, returning whether
+/// it was found. Both statements are run with the same parameters.
+fn move_entry(
+    connection: &mut Connection,
+    insert: &str,
+    delete: &str,
+    params: &[&dyn ToSql],
+) -> Result<bool, StorageError> {
+    let transaction = connection.transaction()?;
+    let moved = transaction.execute(insert, params)?;
+    transaction.execute(delete, params)?;
+    transaction.commit()?;
+    Ok(moved == 1)
+}
+
+fn params_to_columns(
+    params: PasswordParams,
+) -> (&'static str, Option<u32>, Option<u32>, Option<u32>) {
+    match params {
+        PasswordParams::Sha256 => ("sha256", None, None, None),
+        PasswordParams::Argon2id {
+            memory_kib,
+            iterations,
+            lanes,
+        } => ("argon2id", Some(memory_kib), Some(iterations), Some(lanes)),
+    }
+}
+
+fn params_from_row(row: &Row) -> rusqlite::Result<PasswordParams> {
+    let algorithm: String = row.get("algorithm")?;
+    Ok(match algorithm.as_str() {
+        "argon2id" => PasswordParams::Argon2id {
+            memory_kib: row.get("memory_kib")?,
+            iterations: row.get("iterations")?,
+            lanes: row.get("lanes")?,
+        },
+        _ => PasswordParams::Sha256,
+    })
+}
+
+#[async_trait]
+impl Storage for SqliteStorage {
+    async fn insert_user(&self, name: UserKey, user: User) -> Result<bool, StorageError> {
+        self.run(move |connection| {
+            let (algorithm, memory_kib, iterations, lanes) = params_to_columns(user.params);
+            let inserted = connection.execute(
+                "INSERT OR IGNORE INTO users (name, hash, salt, algorithm, memory_kib, iterations, lanes)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                params![name, user.hash, user.salt, algorithm, memory_kib, iterations, lanes],
+            )?;
+            Ok(inserted == 1)
+        })
+        .await
+    }
+
+    async fn get_user(&self, name: &str) -> Result<Option<User>, StorageError> {
+        let name = name.to_owned();
+        self.run(move |connection| {
+            Ok(connection
+                .query_row(
+                    "SELECT hash, salt, algorithm, memory_kib, iterations, lanes FROM users
+                     WHERE name = ?1",
+                    params![name],
+                    |row| {
+                        Ok(User {
+                            hash: row.get("hash")?,
+                            salt: row.get("salt")?,
+                            params: params_from_row(row)?,
+                        })
+                    },
+                )
+                .optional()?)
+        })
+        .await
+    }
+
+    async fn update_user(&self, name: &str, user: User) -> Result<bool, StorageError> {
+        let name = name.to_owned();
+        self.run(move |connection| {
+            let (algorithm, memory_kib, iterations, lanes) = params_to_columns(user.params);
+            let updated = connection.execute(
+                "UPDATE users SET hash = ?2, salt = ?3, algorithm = ?4, memory_kib = ?5,
+                 iterations = ?6, lanes = ?7 WHERE name = ?1",
+                params![name, user.hash, user.salt, algorithm, memory_kib, iterations, lanes],
+            )?;
+            Ok(updated == 1)
+        })
+        .await
+    }
+
+    async fn insert_entry(&self, user: &str, entry: KilometerEntry) -> Result<(), StorageError> {
+        let user = user.to_owned();
+        self.run(move |connection| {
+            connection.execute(
+                &format!(
+                    "INSERT INTO entries ({}) \
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+                    ENTRY_COLUMNS
+                ),
+                params![
+                    entry.id.id.to_string(),
+                    user,
+                    entry.measurement.value().thousandths(),
+                    entry.measurement.unit().as_str(),
+                    entry.kind.as_str(),
+                    entry.timestamp,
+                    entry.recorded_at,
+                    entry.details.duration_minutes,
+                    entry.details.elevation_gain.map(Decimal::to_f64),
+                    entry.details.note,
+                    entry.details.location,
+                    entry.points.thousandths()
+                ],
+            )?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn get_entry(&self, id: &Id) -> Option<(UserKey, KilometerEntry)> {
+        let id = id.id.to_string();
+        self.run(move |connection| {
+            read_rows(
+                connection,
+                "SELECT * FROM entries WHERE id = ?1",
+                params![id],
+                entry_from_row,
+            )
+            .pop()
+        })
+        .await
+    }
+
+    async fn update_entry(&self, user: &str, entry: KilometerEntry) -> Result<bool, StorageError> {
+        let user = user.to_owned();
+        self.run(move |connection| {
+            let updated = connection.execute(
+                "UPDATE entries SET amount = ?1, unit = ?2, kind = ?3, timestamp = ?4,
+                 duration_minutes = ?5, elevation_gain = ?6, note = ?7, location = ?8,
+                 points = ?9
+                 WHERE id = ?10 AND user = ?11",
+                params![
+                    entry.measurement.value().thousandths(),
+                    entry.measurement.unit().as_str(),
+                    entry.kind.as_str(),
+                    entry.timestamp,
+                    entry.details.duration_minutes,
+                    entry.details.elevation_gain.map(Decimal::to_f64),
+                    entry.details.note,
+                    entry.details.location,
+                    entry.points.thousandths(),
+                    entry.id.id.to_string(),
+                    user
+                ],
+            )?;
+            Ok(updated == 1)
+        })
+        .await
+    }
+
+    async fn delete_entry(
+        &self,
+        user: &str,
+        id: &Id,
+        deleted_at: DateTime<Utc>,
+    ) -> Result<bool, StorageError> {
+        let user = user.to_owned();
+        let id = id.id.to_string();
+        self.run(move |connection| {
+            move_entry(
+                connection,
+                &format!(
+                    "INSERT INTO trash ({0}, deleted_at) SELECT {0}, ?1 FROM entries
+                     WHERE id = ?2 AND user = ?3",
+                    ENTRY_COLUMNS
+                ),
+                "DELETE FROM entries WHERE id = ?2 AND user = ?3",
+                params![deleted_at, id, user],
+            )
+        })
+        .await
+    }
+
+    async fn restore_entry(&self, user: &str, id: &Id) -> Result<bool, StorageError> {
+        let user = user.to_owned();
+        let id = id.id.to_string();
+        self.run(move |connection| {
+            move_entry(
+                connection,
+                &format!(
+                    "INSERT INTO entries ({0}) SELECT {0} FROM trash WHERE id = ?1 AND user = ?2",
+                    ENTRY_COLUMNS
+                ),
+                "DELETE FROM trash WHERE id = ?1 AND user = ?2",
+                params![id, user],
+            )
+        })
+        .await
+    }
+
+    async fn get_trash(&self, user: &str) -> Vec<TrashedEntry> {
+        let user = user.to_owned();
+        self.run(move |connection| {
+            read_rows(
+                connection,
+                "SELECT * FROM trash WHERE user = ?1 ORDER BY seq",
+                params![user],
+                trashed_entry_from_row,
+            )
+        })
+        .await
+    }
+
+    async fn purge_trash(&self, before: DateTime<Utc>) -> Result<usize, StorageError> {
+        self.run(move |connection| {
+            Ok(connection.execute("DELETE FROM trash WHERE deleted_at < ?1", params![before])?)
+        })
+        .await
+    }
+
+    async fn get_entries(&self, user: &str) -> Vec<KilometerEntry> {
+        let user = user.to_owned();
+        self.run(move |connection| {
+            read_rows(
+                connection,
+                "SELECT * FROM entries WHERE user = ?1 ORDER BY seq",
+                params![user],
+                entry_from_row,
+            )
+            .into_iter()
+            .map(|(_, entry)| entry)
+            .collect()
+        })
+        .await
+    }
+
+    async fn get_all_entries(&self) -> EntryDatabaseModel {
+        self.run(|connection| {
+            let mut entries = EntryDatabaseModel::new();
+            let rows = read_rows(
+                connection,
+                "SELECT * FROM entries ORDER BY seq",
+                params![],
+                entry_from_row,
+            );
+            for (user, entry) in rows {
+                entries.entry(user).or_default().push(entry);
+            }
+            entries
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::prelude::*;
+    use shared::{Decimal, EntryDetails, Id, KilometerEntry, Kind, Measurement};
+    use uuid::Uuid;
+
+    use rusqlite::{params, Connection};
+
+    use super::{SqliteStorage, SCHEMA};
+    use crate::db::storage::Storage;
+    use crate::models::{PasswordParams, User};
+
+    fn entry(kilometers: u32, kind: &str, multiplier: u32) -> KilometerEntry {
+        KilometerEntry {
+            id: Id { id: Uuid::new_v4() },
+            measurement: Measurement::Distance {
+                kilometers: Decimal::from(kilometers),
+            },
+            timestamp: Utc.ymd(2021, 5, 1).and_hms(10, 0, 0),
+            recorded_at: Utc.ymd(2021, 5, 2).and_hms(10, 0, 0),
+            points: Decimal::from(kilometers * multiplier),
+            kind: Kind::new(kind),
+            details: Default::default(),
+        }
+    }
+
+    #[tokio::test]
+    pub async fn rejects_duplicate_users() {
+        let storage = SqliteStorage::open_in_memory().unwrap();
+        let user = User {
+            hash: "hash".to_owned(),
+            salt: "salt".to_owned(),
+            params: PasswordParams::Sha256,
+        };
+
+        assert!(storage
+            .insert_user("hase".to_owned(), user.clone())
+            .await
+            .unwrap());
+        assert!(!storage.insert_user("hase".to_owned(), user).await.unwrap());
+        assert_eq!(
+            storage.get_user("hase").await.unwrap().unwrap().salt,
+            "salt"
+        );
+        assert!(storage.get_user("igel").await.unwrap().is_none());
+
+        let rehashed = User {
+            hash: "argon".to_owned(),
+            salt: "pepper".to_owned(),
+            params: PasswordParams::Argon2id {
+                memory_kib: 64,
+                iterations: 1,
+                lanes: 1,
+            },
+        };
+        assert!(storage.update_user("hase", rehashed.clone()).await.unwrap());
+        assert!(!storage.update_user("igel", rehashed.clone()).await.unwrap());
+        let stored = storage.get_user("hase").await.unwrap().unwrap();
+        assert_eq!(stored.hash, "argon");
+        assert_eq!(stored.params, rehashed.params);
+    }
+
+    #[tokio::test]
+    pub async fn stores_and_edits_entries() {
+        let storage = SqliteStorage::open_in_memory().unwrap();
+        let mut first = entry(2, "Running", 1);
+        let second = entry(1, "Climbing", 100);
+
+        storage.insert_entry("hase", first.clone()).await.unwrap();
+        storage.insert_entry("hase", second.clone()).await.unwrap();
+        assert_eq!(
+            storage.get_entries("hase").await,
+            vec![first.clone(), second.clone()]
+        );
+        assert!(storage.insert_entry("igel", first.clone()).await.is_err());
+
+        first.measurement = Measurement::Duration { minutes: 35 };
+        first.points = Decimal::from_thousandths(3_500);
+        first.details = EntryDetails {
+            duration_minutes: Some(90),
+            elevation_gain: Some(Decimal::from(450)),
+            note: Some("Gipfel".to_owned()),
+            location: Some("Kelheim".to_owned()),
+        };
+        assert!(storage.update_entry("hase", first.clone()).await.unwrap());
+        assert!(!storage.update_entry("igel", second.clone()).await.unwrap());
+        assert_eq!(storage.get_entries("hase").await[0], first);
+        assert_eq!(
+            storage.get_entry(&second.id).await,
+            Some(("hase".to_owned(), second.clone()))
+        );
+        assert!(storage.get_entry(&Id { id: Uuid::nil() }).await.is_none());
+
+        let highscore = storage.get_highscore(&Default::default()).await;
+        assert_eq!(highscore.list[0].user, "hase");
+        assert_eq!(highscore.list[0].points, Decimal::from_thousandths(103_500));
+    }
+
+    #[tokio::test]
+    pub async fn skips_unreadable_rows() {
+        let storage = SqliteStorage::open_in_memory().unwrap();
+        let kept = entry(2, "Running", 1);
+        storage.insert_entry("hase", kept.clone()).await.unwrap();
+        storage
+            .connection
+            .lock()
+            .unwrap()
+            .execute(
+                "INSERT INTO entries (id, user, amount, unit, kind, timestamp, recorded_at)
+                 VALUES ('kaputt', 'hase', 1000, 'distance', 'Running', ?1, ?1)",
+                params![Utc.ymd(2021, 5, 1).and_hms(10, 0, 0)],
+            )
+            .unwrap();
+
+        assert_eq!(storage.get_entries("hase").await, vec![kept.clone()]);
+        assert_eq!(storage.get_all_entries().await["hase"], vec![kept]);
+    }
+
+    #[tokio::test]
+    pub async fn upgrades_existing_schema() {
+        let connection = Connection::open_in_memory().unwrap();
+        connection.execute_batch(SCHEMA).unwrap();
+        connection
+            .execute(
+                "INSERT INTO users (name, hash, salt) VALUES ('hase', 'hash', 'salt')",
+                params![],
+            )
+            .unwrap();
+        connection
+            .execute(
+                "INSERT INTO entries (id, user, kilometers, kind, timestamp)
+                 VALUES (?1, 'hase', 2.299999952316284, 'laufen', ?3),
+                        (?2, 'hase', 1.25, 'klettern', ?3)",
+                params![
+                    Uuid::new_v4().to_string(),
+                    Uuid::new_v4().to_string(),
+                    Utc.ymd(2021, 5, 1).and_hms(10, 0, 0)
+                ],
+            )
+            .unwrap();
+
+        let storage = SqliteStorage::with_connection(connection).unwrap();
+
+        let user = storage.get_user("hase").await.unwrap().unwrap();
+        assert_eq!(user.params, PasswordParams::Sha256);
+        let entries = storage.get_entries("hase").await;
+        assert_eq!(entries[0].recorded_at, entries[0].timestamp);
+        assert_eq!(entries[0].kind, Kind::new("Running"));
+        assert_eq!(
+            entries[0].measurement,
+            Measurement::Distance {
+                kilometers: Decimal::from_thousandths(2_300)
+            }
+        );
+        assert_eq!(
+            entries[1].measurement,
+            Measurement::Elevation {
+                metres: Decimal::from(1_250)
+            }
+        );
+    }
+
+    #[tokio::test]
+    pub async fn moves_entries_through_the_trash() {
+        let storage = SqliteStorage::open_in_memory().unwrap();
+        let first = entry(2, "Running", 1);
+        let second = entry(1, "Climbing", 100);
+        storage.insert_entry("hase", first.clone()).await.unwrap();
+        storage.insert_entry("hase", second.clone()).await.unwrap();
+        let deleted_at = Utc.ymd(2021, 5, 2).and_hms(10, 0, 0);
+
+        assert!(storage
+            .delete_entry("hase", &first.id, deleted_at)
+            .await
+            .unwrap());
+        assert!(!storage
+            .delete_entry("igel", &second.id, deleted_at)
+            .await
+            .unwrap());
+        assert_eq!(storage.get_entries("hase").await, vec![second.clone()]);
+        assert_eq!(storage.get_trash("hase").await[0].entry, first);
+
+        assert!(storage.restore_entry("hase", &first.id).await.unwrap());
+        assert!(!storage.restore_entry("hase", &first.id).await.unwrap());
+        assert_eq!(
+            storage.get_entries("hase").await,
+            vec![second.clone(), first]
+        );
+
+        assert!(storage
+            .delete_entry("hase", &second.id, deleted_at)
+            .await
+            .unwrap());
+        assert_eq!(storage.purge_trash(deleted_at).await.unwrap(), 0);
+        assert_eq!(storage.purge_trash(Utc::now()).await.unwrap(), 1);
+        assert!(storage.get_trash("hase").await.is_empty());
+    }
+}
@@ -0,0 +1,101 @@
+use std::fmt;
+use std::io;
+use std::str::FromStr;
+
+use async_trait::async_trait;
+use chrono::prelude::*;
+use shared::{Highscore, Id, KilometerEntry, TrashedEntry};
+
+use crate::highscore::HighscoreFilter;
+use crate::models::{EntryDatabaseModel, User, UserKey};
+
+use super::db::get_highscore;
+
+#[async_trait]
+pub trait Storage: Send + Sync {
+    async fn insert_user(&self, name: UserKey, user: User) -> Result<bool, StorageError>;
+
+    async fn get_user(&self, name: &str) -> Result<Option<User>, StorageError>;
+
+    async fn update_user(&self, name: &str, user: User) -> Result<bool, StorageError>;
+
+    async fn insert_entry(&self, user: &str, entry: KilometerEntry) -> Result<(), StorageError>;
+
+    async fn get_entry(&self, id: &Id) -> Option<(UserKey, KilometerEntry)>;
+
+    async fn update_entry(&self, user: &str, entry: KilometerEntry) -> Result<bool, StorageError>;
+
+    async fn delete_entry(
+        &self,
+        user: &str,
+        id: &Id,
+        deleted_at: DateTime<Utc>,
+    ) -> Result<bool, StorageError>;
+
+    async fn restore_entry(&self, user: &str, id: &Id) -> Result<bool, StorageError>;
+
+    async fn get_entries(&self, user: &str) -> Vec<KilometerEntry>;
+
+    async fn get_trash(&self, user: &str) -> Vec<TrashedEntry>;
+
+    async fn purge_trash(&self, before: DateTime<Utc>) -> Result<usize, StorageError>;
+
+    async fn get_all_entries(&self) -> EntryDatabaseModel;
+
+    async fn get_highscore(&self, filter: &HighscoreFilter) -> Highscore {
+        get_highscore(&self.get_all_entries().await, filter)
+    }
+
+    /// Waits until all changes are written, failing when they could not be.
+    async fn flush(&self) -> Result<(), StorageError> {
+        Ok(())
+    }
+}
+
+/// A change the backend could not write.
+#[derive(Debug)]
+pub enum StorageError {
+    Io(io::Error),
+    Sqlite(rusqlite::Error),
+}
+
+impl fmt::Display for StorageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StorageError::Io(error) => write!(f, "could not write to storage: {}", error),
+            StorageError::Sqlite(error) => write!(f, "could not write to database: {}", error),
+        }
+    }
+}
+
+impl From<io::Error> for StorageError {
+    fn from(error: io::Error) -> Self {
+        StorageError::Io(error)
+    }
+}
+
+impl From<rusqlite::Error> for StorageError {
+    fn from(error: rusqlite::Error) -> Self {
+        StorageError::Sqlite(error)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StorageKind {
+    Json,
+    Sqlite,
+    EventLog,
+}
+
+impl FromStr for StorageKind {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input {
+            "json" => Ok(StorageKind::Json),
+            "sqlite" => Ok(StorageKind::Sqlite),
+            "eventlog" => Ok(StorageKind::EventLog),
+            _ => Err(format!("unknown storage backend: {}", input)),
+        }
+    }
+}
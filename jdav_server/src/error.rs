@@ -0,0 +1,279 @@
+use std::convert::Infallible;
+use std::fmt;
+
+use shared::error::{ErrorBody, ErrorCode};
+use shared::validation::ValidationErrors;
+use warp::body::BodyDeserializeError;
+use warp::http::header::{HeaderValue, RETRY_AFTER};
+use warp::http::StatusCode;
+use warp::reject::{
+    InvalidQuery, MethodNotAllowed, MissingHeader, PayloadTooLarge, UnsupportedMediaType,
+};
+use warp::{Rejection, Reply};
+
+use crate::db::storage::StorageError;
+
+#[derive(Debug)]
+pub enum ApiError {
+    Unauthorized,
+    Forbidden,
+    WrongCredentials,
+    UserExists,
+    NotFound,
+    UnknownKind(String),
+    KindExists(String),
+    InvalidKind(String),
+    InvalidQuery(String),
+    Validation(ValidationErrors),
+    TooManyAttempts { retry_after: u64 },
+    Internal(String),
+}
+
+impl ApiError {
+    pub fn status(&self) -> StatusCode {
+        match self {
+            ApiError::Unauthorized | ApiError::WrongCredentials => StatusCode::UNAUTHORIZED,
+            ApiError::Forbidden => StatusCode::FORBIDDEN,
+            ApiError::UserExists | ApiError::KindExists(_) => StatusCode::CONFLICT,
+            ApiError::NotFound | ApiError::UnknownKind(_) => StatusCode::NOT_FOUND,
+            ApiError::InvalidKind(_) | ApiError::InvalidQuery(_) | ApiError::Validation(_) => {
+                StatusCode::BAD_REQUEST
+            }
+            ApiError::TooManyAttempts { .. } => StatusCode::TOO_MANY_REQUESTS,
+            ApiError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            ApiError::Unauthorized => ErrorCode::Unauthorized,
+            ApiError::Forbidden => ErrorCode::Forbidden,
+            ApiError::WrongCredentials => ErrorCode::WrongCredentials,
+            ApiError::UserExists => ErrorCode::UserExists,
+            ApiError::NotFound => ErrorCode::NotFound,
+            ApiError::UnknownKind(_) => ErrorCode::UnknownKind,
+            ApiError::KindExists(_) => ErrorCode::KindExists,
+            ApiError::InvalidKind(_) | ApiError::InvalidQuery(_) => ErrorCode::BadRequest,
+            ApiError::Validation(_) => ErrorCode::ValidationFailed,
+            ApiError::TooManyAttempts { .. } => ErrorCode::TooManyAttempts,
+            ApiError::Internal(_) => ErrorCode::Internal,
+        }
+    }
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ApiError::Unauthorized => write!(f, "missing or invalid credentials"),
+            ApiError::Forbidden => write!(f, "not allowed to access this resource"),
+            ApiError::WrongCredentials => write!(f, "wrong user or password"),
+            ApiError::UserExists => write!(f, "user already exists"),
+            ApiError::NotFound => write!(f, "not found"),
+            ApiError::UnknownKind(kind) => write!(f, "unknown kind {}", kind),
+            ApiError::KindExists(kind) => write!(f, "kind {} already exists", kind),
+            ApiError::InvalidKind(message) | ApiError::InvalidQuery(message) => {
+                write!(f, "{}", message)
+            }
+            ApiError::Validation(errors) => write!(f, "invalid entry: {}", errors),
+            ApiError::TooManyAttempts { retry_after } => write!(
+                f,
+                "too many failed attempts, retry in {} seconds",
+                retry_after
+            ),
+            ApiError::Internal(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl warp::reject::Reject for ApiError {}
+
+impl From<StorageError> for ApiError {
+    fn from(error: StorageError) -> Self {
+        ApiError::Internal(error.to_string())
+    }
+}
+
+impl From<ValidationErrors> for ApiError {
+    fn from(errors: ValidationErrors) -> Self {
+        ApiError::Validation(errors)
+    }
+}
+
+fn error_reply(status: StatusCode, body: ErrorBody) -> warp::reply::Response {
+    warp::reply::with_status(warp::reply::json(&body), status).into_response()
+}
+
+const INTERNAL_ERROR_MESSAGE: &str = "internal server error";
+
+pub async fn handle_rejection(rejection: Rejection) -> Result<impl Reply, Infallible> {
+    if let Some(error) = rejection.find::<ApiError>() {
+        let errors = match error {
+            ApiError::Validation(errors) => errors.errors.clone(),
+            _ => Vec::new(),
+        };
+        // the details of internal errors are for the log, not for clients
+        let message = match error {
+            ApiError::Internal(message) => {
+                eprintln!("internal error: {}", message);
+                INTERNAL_ERROR_MESSAGE.to_owned()
+            }
+            _ => error.to_string(),
+        };
+        let retry_after = match error {
+            ApiError::TooManyAttempts { retry_after } => Some(*retry_after),
+            _ => None,
+        };
+        let mut reply = error_reply(
+            error.status(),
+            ErrorBody {
+                code: error.code(),
+                message,
+                errors,
+                retry_after,
+            },
+        );
+        if let Some(retry_after) = retry_after {
+            reply
+                .headers_mut()
+                .insert(RETRY_AFTER, HeaderValue::from(retry_after));
+        }
+        return Ok(reply);
+    }
+
+    let (status, code, message) = if rejection.is_not_found() {
+        (
+            StatusCode::NOT_FOUND,
+            ErrorCode::NotFound,
+            "not found".to_owned(),
+        )
+    } else if let Some(error) = rejection.find::<MissingHeader>() {
+        if error.name() == "Authorization" {
+            (
+                StatusCode::UNAUTHORIZED,
+                ErrorCode::Unauthorized,
+                ApiError::Unauthorized.to_string(),
+            )
+        } else {
+            (
+                StatusCode::BAD_REQUEST,
+                ErrorCode::BadRequest,
+                error.to_string(),
+            )
+        }
+    } else if let Some(error) = rejection.find::<BodyDeserializeError>() {
+        (
+            StatusCode::BAD_REQUEST,
+            ErrorCode::BadRequest,
+            error.to_string(),
+        )
+    } else if let Some(error) = rejection.find::<InvalidQuery>() {
+        (
+            StatusCode::BAD_REQUEST,
+            ErrorCode::BadRequest,
+            error.to_string(),
+        )
+    } else if let Some(error) = rejection.find::<PayloadTooLarge>() {
+        (
+            StatusCode::PAYLOAD_TOO_LARGE,
+            ErrorCode::PayloadTooLarge,
+            error.to_string(),
+        )
+    } else if let Some(error) = rejection.find::<UnsupportedMediaType>() {
+        (
+            StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            ErrorCode::UnsupportedMediaType,
+            error.to_string(),
+        )
+    } else if let Some(error) = rejection.find::<MethodNotAllowed>() {
+        (
+            StatusCode::METHOD_NOT_ALLOWED,
+            ErrorCode::MethodNotAllowed,
+            error.to_string(),
+        )
+    } else {
+        eprintln!("unhandled rejection: {:?}", rejection);
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            ErrorCode::Internal,
+            INTERNAL_ERROR_MESSAGE.to_owned(),
+        )
+    };
+
+    Ok(error_reply(
+        status,
+        ErrorBody {
+            code,
+            message,
+            errors: Vec::new(),
+            retry_after: None,
+        },
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use shared::error::{ErrorBody, ErrorCode};
+    use shared::validation::{Field, Problem, ValidationError, ValidationErrors};
+    use warp::http::StatusCode;
+    use warp::hyper::body::to_bytes;
+    use warp::Reply;
+
+    use super::{handle_rejection, ApiError};
+
+    async fn render(rejection: warp::Rejection) -> (StatusCode, ErrorBody) {
+        let response = handle_rejection(rejection).await.unwrap().into_response();
+        let status = response.status();
+        let body = to_bytes(response.into_body()).await.unwrap();
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    #[tokio::test]
+    pub async fn renders_api_errors_as_json() {
+        let error = ValidationError {
+            field: Field::Measurement,
+            problem: Problem::NotPositive,
+        };
+        let rejection = ApiError::Validation(ValidationErrors {
+            errors: vec![error],
+        })
+        .into();
+
+        let (status, body) = render(rejection).await;
+
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body.code, ErrorCode::ValidationFailed);
+        assert_eq!(body.errors, vec![error]);
+    }
+
+    #[tokio::test]
+    pub async fn tells_throttled_clients_when_to_retry() {
+        let rejection = ApiError::TooManyAttempts { retry_after: 8 }.into();
+
+        let response = handle_rejection(rejection).await.unwrap().into_response();
+
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(response.headers()["Retry-After"], "8");
+        let body = to_bytes(response.into_body()).await.unwrap();
+        let body: ErrorBody = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body.retry_after, Some(8));
+    }
+
+    #[tokio::test]
+    pub async fn keeps_internal_details_from_clients() {
+        let rejection = ApiError::Internal("sessions.json: permission denied".to_owned()).into();
+
+        let (status, body) = render(rejection).await;
+
+        assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(body.code, ErrorCode::Internal);
+        assert_eq!(body.message, "internal server error");
+    }
+
+    #[tokio::test]
+    pub async fn renders_unmatched_routes_as_json() {
+        let (status, body) = render(warp::reject::not_found()).await;
+
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(body.code, ErrorCode::NotFound);
+    }
+}
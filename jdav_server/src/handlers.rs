@@ -1,136 +1,367 @@
-use std::convert::Infallible;
+use std::net::IpAddr;
 
-use shared::{Kilometer, UserAuth};
+use chrono::prelude::*;
+use shared::{
+    EntriesQuery, HighscoreQuery, Id, KilometerEntry, KilometerEntryPatch, Kind, KindInfo,
+    NewKilometerEntry, UserAuth,
+};
+use uuid::Uuid;
 use warp::{self, http::StatusCode};
 
-use crate::{db::db::Database, middleware::extract_basicauth};
+use crate::highscore::HighscoreFilter;
+use crate::middleware::{extract_bearer, Credentials};
+use crate::pagination::EntryFilter;
+use crate::throttle::ThrottleKey;
+use crate::{db::db::Database, error::ApiError};
 
 pub async fn create_user(
     new_user: UserAuth,
     database: Database,
 ) -> Result<Box<dyn warp::Reply>, warp::Rejection> {
-    if database.create_user(new_user).await {
-        Ok(Box::new(warp::reply::json(&"user created")))
-    } else {
+    if database.create_user(new_user).await? {
         Ok(Box::new(warp::reply::with_status(
-            "Error Creating User".to_owned(),
-            StatusCode::FORBIDDEN,
+            warp::reply::json(&"user created"),
+            StatusCode::CREATED,
         )))
+    } else {
+        Err(ApiError::UserExists.into())
     }
 }
 
 pub async fn authenticate_user(
     user_auth: UserAuth,
+    address: Option<IpAddr>,
     database: Database,
 ) -> Result<Box<dyn warp::Reply>, warp::Rejection> {
-    if database.authenticate_user(&user_auth).await {
-        Ok(Box::new(warp::reply::json(&"user auth'd")))
-    } else {
-        Ok(Box::new(warp::reply::with_status(
-            "Wrong user/password".to_owned(),
-            StatusCode::UNAUTHORIZED,
-        )))
+    if !check_password(&user_auth, address, &database).await? {
+        return Err(ApiError::WrongCredentials.into());
     }
+    let session = database
+        .sessions()
+        .create(&user_auth.name, Utc::now())
+        .await?;
+    Ok(Box::new(warp::reply::json(&session)))
+}
+
+pub async fn logout(
+    credentials: Credentials,
+    database: Database,
+) -> Result<Box<dyn warp::Reply>, warp::Rejection> {
+    let token = extract_bearer(&credentials.header).map_err(|_| ApiError::Unauthorized)?;
+    let keys = address_key(&credentials);
+    database.throttle().check(&keys)?;
+    if !database.sessions().revoke(&token).await? {
+        database.throttle().failed(&keys);
+        return Err(ApiError::Unauthorized.into());
+    }
+    Ok(Box::new(warp::reply::json(&"logged out")))
 }
 
 pub async fn create_kilometer_entry(
     user: String,
-    kind: shared::Kind,
-    header: String,
-    kilometer: Kilometer,
+    path: String,
+    credentials: Credentials,
+    new_entry: NewKilometerEntry,
     database: Database,
-) -> Result<Box<dyn warp::Reply>, Infallible> {
-    let authorization = authorize(&user, header, database.clone()).await;
+) -> Result<Box<dyn warp::Reply>, warp::Rejection> {
+    authorize(&user, credentials, &database).await?;
 
-    if authorization.is_ok() {
-        let id = database.create_kilometer_entry(kilometer, user, kind).await;
-        Ok(Box::new(warp::reply::json(&id.to_string())))
-    } else {
-        Ok(Box::new(warp::reply::with_status(
-            "Unauthorized".to_string(),
-            StatusCode::UNAUTHORIZED,
-        )))
+    let catalogue = database.kinds().catalogue().await;
+    let kind = catalogue
+        .active()
+        .find(|info| info.path == path)
+        .ok_or(ApiError::UnknownKind(path))?;
+    let location = format!("/api/v1/users/{}/entries/", user);
+    let id = database
+        .create_kilometer_entry(new_entry, user, kind)
+        .await?;
+    Ok(Box::new(warp::reply::with_header(
+        warp::reply::with_status(warp::reply::json(&id.to_string()), StatusCode::CREATED),
+        "Location",
+        format!("{}{}", location, id),
+    )))
+}
+
+pub async fn get_entries_for_user(
+    user: String,
+    credentials: Credentials,
+    query: EntriesQuery,
+    database: Database,
+) -> Result<Box<dyn warp::Reply>, warp::Rejection> {
+    authorize(&user, credentials, &database).await?;
+
+    let filter = EntryFilter::from_query(query, &database.kinds().catalogue().await)?;
+    let entries = database.get_entries_for_user(user, &filter).await;
+    Ok(Box::new(warp::reply::json(&entries)))
+}
+
+pub async fn get_kilometer_entry(
+    user: String,
+    id: Uuid,
+    credentials: Credentials,
+    database: Database,
+) -> Result<Box<dyn warp::Reply>, warp::Rejection> {
+    let entry = authorize_entry(&user, id, credentials, &database).await?;
+    Ok(Box::new(warp::reply::json(&entry)))
+}
+
+pub async fn replace_kilometer_entry(
+    user: String,
+    id: Uuid,
+    credentials: Credentials,
+    mut entry: KilometerEntry,
+    database: Database,
+) -> Result<Box<dyn warp::Reply>, warp::Rejection> {
+    let existing = authorize_entry(&user, id, credentials, &database).await?;
+
+    entry.id = Id { id };
+    entry.recorded_at = existing.recorded_at;
+    save_entry(user, &existing.kind, entry, &database).await
+}
+
+pub async fn patch_kilometer_entry(
+    user: String,
+    id: Uuid,
+    credentials: Credentials,
+    patch: KilometerEntryPatch,
+    database: Database,
+) -> Result<Box<dyn warp::Reply>, warp::Rejection> {
+    let mut entry = authorize_entry(&user, id, credentials, &database).await?;
+    let previous_kind = entry.kind.clone();
+
+    patch.apply(&mut entry);
+    save_entry(user, &previous_kind, entry, &database).await
+}
+
+async fn save_entry(
+    user: String,
+    previous_kind: &Kind,
+    entry: KilometerEntry,
+    database: &Database,
+) -> Result<Box<dyn warp::Reply>, warp::Rejection> {
+    let catalogue = database.kinds().catalogue().await;
+    let kind = catalogue
+        .for_edit(previous_kind, &entry.kind)
+        .ok_or_else(|| ApiError::UnknownKind(entry.kind.to_string()))?;
+    match database.edit_kilometer_entry(user, entry, kind).await? {
+        Some(entry) => Ok(Box::new(warp::reply::json(&entry))),
+        None => Err(ApiError::NotFound.into()),
     }
 }
 
-pub async fn edit_kilometer_entry(
+pub async fn delete_kilometer_entry(
     user: String,
-    header: String,
-    kilometer: shared::KilometerEntry,
-    database: Database,
-) -> Result<Box<dyn warp::Reply>, Infallible> {
-    let authorization = authorize(&user, header, database.clone()).await;
-
-    if authorization.is_ok() {
-        let success = database.edit_kilometer_entry(user, kilometer).await;
-        if success {
-            return Ok(Box::new(warp::reply::with_status(
-                warp::reply::json(&"entry edited".to_owned()),
-                StatusCode::OK,
-            )));
-        } else {
-            return Ok(Box::new(warp::reply::with_status(
-                "edit failed".to_owned(),
-                StatusCode::NOT_MODIFIED,
-            )));
-        }
+    id: Uuid,
+    credentials: Credentials,
+    database: Database,
+) -> Result<Box<dyn warp::Reply>, warp::Rejection> {
+    authorize_entry(&user, id, credentials, &database).await?;
+
+    if database.delete_kilometer_entry(user, id).await? {
+        Ok(Box::new(warp::reply::json(&"entry deleted".to_owned())))
     } else {
-        Ok(Box::new(warp::reply::with_status(
-            "Unauthorized".to_string(),
-            StatusCode::UNAUTHORIZED,
-        )))
+        Err(ApiError::NotFound.into())
     }
 }
 
-pub async fn get_entries_for_user(
+pub async fn get_stats_for_user(
+    user: String,
+    credentials: Credentials,
+    database: Database,
+) -> Result<Box<dyn warp::Reply>, warp::Rejection> {
+    authorize(&user, credentials, &database).await?;
+
+    let stats = database.get_stats_for_user(user).await;
+    Ok(Box::new(warp::reply::json(&stats)))
+}
+
+pub async fn get_trash_for_user(
     user: String,
-    header: String,
+    credentials: Credentials,
     database: Database,
-) -> Result<Box<dyn warp::Reply>, Infallible> {
-    let authorization = authorize(&user, header, database.clone()).await;
+) -> Result<Box<dyn warp::Reply>, warp::Rejection> {
+    authorize(&user, credentials, &database).await?;
 
-    if authorization.is_ok() {
-        let entries = database.get_entries_for_user(user).await;
-        Ok(Box::new(warp::reply::json(&entries)))
+    let trash = database.get_trash_for_user(user).await;
+    Ok(Box::new(warp::reply::json(&trash)))
+}
+
+pub async fn restore_kilometer_entry(
+    user: String,
+    id: Uuid,
+    credentials: Credentials,
+    database: Database,
+) -> Result<Box<dyn warp::Reply>, warp::Rejection> {
+    authorize(&user, credentials, &database).await?;
+
+    if database.restore_kilometer_entry(user, id).await? {
+        Ok(Box::new(warp::reply::json(&"entry restored".to_owned())))
     } else {
-        Ok(Box::new(warp::reply::with_status(
-            "Unauthorized".to_string(),
-            StatusCode::UNAUTHORIZED,
-        )))
+        Err(ApiError::NotFound.into())
     }
 }
 
 pub async fn get_highscore(
-    header: String,
+    credentials: Credentials,
+    query: HighscoreQuery,
     database: Database,
-) -> Result<Box<dyn warp::Reply>, Infallible> {
-    let authorization = authorize_general(header, database.clone()).await;
+) -> Result<Box<dyn warp::Reply>, warp::Rejection> {
+    authorize_general(credentials, &database).await?;
 
-    if authorization.is_ok() {
-        let highscore = database.get_highscore().await;
-        Ok(Box::new(warp::reply::json(&highscore)))
-    } else {
-        Ok(Box::new(warp::reply::with_status(
-            "Unauthorized".to_string(),
-            StatusCode::UNAUTHORIZED,
-        )))
+    let catalogue = database.kinds().catalogue().await;
+    let filter = HighscoreFilter::from_query(query, Utc::now(), &catalogue)?;
+    let highscore = database.get_highscore(&filter).await;
+    Ok(Box::new(warp::reply::json(&highscore)))
+}
+
+pub async fn get_kinds(database: Database) -> Result<Box<dyn warp::Reply>, warp::Rejection> {
+    let today = Utc::now().date().naive_utc();
+    let mut catalogue = database.kinds().catalogue().await;
+    for kind in &mut catalogue.kinds {
+        kind.multiplier = database.scoring().multiplier(kind, today);
+    }
+    Ok(Box::new(warp::reply::json(&catalogue.kinds)))
+}
+
+pub async fn create_kind(
+    credentials: Credentials,
+    kind: KindInfo,
+    database: Database,
+) -> Result<Box<dyn warp::Reply>, warp::Rejection> {
+    authorize_admin(credentials, &database).await?;
+
+    let location = format!("/api/v1/kinds/{}", kind.kind);
+    database.kinds().add(kind.clone()).await?;
+    Ok(Box::new(warp::reply::with_header(
+        warp::reply::with_status(warp::reply::json(&kind), StatusCode::CREATED),
+        "Location",
+        location,
+    )))
+}
+
+pub async fn update_kind(
+    id: String,
+    credentials: Credentials,
+    kind: KindInfo,
+    database: Database,
+) -> Result<Box<dyn warp::Reply>, warp::Rejection> {
+    authorize_admin(credentials, &database).await?;
+
+    database.kinds().update(&Kind(id), kind.clone()).await?;
+    Ok(Box::new(warp::reply::json(&kind)))
+}
+
+pub async fn retire_kind(
+    id: String,
+    credentials: Credentials,
+    database: Database,
+) -> Result<Box<dyn warp::Reply>, warp::Rejection> {
+    authorize_admin(credentials, &database).await?;
+
+    database.kinds().retire(&Kind(id)).await?;
+    Ok(Box::new(warp::reply::json(&"kind retired".to_owned())))
+}
+
+/// Lifts a lockout early, like `jdav_server unlock`.
+pub async fn clear_lockout(
+    key: String,
+    credentials: Credentials,
+    database: Database,
+) -> Result<Box<dyn warp::Reply>, warp::Rejection> {
+    authorize_admin(credentials, &database).await?;
+
+    if !database.throttle().lift(&key) {
+        return Err(ApiError::NotFound.into());
+    }
+    Ok(Box::new(warp::reply::json(&"lockout cleared")))
+}
+
+pub async fn get_scoring(database: Database) -> Result<Box<dyn warp::Reply>, warp::Rejection> {
+    Ok(Box::new(warp::reply::json(database.scoring())))
+}
+
+pub async fn authorize(
+    user: &str,
+    credentials: Credentials,
+    database: &Database,
+) -> Result<String, ApiError> {
+    let name = authorize_general(credentials, database).await?;
+
+    if name != user {
+        return Err(ApiError::Forbidden);
     }
+    Ok(name)
 }
 
-pub async fn authorize(user: &str, header: String, database: Database) -> Result<(), ()> {
-    let auth = extract_basicauth(header).map_err(|_| ())?;
+async fn authorize_admin(
+    credentials: Credentials,
+    database: &Database,
+) -> Result<String, ApiError> {
+    let name = authorize_general(credentials, database).await?;
 
-    if !database.authenticate_user(&auth).await || auth.name != user {
-        return Err(());
+    if !database.is_admin(&name) {
+        return Err(ApiError::Forbidden);
     }
-    Ok(())
+    Ok(name)
 }
 
-pub async fn authorize_general(header: String, database: Database) -> Result<(), ()> {
-    let auth = extract_basicauth(header).map_err(|_| ())?;
+async fn authorize_entry(
+    user: &str,
+    id: Uuid,
+    credentials: Credentials,
+    database: &Database,
+) -> Result<KilometerEntry, ApiError> {
+    authorize(user, credentials, database).await?;
+
+    match database.get_kilometer_entry(id).await {
+        Some((owner, entry)) if owner == user => Ok(entry),
+        Some(_) => Err(ApiError::Forbidden),
+        None => Err(ApiError::NotFound),
+    }
+}
+
+/// Accepts the token of a session. Passwords are only checked when logging
+/// in, since hashing one on every request would be slow and easy to abuse.
+pub async fn authorize_general(
+    credentials: Credentials,
+    database: &Database,
+) -> Result<String, ApiError> {
+    let token = extract_bearer(&credentials.header).map_err(|_| ApiError::Unauthorized)?;
+    let keys = address_key(&credentials);
+    database.throttle().check(&keys)?;
+    let user = database.sessions().user(&token, Utc::now()).await;
+    if user.is_none() {
+        database.throttle().failed(&keys);
+    }
+    user.ok_or(ApiError::Unauthorized)
+}
+
+fn address_key(credentials: &Credentials) -> Vec<ThrottleKey> {
+    credentials
+        .address
+        .map(ThrottleKey::Address)
+        .into_iter()
+        .collect()
+}
 
-    if !database.authenticate_user(&auth).await {
-        return Err(());
+/// Checks a password unless the user or the address is throttled. Failures are
+/// counted for both, a success forgets those of the user. Checks for the same
+/// user run one after another, so that parallel guesses cannot all start
+/// before the first failure is counted.
+async fn check_password(
+    user_auth: &UserAuth,
+    address: Option<IpAddr>,
+    database: &Database,
+) -> Result<bool, ApiError> {
+    let keys = ThrottleKey::login(&user_auth.name, address);
+    let _checking = database.throttle().one_at_a_time(&keys[0]).await;
+    database.throttle().check(&keys)?;
+    if !database.authenticate_user(user_auth).await? {
+        database.throttle().failed(&keys);
+        return Ok(false);
     }
-    Ok(())
+    database
+        .throttle()
+        .clear(&ThrottleKey::User(user_auth.name.clone()));
+    Ok(true)
 }
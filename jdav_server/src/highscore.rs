@@ -0,0 +1,146 @@
+use chrono::{prelude::*, Duration};
+use shared::{HighscoreQuery, HighscoreWindow, KilometerEntry, Kind, KindCatalogue};
+
+use crate::error::ApiError;
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct HighscoreFilter {
+    from: Option<DateTime<Utc>>,
+    to: Option<DateTime<Utc>>,
+    kind: Option<Kind>,
+}
+
+fn start_of(day: NaiveDate) -> DateTime<Utc> {
+    Utc.from_utc_date(&day).and_hms(0, 0, 0)
+}
+
+impl HighscoreFilter {
+    pub fn from_query(
+        query: HighscoreQuery,
+        now: DateTime<Utc>,
+        catalogue: &KindCatalogue,
+    ) -> Result<Self, ApiError> {
+        let today = now.date().naive_utc();
+        let kind = match query.kind {
+            Some(path) => Some(
+                catalogue
+                    .by_path(&path)
+                    .map(|info| info.kind.clone())
+                    .ok_or(ApiError::UnknownKind(path))?,
+            ),
+            None => None,
+        };
+        let filter = match query.window {
+            HighscoreWindow::All => HighscoreFilter {
+                from: query.from,
+                to: query.to,
+                kind,
+            },
+            _ if query.from.is_some() || query.to.is_some() => {
+                return Err(ApiError::InvalidQuery(
+                    "from and to can only be used with window=all".to_owned(),
+                ))
+            }
+            HighscoreWindow::Week => {
+                let monday = today - Duration::days(today.weekday().num_days_from_monday().into());
+                HighscoreFilter {
+                    from: Some(start_of(monday)),
+                    to: Some(start_of(monday + Duration::weeks(1))),
+                    kind,
+                }
+            }
+            HighscoreWindow::Month => {
+                let first = NaiveDate::from_ymd(today.year(), today.month(), 1);
+                let next = match today.month() {
+                    12 => NaiveDate::from_ymd(today.year() + 1, 1, 1),
+                    month => NaiveDate::from_ymd(today.year(), month + 1, 1),
+                };
+                HighscoreFilter {
+                    from: Some(start_of(first)),
+                    to: Some(start_of(next)),
+                    kind,
+                }
+            }
+        };
+        if let (Some(from), Some(to)) = (filter.from, filter.to) {
+            if from >= to {
+                return Err(ApiError::InvalidQuery("from must be before to".to_owned()));
+            }
+        }
+        Ok(filter)
+    }
+
+    pub fn kind(&self) -> Option<&Kind> {
+        self.kind.as_ref()
+    }
+
+    pub fn matches(&self, entry: &KilometerEntry) -> bool {
+        self.from.is_none_or(|from| entry.timestamp >= from)
+            && self.to.is_none_or(|to| entry.timestamp < to)
+            && self.kind.as_ref().is_none_or(|kind| entry.kind == *kind)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::prelude::*;
+    use shared::{HighscoreQuery, HighscoreWindow, KilometerEntry, KindCatalogue};
+
+    use super::HighscoreFilter;
+
+    fn at(month: u32, day: u32) -> KilometerEntry {
+        KilometerEntry {
+            timestamp: Utc.ymd(2021, month, day).and_hms(12, 0, 0),
+            ..Default::default()
+        }
+    }
+
+    fn window(window: HighscoreWindow) -> HighscoreFilter {
+        let query = HighscoreQuery {
+            window,
+            ..Default::default()
+        };
+        // a Wednesday
+        HighscoreFilter::from_query(
+            query,
+            Utc.ymd(2021, 6, 2).and_hms(8, 0, 0),
+            &KindCatalogue::default(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    pub fn resolves_week_and_month() {
+        let week = window(HighscoreWindow::Week);
+        assert!(!week.matches(&at(5, 30)));
+        assert!(week.matches(&at(5, 31)));
+        assert!(week.matches(&at(6, 6)));
+        assert!(!week.matches(&at(6, 7)));
+
+        let month = window(HighscoreWindow::Month);
+        assert!(!month.matches(&at(5, 31)));
+        assert!(month.matches(&at(6, 1)));
+        assert!(month.matches(&at(6, 30)));
+        assert!(!month.matches(&at(7, 1)));
+
+        assert!(window(HighscoreWindow::All).matches(&at(1, 1)));
+    }
+
+    #[test]
+    pub fn rejects_invalid_ranges() {
+        let filter =
+            |query| HighscoreFilter::from_query(query, Utc::now(), &KindCatalogue::default());
+        let range = |window, from: u32, to: u32| HighscoreQuery {
+            window,
+            from: Some(Utc.ymd(2021, 5, from).and_hms(0, 0, 0)),
+            to: Some(Utc.ymd(2021, 5, to).and_hms(0, 0, 0)),
+            ..Default::default()
+        };
+
+        let custom = filter(range(HighscoreWindow::All, 2, 4)).unwrap();
+        assert!(custom.matches(&at(5, 3)));
+        assert!(!custom.matches(&at(5, 4)));
+        assert!(filter(range(HighscoreWindow::All, 4, 2)).is_err());
+        assert!(filter(range(HighscoreWindow::Week, 2, 4)).is_err());
+    }
+}
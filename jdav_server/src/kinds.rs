@@ -0,0 +1,345 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use shared::validation::MAX_MULTIPLIER;
+use shared::{Decimal, Kind, KindCatalogue, KindInfo, Unit};
+use tokio::sync::RwLock;
+
+use crate::db::persistence::{write_atomically, write_atomically_async};
+use crate::error::ApiError;
+
+pub static KINDS_FILENAME: &str = "./kinds.json";
+
+#[derive(Debug)]
+pub enum KindsError {
+    Unreadable { path: PathBuf, source: io::Error },
+    Invalid { path: PathBuf, message: String },
+}
+
+impl fmt::Display for KindsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KindsError::Unreadable { path, source } => {
+                write!(f, "could not read {}: {}", path.display(), source)
+            }
+            KindsError::Invalid { path, message } => {
+                write!(f, "invalid kinds in {}: {}", path.display(), message)
+            }
+        }
+    }
+}
+
+/// The catalogue of activity kinds, kept in a JSON file next to the database.
+pub struct KindStore {
+    path: PathBuf,
+    catalogue: RwLock<KindCatalogue>,
+}
+
+impl KindStore {
+    /// Loads the catalogue, starting with the built-in kinds when the file does
+    /// not exist.
+    pub fn load(path: &Path) -> Result<Self, KindsError> {
+        let catalogue = match fs::read_to_string(path) {
+            Ok(content) => {
+                let invalid = |message: String| KindsError::Invalid {
+                    path: path.to_owned(),
+                    message,
+                };
+                let mut catalogue: KindCatalogue =
+                    serde_json::from_str(&content).map_err(|error| invalid(error.to_string()))?;
+                check_catalogue(&catalogue).map_err(invalid)?;
+                if climb_in_metres(&mut catalogue) {
+                    let json = serde_json::to_vec_pretty(&catalogue)
+                        .map_err(|error| invalid(error.to_string()))?;
+                    write_atomically(path, &json).map_err(|source| KindsError::Unreadable {
+                        path: path.to_owned(),
+                        source,
+                    })?;
+                }
+                catalogue
+            }
+            Err(error) if error.kind() == io::ErrorKind::NotFound => KindCatalogue::default(),
+            Err(source) => {
+                return Err(KindsError::Unreadable {
+                    path: path.to_owned(),
+                    source,
+                })
+            }
+        };
+        Ok(KindStore {
+            path: path.to_owned(),
+            catalogue: RwLock::new(catalogue),
+        })
+    }
+
+    pub async fn catalogue(&self) -> KindCatalogue {
+        self.catalogue.read().await.clone()
+    }
+
+    pub async fn add(&self, info: KindInfo) -> Result<(), ApiError> {
+        let mut catalogue = self.catalogue.write().await;
+        let mut changed = catalogue.clone();
+        add_kind(&mut changed, info)?;
+        self.save(&mut catalogue, changed).await
+    }
+
+    pub async fn update(&self, kind: &Kind, info: KindInfo) -> Result<(), ApiError> {
+        let mut catalogue = self.catalogue.write().await;
+        let mut changed = catalogue.clone();
+        update_kind(&mut changed, kind, info)?;
+        self.save(&mut catalogue, changed).await
+    }
+
+    pub async fn retire(&self, kind: &Kind) -> Result<(), ApiError> {
+        let mut catalogue = self.catalogue.write().await;
+        let mut changed = catalogue.clone();
+        let info = changed
+            .kinds
+            .iter_mut()
+            .find(|info| info.kind == *kind)
+            .ok_or_else(|| ApiError::UnknownKind(kind.to_string()))?;
+        info.retired = true;
+        self.save(&mut catalogue, changed).await
+    }
+
+    async fn save(
+        &self,
+        catalogue: &mut KindCatalogue,
+        changed: KindCatalogue,
+    ) -> Result<(), ApiError> {
+        let json = serde_json::to_vec_pretty(&changed)
+            .map_err(|error| ApiError::Internal(error.to_string()))?;
+        write_atomically_async(&self.path, json)
+            .await
+            .map_err(|error| {
+                ApiError::Internal(format!("could not save {}: {}", self.path.display(), error))
+            })?;
+        *catalogue = changed;
+        Ok(())
+    }
+}
+
+/// Climbing used to be measured in kilometers. Catalogues saved before are
+/// converted like the entries, so that climbing still scores the same points.
+fn climb_in_metres(catalogue: &mut KindCatalogue) -> bool {
+    let climbing = catalogue
+        .kinds
+        .iter_mut()
+        .find(|info| info.kind == Kind::new("Climbing") && info.unit == Unit::Distance);
+    let info = match climbing {
+        Some(info) => info,
+        None => return false,
+    };
+    let multiplier = info.multiplier.exact_thousandth();
+    let max_amount = info.max_amount.checked_mul(Decimal::from(1000));
+    match (multiplier, max_amount) {
+        (Some(multiplier), Some(max_amount)) => {
+            info.unit = Unit::Elevation;
+            info.multiplier = multiplier;
+            info.max_amount = max_amount;
+            true
+        }
+        _ => false,
+    }
+}
+
+fn check_kind(info: &KindInfo) -> Result<(), String> {
+    let id = info.kind.as_str();
+    if id.is_empty() || !id.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return Err(format!("kind {:?} must be alphanumeric", id));
+    }
+    if info.path.is_empty()
+        || !info
+            .path
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+    {
+        return Err(format!(
+            "path {:?} must only contain a-z, 0-9 and _",
+            info.path
+        ));
+    }
+    if info.name.trim().is_empty() || info.name_en.trim().is_empty() {
+        return Err(format!("kind {} needs German and English labels", id));
+    }
+    if info.multiplier.is_negative() || info.multiplier > MAX_MULTIPLIER {
+        return Err(format!(
+            "kind {} needs a multiplier from 0 to {}",
+            id, MAX_MULTIPLIER
+        ));
+    }
+    if !info.max_amount.is_positive() {
+        return Err(format!("kind {} has an invalid limit", id));
+    }
+    let hex = info.color.strip_prefix('#').unwrap_or_default();
+    if hex.len() != 6 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(format!("colour {:?} must look like #1e88e5", info.color));
+    }
+    Ok(())
+}
+
+fn check_catalogue(catalogue: &KindCatalogue) -> Result<(), String> {
+    for (index, info) in catalogue.kinds.iter().enumerate() {
+        check_kind(info)?;
+        let others = &catalogue.kinds[..index];
+        if others.iter().any(|other| other.kind == info.kind) {
+            return Err(format!("kind {} is defined twice", info.kind));
+        }
+        if others.iter().any(|other| other.path == info.path) {
+            return Err(format!("path {} is used twice", info.path));
+        }
+    }
+    Ok(())
+}
+
+fn add_kind(catalogue: &mut KindCatalogue, info: KindInfo) -> Result<(), ApiError> {
+    if catalogue.get(&info.kind).is_some() {
+        return Err(ApiError::KindExists(info.kind.to_string()));
+    }
+    catalogue.kinds.push(info);
+    check_catalogue(catalogue).map_err(ApiError::InvalidKind)
+}
+
+fn update_kind(catalogue: &mut KindCatalogue, kind: &Kind, info: KindInfo) -> Result<(), ApiError> {
+    if info.kind != *kind {
+        return Err(ApiError::InvalidKind(format!(
+            "the id of kind {} cannot be changed",
+            kind
+        )));
+    }
+    let existing = catalogue
+        .kinds
+        .iter_mut()
+        .find(|existing| existing.kind == *kind)
+        .ok_or_else(|| ApiError::UnknownKind(kind.to_string()))?;
+    if info.unit != existing.unit {
+        return Err(ApiError::InvalidKind(format!(
+            "the unit of kind {} cannot be changed",
+            kind
+        )));
+    }
+    if info.multiplier != existing.multiplier {
+        return Err(ApiError::InvalidKind(format!(
+            "the multiplier of kind {} cannot be changed here, add a version to the \
+             scoring rules and rescore instead",
+            kind
+        )));
+    }
+    *existing = info;
+    check_catalogue(catalogue).map_err(ApiError::InvalidKind)
+}
+
+#[cfg(test)]
+mod test {
+    use shared::validation::MAX_MULTIPLIER;
+    use shared::{Decimal, Kind, KindCatalogue, KindInfo, Unit};
+
+    use super::{add_kind, check_catalogue, climb_in_metres, update_kind};
+    use crate::error::ApiError;
+
+    fn rowing() -> KindInfo {
+        KindInfo {
+            kind: Kind::new("Rowing"),
+            path: "rudern".to_owned(),
+            name: "Rudern".to_owned(),
+            name_en: "Rowing".to_owned(),
+            multiplier: Decimal::from_thousandths(2_500),
+            max_amount: Decimal::from(60),
+            color: "#795548".to_owned(),
+            unit: Unit::Distance,
+            retired: false,
+        }
+    }
+
+    #[test]
+    pub fn adds_and_updates_kinds() {
+        let mut catalogue = KindCatalogue::default();
+        assert_eq!(check_catalogue(&catalogue), Ok(()));
+
+        add_kind(&mut catalogue, rowing()).unwrap();
+        assert_eq!(catalogue.by_path("rudern"), Some(&rowing()));
+        assert!(matches!(
+            add_kind(&mut catalogue, rowing()),
+            Err(ApiError::KindExists(_))
+        ));
+
+        let retired = KindInfo {
+            retired: true,
+            ..rowing()
+        };
+        update_kind(&mut catalogue, &Kind::new("Rowing"), retired).unwrap();
+        assert_eq!(catalogue.active().count(), 9);
+        assert!(matches!(
+            update_kind(&mut catalogue, &Kind::new("Running"), rowing()),
+            Err(ApiError::InvalidKind(_))
+        ));
+        let counted = KindInfo {
+            unit: Unit::Count,
+            ..rowing()
+        };
+        assert!(matches!(
+            update_kind(&mut catalogue, &Kind::new("Rowing"), counted),
+            Err(ApiError::InvalidKind(_))
+        ));
+        let doubled = KindInfo {
+            multiplier: Decimal::from(5),
+            ..rowing()
+        };
+        assert!(matches!(
+            update_kind(&mut catalogue, &Kind::new("Rowing"), doubled),
+            Err(ApiError::InvalidKind(_))
+        ));
+    }
+
+    #[test]
+    pub fn converts_climbing_to_metres() {
+        let mut catalogue = KindCatalogue::default();
+        let climbing = catalogue.get(&Kind::new("Climbing")).unwrap().clone();
+        assert!(!climb_in_metres(&mut catalogue));
+
+        catalogue.kinds[2] = KindInfo {
+            unit: Unit::Distance,
+            multiplier: Decimal::from(100),
+            max_amount: Decimal::from(5),
+            ..climbing.clone()
+        };
+        assert!(climb_in_metres(&mut catalogue));
+        assert_eq!(catalogue.kinds[2], climbing);
+    }
+
+    #[test]
+    pub fn rejects_invalid_kinds() {
+        let invalid = |info: KindInfo| {
+            let mut catalogue = KindCatalogue::default();
+            add_kind(&mut catalogue, info).is_err()
+        };
+
+        assert!(!invalid(rowing()));
+        assert!(invalid(KindInfo {
+            path: "laufen".to_owned(),
+            ..rowing()
+        }));
+        assert!(invalid(KindInfo {
+            kind: Kind::new("Row ing"),
+            ..rowing()
+        }));
+        assert!(invalid(KindInfo {
+            color: "brown".to_owned(),
+            ..rowing()
+        }));
+        assert!(invalid(KindInfo {
+            max_amount: Decimal::ZERO,
+            ..rowing()
+        }));
+        assert!(invalid(KindInfo {
+            multiplier: MAX_MULTIPLIER + Decimal::from_thousandths(1),
+            ..rowing()
+        }));
+        assert!(invalid(KindInfo {
+            name_en: " ".to_owned(),
+            ..rowing()
+        }));
+    }
+}
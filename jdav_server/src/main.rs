@@ -1,9 +1,32 @@
+mod challenge;
 mod db;
+mod error;
 mod handlers;
+mod highscore;
+mod kinds;
 mod middleware;
 mod models;
+mod openapi;
+mod pagination;
+mod password;
 mod routes;
+mod scoring;
+mod sessions;
+mod stats;
+#[cfg(test)]
+mod test_util;
+mod throttle;
 
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use challenge::ChallengePeriod;
+use chrono::NaiveDate;
+use db::db::{Database, DatabaseConfig};
+use db::event_log::EVENT_LOG_DIRECTORY;
+use db::persistence::{BackupConfig, BACKUP_DIRECTORY};
+use db::storage::StorageKind;
 use structopt::StructOpt;
 
 #[cfg(unix)]
@@ -16,6 +39,69 @@ use tokio::sync::oneshot::Sender;
 struct Options {
     #[structopt(short, long)]
     use_tls: bool,
+    #[structopt(
+        long,
+        default_value = "json",
+        possible_values = &["json", "sqlite", "eventlog"]
+    )]
+    storage: StorageKind,
+    #[structopt(long, default_value = BACKUP_DIRECTORY, parse(from_os_str))]
+    backup_dir: PathBuf,
+    #[structopt(long, default_value = "5")]
+    backups: usize,
+    /// How often the running server backs up the database
+    #[structopt(long, default_value = "60")]
+    backup_minutes: u64,
+    #[structopt(long, default_value = EVENT_LOG_DIRECTORY, parse(from_os_str))]
+    event_log_dir: PathBuf,
+    #[structopt(long, default_value = "1000")]
+    snapshot_every: u64,
+    #[structopt(long)]
+    init_empty: bool,
+    #[structopt(long, default_value = "30")]
+    trash_days: i64,
+    #[structopt(long)]
+    challenge_start: Option<NaiveDate>,
+    #[structopt(long)]
+    challenge_end: Option<NaiveDate>,
+    #[structopt(long, default_value = scoring::SCORING_FILENAME, parse(from_os_str))]
+    scoring: PathBuf,
+    #[structopt(long, default_value = kinds::KINDS_FILENAME, parse(from_os_str))]
+    kinds: PathBuf,
+    #[structopt(long, default_value = sessions::SESSIONS_FILENAME, parse(from_os_str))]
+    sessions: PathBuf,
+    /// How long a login stays valid
+    #[structopt(long, default_value = "168")]
+    session_hours: i64,
+    /// Users allowed to manage the activity kinds, may be given several times
+    #[structopt(long = "admin")]
+    admins: Vec<String>,
+    /// Where `unlock` leaves its requests for the running server
+    #[structopt(long, default_value = throttle::UNLOCK_DIRECTORY, parse(from_os_str))]
+    unlock_dir: PathBuf,
+    #[structopt(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(StructOpt, Debug)]
+enum Command {
+    /// Replaces the database with the given backup
+    Restore {
+        #[structopt(parse(from_os_str))]
+        backup: PathBuf,
+    },
+    /// Migrates the database to the current version
+    Migrate {
+        #[structopt(long)]
+        dry_run: bool,
+    },
+    /// Scores all entries again with the current scoring rules
+    Rescore {
+        #[structopt(long)]
+        dry_run: bool,
+    },
+    /// Lifts the lockout of a user or address in the running server
+    Unlock { key: String },
 }
 
 #[derive(Debug)]
@@ -23,10 +109,123 @@ struct ShutDownCommand;
 
 #[tokio::main]
 async fn main() {
-    let database = db::db::init_db().await;
-
     let opt = Options::from_args();
 
+    let backups = BackupConfig {
+        directory: opt.backup_dir.clone(),
+        count: opt.backups,
+        interval: std::time::Duration::from_secs(opt.backup_minutes * 60),
+    };
+
+    match &opt.command {
+        Some(Command::Restore { backup }) => {
+            let target = Path::new(db::json::DATABASE_FILENAME);
+            match db::persistence::restore(backup, target, &backups) {
+                Ok(source) => println!("Restored {}", source.display()),
+                Err(error) => {
+                    eprintln!("Restoring {} failed: {}", backup.display(), error);
+                    std::process::exit(1);
+                }
+            }
+            return;
+        }
+        Some(Command::Migrate { dry_run }) => {
+            let target = Path::new(db::json::DATABASE_FILENAME);
+            if let Err(error) = db::migration::migrate_file(target, &backups, *dry_run).await {
+                eprintln!("Migration failed: {}", error);
+                std::process::exit(1);
+            }
+            return;
+        }
+        Some(Command::Unlock { key }) => {
+            if let Err(error) = throttle::request_lift(&opt.unlock_dir, key) {
+                eprintln!("Requesting the unlock of {} failed: {}", key, error);
+                std::process::exit(1);
+            }
+            println!("The running server lifts the lockout of {} shortly", key);
+            return;
+        }
+        Some(Command::Rescore { .. }) | None => {}
+    }
+
+    let scoring = match scoring::load(&opt.scoring) {
+        Ok(scoring) => scoring,
+        Err(error) => {
+            eprintln!("Refusing to start: {}", error);
+            std::process::exit(1);
+        }
+    };
+
+    let kinds = match kinds::KindStore::load(&opt.kinds) {
+        Ok(kinds) => kinds,
+        Err(error) => {
+            eprintln!("Refusing to start: {}", error);
+            std::process::exit(1);
+        }
+    };
+
+    let sessions = match sessions::SessionStore::load(
+        &opt.sessions,
+        chrono::Duration::hours(opt.session_hours),
+    ) {
+        Ok(sessions) => sessions,
+        Err(error) => {
+            eprintln!("Refusing to start: {}", error);
+            std::process::exit(1);
+        }
+    };
+
+    let config = DatabaseConfig {
+        storage: opt.storage,
+        backups,
+        event_log_directory: opt.event_log_dir.clone(),
+        snapshot_every: opt.snapshot_every,
+        init_empty: opt.init_empty,
+        challenge: ChallengePeriod::from_dates(opt.challenge_start, opt.challenge_end),
+        scoring,
+        kinds,
+        sessions,
+        throttle: throttle::Throttle::new(Default::default(), Arc::new(throttle::SystemClock)),
+        admins: opt.admins.clone(),
+    };
+
+    let database = match db::db::init_db(config).await {
+        Ok(database) => database,
+        Err(error) => {
+            eprintln!("Refusing to start: {}", error);
+            std::process::exit(1);
+        }
+    };
+
+    if let Some(Command::Rescore { dry_run }) = opt.command {
+        let changed = match database.rescore(dry_run).await {
+            Ok(changed) => changed,
+            Err(error) => {
+                eprintln!("Saving the new points failed: {}", error);
+                std::process::exit(1);
+            }
+        };
+        if let Err(error) = database.flush().await {
+            eprintln!("Saving the new points failed: {}", error);
+            std::process::exit(1);
+        }
+        if dry_run {
+            println!("{} entries would be rescored", changed);
+        } else {
+            println!("Rescored {} entries", changed);
+        }
+        return;
+    }
+
+    match database.score_unscored().await {
+        Ok(0) => {}
+        Ok(scored) => println!("Scored {} entries stored without points", scored),
+        Err(error) => eprintln!("Scoring entries stored without points failed: {}", error),
+    }
+
+    tokio::spawn(purge_trash(database.clone(), opt.trash_days));
+    tokio::spawn(lift_lockouts(database.clone(), opt.unlock_dir.clone()));
+
     #[cfg(unix)]
     {
         let (shutdown_trigger, shutdown_command) = oneshot::channel::<ShutDownCommand>();
@@ -52,6 +251,13 @@ async fn main() {
                 .1
                 .await;
         };
+        if let Err(error) = database.flush().await {
+            eprintln!(
+                "Changes since the last successful write are lost: {}",
+                error
+            );
+            std::process::exit(1);
+        }
     }
     #[cfg(windows)]
     {
@@ -70,13 +276,49 @@ async fn main() {
     }
 }
 
+async fn purge_trash(database: Database, days: i64) {
+    let mut interval = tokio::time::interval(Duration::from_secs(60 * 60));
+    loop {
+        interval.tick().await;
+        let before = chrono::Utc::now() - chrono::Duration::days(days);
+        match database.purge_trash(before).await {
+            Ok(0) => {}
+            Ok(purged) => println!("Purged {} entries from the trash", purged),
+            Err(error) => eprintln!("Purging the trash failed: {}", error),
+        }
+    }
+}
+
+async fn lift_lockouts(database: Database, directory: PathBuf) {
+    let mut interval = tokio::time::interval(Duration::from_secs(5));
+    loop {
+        interval.tick().await;
+        match database.throttle().lift_requested(&directory) {
+            Ok(lifted) => {
+                for (key, locked) in lifted {
+                    if locked {
+                        println!("Lifted the lockout of {}", key);
+                    } else {
+                        println!("{} was not locked out", key);
+                    }
+                }
+            }
+            Err(error) => eprintln!("Lifting requested lockouts failed: {}", error),
+        }
+    }
+}
+
 #[cfg(unix)]
 fn handle_shutdown(tx: Sender<ShutDownCommand>) {
     use tokio::signal::unix::{signal, SignalKind};
 
     let term_receiver = async {
-        let mut stream = signal(SignalKind::terminate()).unwrap();
-        stream.recv().await;
+        let mut terminate = signal(SignalKind::terminate()).unwrap();
+        let mut interrupt = signal(SignalKind::interrupt()).unwrap();
+        tokio::select! {
+            _ = terminate.recv() => {}
+            _ = interrupt.recv() => {}
+        }
         tx.send(ShutDownCommand).unwrap();
     };
 
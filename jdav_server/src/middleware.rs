@@ -1,6 +1,6 @@
 use std::convert::Infallible;
+use std::net::{IpAddr, SocketAddr};
 
-use shared::UserAuth;
 use warp::Filter;
 
 use crate::db::db::Database;
@@ -8,37 +8,31 @@ use crate::db::db::Database;
 #[derive(Debug)]
 pub enum AuthError {
     InvalidAuthHeader,
-    InvalidBase64,
 }
 
-pub fn extract_basicauth(header: String) -> Result<UserAuth, AuthError> {
-    let stripped = header.split("Basic ").collect::<Vec<&str>>();
-    let stripped = match stripped.as_slice() {
-        [_, ref base64_content] => Ok((base64_content).to_owned()),
-        _ => Err(AuthError::InvalidAuthHeader),
-    }?;
-    let decoded =
-        String::from_utf8(base64::decode(stripped).map_err(|_| AuthError::InvalidBase64)?)
-            .map_err(|_| AuthError::InvalidBase64)?;
-
-    let split = decoded.split(':').collect::<Vec<&str>>();
-    match split.as_slice() {
-        [ref name, ref pass, ..] => Ok(UserAuth {
-            name: (name.to_owned()).to_owned(),
-            pass: (pass.to_owned()).to_owned(),
-        }),
+pub fn extract_bearer(header: &str) -> Result<String, AuthError> {
+    match header.strip_prefix("Bearer ") {
+        Some(token) if !token.is_empty() => Ok(token.to_owned()),
         _ => Err(AuthError::InvalidAuthHeader),
     }
 }
 
-#[derive(Debug)]
-struct UnAuthorized;
+/// The `Authorization` header of a request and the address it came from.
+#[derive(Debug, Clone)]
+pub struct Credentials {
+    pub header: String,
+    pub address: Option<IpAddr>,
+}
 
-impl warp::reject::Reject for UnAuthorized {}
+pub fn remote_address() -> impl Filter<Extract = (Option<IpAddr>,), Error = Infallible> + Clone {
+    warp::addr::remote().map(|remote: Option<SocketAddr>| remote.map(|remote| remote.ip()))
+}
 
 pub fn authentication_middleware(
-) -> impl Filter<Extract = (String,), Error = warp::Rejection> + Clone {
+) -> impl Filter<Extract = (Credentials,), Error = warp::Rejection> + Clone {
     warp::header::<String>("Authorization")
+        .and(remote_address())
+        .map(|header, address| Credentials { header, address })
 }
 
 pub fn with_database(
@@ -48,20 +42,12 @@ pub fn with_database(
 }
 #[cfg(test)]
 pub mod test {
-    use shared::UserAuth;
-
-    use super::extract_basicauth;
+    use super::extract_bearer;
 
     #[test]
-    pub fn works() {
-        let pass_entry = base64::encode(b"wurst:kaese");
-        let user_auth = extract_basicauth(format!("Basic {}", pass_entry));
-        assert_eq!(
-            user_auth.unwrap(),
-            UserAuth {
-                name: "wurst".to_owned(),
-                pass: "kaese".to_owned(),
-            },
-        )
+    pub fn extracts_bearer_tokens() {
+        assert_eq!(extract_bearer("Bearer abc").unwrap(), "abc");
+        assert!(extract_bearer("Bearer ").is_err());
+        assert!(extract_bearer("Basic abc").is_err());
     }
 }
@@ -1,23 +1,42 @@
+use chrono::prelude::*;
 use serde::{Deserialize, Serialize};
-use shared::KilometerEntry;
+use shared::{Id, KilometerEntry, TrashedEntry};
 use std::collections::HashMap;
 
 use crate::db::migration::DatabaseVersion;
 
+/// How the hash of a password was computed.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(tag = "algorithm", rename_all = "snake_case")]
+pub enum PasswordParams {
+    /// `SHA256(pass + salt)`, used for users created before Argon2id.
+    #[default]
+    Sha256,
+    Argon2id {
+        memory_kib: u32,
+        iterations: u32,
+        lanes: u32,
+    },
+}
+
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct User {
     pub hash: String,
     pub salt: String,
+    #[serde(default)]
+    pub params: PasswordParams,
 }
 
 pub type UserKey = String;
 pub type EntryDatabaseModel = HashMap<UserKey, Vec<KilometerEntry>>;
 pub type UserDatabaseModel = HashMap<UserKey, User>;
+pub type TrashDatabaseModel = HashMap<UserKey, Vec<TrashedEntry>>;
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct DatabaseModel {
     pub entries: EntryDatabaseModel,
     pub users: UserDatabaseModel,
+    pub trash: TrashDatabaseModel,
     pub database_version: DatabaseVersion,
 }
 
@@ -26,7 +45,144 @@ impl Default for DatabaseModel {
         DatabaseModel {
             entries: HashMap::new(),
             users: HashMap::new(),
-            database_version: DatabaseVersion::V1,
+            trash: HashMap::new(),
+            database_version: DatabaseVersion::CURRENT,
+        }
+    }
+}
+
+impl DatabaseModel {
+    pub fn insert_user(&mut self, name: UserKey, user: User) -> bool {
+        if self.users.contains_key(&name) {
+            return false;
+        }
+        self.users.insert(name, user);
+        true
+    }
+
+    pub fn update_user(&mut self, name: &str, user: User) -> bool {
+        match self.users.get_mut(name) {
+            Some(existing) => {
+                *existing = user;
+                true
+            }
+            None => false,
         }
     }
+
+    pub fn insert_entry(&mut self, user: &str, entry: KilometerEntry) {
+        self.entries.entry(user.to_owned()).or_default().push(entry);
+    }
+
+    pub fn find_entry(&self, id: &Id) -> Option<(UserKey, KilometerEntry)> {
+        self.entries.iter().find_map(|(user, entries)| {
+            entries
+                .iter()
+                .find(|entry| entry.id == *id)
+                .map(|entry| (user.clone(), entry.clone()))
+        })
+    }
+
+    pub fn update_entry(&mut self, user: &str, new_entry: KilometerEntry) -> bool {
+        let entry = self
+            .entries
+            .get_mut(user)
+            .and_then(|entries| entries.iter_mut().find(|entry| entry.id == new_entry.id));
+        match entry {
+            Some(entry) => {
+                *entry = KilometerEntry {
+                    recorded_at: entry.recorded_at,
+                    ..new_entry
+                };
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn delete_entry(&mut self, user: &str, id: &Id, deleted_at: DateTime<Utc>) -> bool {
+        let entries = match self.entries.get_mut(user) {
+            Some(entries) => entries,
+            None => return false,
+        };
+        match entries.iter().position(|entry| entry.id == *id) {
+            Some(index) => {
+                let entry = entries.remove(index);
+                self.trash
+                    .entry(user.to_owned())
+                    .or_default()
+                    .push(TrashedEntry { entry, deleted_at });
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn restore_entry(&mut self, user: &str, id: &Id) -> bool {
+        let trash = match self.trash.get_mut(user) {
+            Some(trash) => trash,
+            None => return false,
+        };
+        match trash.iter().position(|trashed| trashed.entry.id == *id) {
+            Some(index) => {
+                let trashed = trash.remove(index);
+                self.insert_entry(user, trashed.entry);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn purge_trash(&mut self, before: DateTime<Utc>) -> usize {
+        let mut purged = 0;
+        for trash in self.trash.values_mut() {
+            let count = trash.len();
+            trash.retain(|trashed| trashed.deleted_at >= before);
+            purged += count - trash.len();
+        }
+        self.trash.retain(|_, trash| !trash.is_empty());
+        purged
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::prelude::*;
+    use shared::{Id, KilometerEntry};
+    use uuid::Uuid;
+
+    use super::DatabaseModel;
+
+    #[test]
+    pub fn moves_deleted_entries_through_the_trash() {
+        let mut database = DatabaseModel::default();
+        let id = Id { id: Uuid::new_v4() };
+        database.insert_entry(
+            "hase",
+            KilometerEntry {
+                id,
+                ..Default::default()
+            },
+        );
+
+        assert!(database.delete_entry("hase", &id, Utc.ymd(2021, 5, 1).and_hms(10, 0, 0)));
+        assert!(!database.delete_entry("hase", &id, Utc::now()));
+        assert!(database.entries["hase"].is_empty());
+        assert_eq!(database.trash["hase"].len(), 1);
+
+        assert!(database.restore_entry("hase", &id));
+        assert!(!database.restore_entry("hase", &id));
+        assert_eq!(database.entries["hase"][0].id, id);
+
+        database.delete_entry("hase", &id, Utc.ymd(2021, 5, 1).and_hms(10, 0, 0));
+        assert_eq!(
+            database.purge_trash(Utc.ymd(2021, 5, 1).and_hms(9, 0, 0)),
+            0
+        );
+        assert_eq!(
+            database.purge_trash(Utc.ymd(2021, 5, 2).and_hms(0, 0, 0)),
+            1
+        );
+        assert!(database.trash.is_empty());
+    }
 }
@@ -0,0 +1,335 @@
+use serde_json::{json, Map, Value};
+use shared::error::ErrorBody;
+use shared::schema::{components, reference};
+use shared::{
+    Entries, Highscore, KilometerEntry, KilometerEntryPatch, KindInfo, NewKilometerEntry,
+    ScoringRules, Session, Trash, UserAuth, UserStats,
+};
+
+use crate::pagination::MAX_LIMIT;
+
+struct Operation {
+    method: &'static str,
+    path: &'static str,
+    summary: &'static str,
+    query: Vec<(&'static str, bool)>,
+    secured: bool,
+    request: Option<Value>,
+    status: u16,
+    response: Value,
+}
+
+fn operations() -> Vec<Operation> {
+    let message = json!({ "type": "string" });
+    let secured = |method, path, summary, request, status, response| Operation {
+        method,
+        path,
+        summary,
+        query: Vec::new(),
+        secured: true,
+        request,
+        status,
+        response,
+    };
+    vec![
+        Operation {
+            method: "post",
+            path: "/users",
+            summary: "Create a user",
+            query: Vec::new(),
+            secured: false,
+            request: Some(reference::<UserAuth>()),
+            status: 201,
+            response: message.clone(),
+        },
+        Operation {
+            method: "post",
+            path: "/authenticate",
+            summary: "Log in and open a session",
+            query: Vec::new(),
+            secured: false,
+            request: Some(reference::<UserAuth>()),
+            status: 200,
+            response: reference::<Session>(),
+        },
+        secured(
+            "delete",
+            "/lockouts/{key}",
+            "Clear the lockout of a user or address in the running server (admins only)",
+            None,
+            200,
+            message.clone(),
+        ),
+        secured(
+            "post",
+            "/logout",
+            "End the session of the bearer token",
+            None,
+            200,
+            message.clone(),
+        ),
+        Operation {
+            method: "get",
+            path: "/kinds",
+            summary: "List the kinds, including retired ones",
+            query: Vec::new(),
+            secured: false,
+            request: None,
+            status: 200,
+            response: json!({ "type": "array", "items": reference::<KindInfo>() }),
+        },
+        secured(
+            "post",
+            "/kinds",
+            "Add a kind (admins only)",
+            Some(reference::<KindInfo>()),
+            201,
+            reference::<KindInfo>(),
+        ),
+        secured(
+            "put",
+            "/kinds/{kind}",
+            "Change the labels, limit or colour of a kind, or retire it (admins only)",
+            Some(reference::<KindInfo>()),
+            200,
+            reference::<KindInfo>(),
+        ),
+        secured(
+            "delete",
+            "/kinds/{kind}",
+            "Retire a kind (admins only)",
+            None,
+            200,
+            message.clone(),
+        ),
+        Operation {
+            method: "get",
+            path: "/scoring",
+            summary: "List the versions of the scoring rules",
+            query: Vec::new(),
+            secured: false,
+            request: None,
+            status: 200,
+            response: reference::<ScoringRules>(),
+        },
+        secured(
+            "get",
+            "/users/{user}/entries",
+            "List the entries of a user",
+            None,
+            200,
+            reference::<Entries>(),
+        )
+        .with_query("from", false)
+        .with_query("to", false)
+        .with_query("kind", false)
+        .with_query("sort", false)
+        .with_query("limit", false)
+        .with_query("cursor", false),
+        secured(
+            "post",
+            "/users/{user}/entries",
+            "Create an entry",
+            Some(reference::<NewKilometerEntry>()),
+            201,
+            json!({ "type": "string", "format": "uuid" }),
+        )
+        .with_query("kind", true),
+        secured(
+            "get",
+            "/users/{user}/entries/{id}",
+            "Get an entry",
+            None,
+            200,
+            reference::<KilometerEntry>(),
+        ),
+        secured(
+            "put",
+            "/users/{user}/entries/{id}",
+            "Replace an entry",
+            Some(reference::<KilometerEntry>()),
+            200,
+            reference::<KilometerEntry>(),
+        ),
+        secured(
+            "patch",
+            "/users/{user}/entries/{id}",
+            "Change the given fields of an entry",
+            Some(reference::<KilometerEntryPatch>()),
+            200,
+            reference::<KilometerEntry>(),
+        ),
+        secured(
+            "delete",
+            "/users/{user}/entries/{id}",
+            "Move an entry to the trash",
+            None,
+            200,
+            message.clone(),
+        ),
+        secured(
+            "get",
+            "/users/{user}/stats",
+            "Get the statistics of a user",
+            None,
+            200,
+            reference::<UserStats>(),
+        ),
+        secured(
+            "get",
+            "/users/{user}/trash",
+            "List the trash of a user",
+            None,
+            200,
+            reference::<Trash>(),
+        ),
+        secured(
+            "post",
+            "/users/{user}/trash/{id}/restore",
+            "Restore an entry from the trash",
+            None,
+            200,
+            message,
+        ),
+        secured(
+            "get",
+            "/highscore",
+            "Get the highscore",
+            None,
+            200,
+            reference::<Highscore>(),
+        )
+        .with_query("window", false)
+        .with_query("kind", false)
+        .with_query("from", false)
+        .with_query("to", false),
+    ]
+}
+
+fn parameter(name: &str, location: &str, required: bool) -> Value {
+    let schema = match name {
+        "id" => json!({ "type": "string", "format": "uuid" }),
+        "from" | "to" => json!({ "type": "string", "format": "date-time" }),
+        "sort" => json!({ "type": "string", "enum": ["oldest", "newest"] }),
+        "window" => json!({ "type": "string", "enum": ["all", "week", "month"] }),
+        "limit" => json!({ "type": "integer", "minimum": 1, "maximum": MAX_LIMIT }),
+        _ => json!({ "type": "string" }),
+    };
+    json!({ "name": name, "in": location, "required": required, "schema": schema })
+}
+
+fn json_content(schema: Value) -> Value {
+    json!({ "application/json": { "schema": schema } })
+}
+
+impl Operation {
+    fn with_query(mut self, name: &'static str, required: bool) -> Self {
+        self.query.push((name, required));
+        self
+    }
+
+    fn to_json(&self) -> Value {
+        let parameters = self
+            .path
+            .split('/')
+            .filter_map(|segment| segment.strip_prefix('{')?.strip_suffix('}'))
+            .map(|name| parameter(name, "path", true))
+            .chain(
+                self.query
+                    .iter()
+                    .map(|(name, required)| parameter(name, "query", *required)),
+            )
+            .collect::<Vec<_>>();
+        let mut operation = json!({
+            "summary": self.summary,
+            "parameters": parameters,
+            "responses": {
+                self.status.to_string(): {
+                    "description": "OK",
+                    "content": json_content(self.response.clone()),
+                },
+                "default": {
+                    "description": "Error",
+                    "content": json_content(reference::<ErrorBody>()),
+                },
+            },
+        });
+        if let Some(request) = &self.request {
+            operation["requestBody"] = json!({
+                "required": true,
+                "content": json_content(request.clone()),
+            });
+        }
+        if self.secured {
+            operation["security"] = json!([{ "bearerAuth": [] }]);
+        }
+        operation
+    }
+}
+
+pub fn document() -> Value {
+    let mut paths = Map::new();
+    for operation in operations() {
+        let path = paths
+            .entry(operation.path)
+            .or_insert_with(|| Value::Object(Map::new()));
+        path[operation.method] = operation.to_json();
+    }
+    json!({
+        "openapi": "3.0.3",
+        "info": {
+            "title": "JDAV Kilometer Challenge",
+            "version": env!("CARGO_PKG_VERSION"),
+        },
+        "servers": [{ "url": "/api/v1" }],
+        "paths": paths,
+        "components": {
+            "schemas": components(),
+            "securitySchemes": {
+                "bearerAuth": { "type": "http", "scheme": "bearer" },
+            },
+        },
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::Value;
+
+    use super::document;
+
+    fn references(value: &Value, found: &mut Vec<String>) {
+        match value {
+            Value::Object(fields) => {
+                if let Some(Value::String(reference)) = fields.get("$ref") {
+                    found.push(reference.clone());
+                }
+                fields.values().for_each(|field| references(field, found));
+            }
+            Value::Array(items) => items.iter().for_each(|item| references(item, found)),
+            _ => {}
+        }
+    }
+
+    #[test]
+    pub fn resolves_all_references() {
+        let document = document();
+        let mut found = Vec::new();
+        references(&document, &mut found);
+
+        assert!(!found.is_empty());
+        for reference in found {
+            let name = reference.trim_start_matches("#/components/schemas/");
+            assert!(
+                document["components"]["schemas"].get(name).is_some(),
+                "unresolved {}",
+                reference
+            );
+        }
+        assert!(document["paths"]["/users/{user}/entries/{id}"]["patch"].is_object());
+        assert_eq!(
+            document["paths"]["/users/{user}/entries"]["post"]["parameters"][1]["in"],
+            "query"
+        );
+    }
+}
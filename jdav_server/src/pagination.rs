@@ -0,0 +1,174 @@
+use chrono::prelude::*;
+use shared::{Entries, EntriesQuery, KilometerEntry, Kind, KindCatalogue, SortOrder};
+use uuid::Uuid;
+
+use crate::error::ApiError;
+
+pub const DEFAULT_LIMIT: usize = 50;
+pub const MAX_LIMIT: usize = 200;
+
+type Position = (DateTime<Utc>, Uuid);
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct EntryFilter {
+    from: Option<DateTime<Utc>>,
+    to: Option<DateTime<Utc>>,
+    kind: Option<Kind>,
+    sort: SortOrder,
+    limit: usize,
+    after: Option<Position>,
+}
+
+fn position(entry: &KilometerEntry) -> Position {
+    (entry.timestamp, entry.id.id)
+}
+
+fn encode_cursor((timestamp, id): Position) -> String {
+    base64::encode_config(
+        format!("{}|{}", timestamp.to_rfc3339(), id),
+        base64::URL_SAFE_NO_PAD,
+    )
+}
+
+fn decode_cursor(cursor: &str) -> Option<Position> {
+    let decoded = base64::decode_config(cursor, base64::URL_SAFE_NO_PAD).ok()?;
+    let decoded = String::from_utf8(decoded).ok()?;
+    let (timestamp, id) = decoded.split_once('|')?;
+    Some((
+        DateTime::parse_from_rfc3339(timestamp)
+            .ok()?
+            .with_timezone(&Utc),
+        Uuid::parse_str(id).ok()?,
+    ))
+}
+
+impl EntryFilter {
+    pub fn from_query(query: EntriesQuery, catalogue: &KindCatalogue) -> Result<Self, ApiError> {
+        let kind = match query.kind {
+            Some(path) => Some(
+                catalogue
+                    .by_path(&path)
+                    .map(|info| info.kind.clone())
+                    .ok_or(ApiError::UnknownKind(path))?,
+            ),
+            None => None,
+        };
+        let after = match query.cursor {
+            Some(cursor) => Some(
+                decode_cursor(&cursor)
+                    .ok_or_else(|| ApiError::InvalidQuery(format!("invalid cursor {}", cursor)))?,
+            ),
+            None => None,
+        };
+        Ok(EntryFilter {
+            from: query.from,
+            to: query.to,
+            kind,
+            sort: query.sort,
+            limit: query.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT),
+            after,
+        })
+    }
+
+    fn matches(&self, entry: &KilometerEntry) -> bool {
+        self.from.is_none_or(|from| entry.timestamp >= from)
+            && self.to.is_none_or(|to| entry.timestamp < to)
+            && self.kind.as_ref().is_none_or(|kind| entry.kind == *kind)
+            && self.after.is_none_or(|after| match self.sort {
+                SortOrder::Oldest => position(entry) > after,
+                SortOrder::Newest => position(entry) < after,
+            })
+    }
+
+    pub fn apply(&self, entries: Vec<KilometerEntry>) -> Entries {
+        let mut list = entries
+            .into_iter()
+            .filter(|entry| self.matches(entry))
+            .collect::<Vec<_>>();
+        list.sort_by_key(position);
+        if self.sort == SortOrder::Newest {
+            list.reverse();
+        }
+
+        let next_cursor = if list.len() > self.limit {
+            list.truncate(self.limit);
+            list.last().map(|entry| encode_cursor(position(entry)))
+        } else {
+            None
+        };
+        Entries { list, next_cursor }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::prelude::*;
+    use shared::{EntriesQuery, Id, KilometerEntry, Kind, KindCatalogue, SortOrder};
+    use uuid::Uuid;
+
+    use super::EntryFilter;
+
+    fn entries() -> Vec<KilometerEntry> {
+        (1..=5)
+            .map(|day| KilometerEntry {
+                id: Id { id: Uuid::new_v4() },
+                kind: Kind::new(if day % 2 == 0 { "Biking" } else { "Running" }),
+                timestamp: Utc.ymd(2021, 5, day).and_hms(12, 0, 0),
+                ..Default::default()
+            })
+            .collect()
+    }
+
+    fn days(entries: &[KilometerEntry]) -> Vec<u32> {
+        entries.iter().map(|entry| entry.timestamp.day()).collect()
+    }
+
+    #[test]
+    pub fn pages_through_entries() {
+        let entries = entries();
+        let mut query = EntriesQuery {
+            sort: SortOrder::Newest,
+            limit: Some(2),
+            ..Default::default()
+        };
+        let mut pages = Vec::new();
+        loop {
+            let page = EntryFilter::from_query(query.clone(), &KindCatalogue::default())
+                .unwrap()
+                .apply(entries.clone());
+            pages.push(days(&page.list));
+            match page.next_cursor {
+                Some(cursor) => query.cursor = Some(cursor),
+                None => break,
+            }
+        }
+
+        assert_eq!(pages, vec![vec![5, 4], vec![3, 2], vec![1]]);
+    }
+
+    #[test]
+    pub fn filters_by_date_and_kind() {
+        let query = EntriesQuery {
+            from: Some(Utc.ymd(2021, 5, 2).and_hms(0, 0, 0)),
+            to: Some(Utc.ymd(2021, 5, 5).and_hms(0, 0, 0)),
+            kind: Some("laufen".to_owned()),
+            ..Default::default()
+        };
+
+        let page = EntryFilter::from_query(query, &KindCatalogue::default())
+            .unwrap()
+            .apply(entries());
+
+        assert_eq!(days(&page.list), vec![3]);
+        assert_eq!(page.next_cursor, None);
+        let invalid = |query| EntryFilter::from_query(query, &KindCatalogue::default()).is_err();
+        assert!(invalid(EntriesQuery {
+            cursor: Some("nonsense".to_owned()),
+            ..Default::default()
+        }));
+        assert!(invalid(EntriesQuery {
+            kind: Some("Running".to_owned()),
+            ..Default::default()
+        }));
+    }
+}
@@ -0,0 +1,140 @@
+use argon2::{Config, ThreadMode, Variant, Version};
+use constant_time_eq::constant_time_eq;
+use rand::prelude::*;
+use sha2::{Digest, Sha256};
+
+use crate::models::{PasswordParams, User};
+
+/// The parameters new hashes are computed with; users with other parameters
+/// are rehashed when they log in.
+pub const CURRENT_PARAMS: PasswordParams = PasswordParams::Argon2id {
+    memory_kib: 19 * 1024,
+    iterations: 2,
+    lanes: 1,
+};
+
+const SALT_LENGTH: usize = 16;
+const HASH_LENGTH: u32 = 32;
+/// Unknown users are checked against this salt, so that they take as long
+/// as known ones.
+const DUMMY_SALT: &str = "AAAAAAAAAAAAAAAAAAAAAA==";
+
+fn compute(pass: &str, salt: &str, params: PasswordParams) -> Result<Vec<u8>, argon2::Error> {
+    match params {
+        PasswordParams::Sha256 => {
+            let mut hasher = Sha256::new();
+            hasher.update(pass.to_owned() + salt);
+            Ok(hasher.finalize().to_vec())
+        }
+        PasswordParams::Argon2id {
+            memory_kib,
+            iterations,
+            lanes,
+        } => {
+            let config = Config {
+                variant: Variant::Argon2id,
+                version: Version::Version13,
+                mem_cost: memory_kib,
+                time_cost: iterations,
+                lanes,
+                thread_mode: ThreadMode::Sequential,
+                hash_length: HASH_LENGTH,
+                ..Config::default()
+            };
+            argon2::hash_raw(pass.as_bytes(), salt.as_bytes(), &config)
+        }
+    }
+}
+
+/// Hashes a password with a new random salt.
+pub fn hash(pass: &str, params: PasswordParams) -> Result<User, argon2::Error> {
+    let mut salt_bytes = [0_u8; SALT_LENGTH];
+    rand::thread_rng().fill_bytes(&mut salt_bytes);
+    let salt = base64::encode(salt_bytes);
+    let hash = compute(pass, &salt, params)?;
+
+    Ok(User {
+        hash: base64::encode(hash),
+        salt,
+        params,
+    })
+}
+
+/// Checks a password against the stored hash, taking the same time for every
+/// hash of the same length.
+pub fn verify(pass: &str, user: &User) -> bool {
+    let expected = match base64::decode(&user.hash) {
+        Ok(expected) => expected,
+        Err(_) => return false,
+    };
+    match compute(pass, &user.salt, user.params) {
+        Ok(hash) => !expected.is_empty() && constant_time_eq(&hash, &expected),
+        Err(_) => false,
+    }
+}
+
+/// Hashes the password for a user that does not exist, taking as long as
+/// [`verify`] with the current parameters, and rejects it.
+pub fn reject_unknown(pass: &str) -> bool {
+    let _ = compute(pass, DUMMY_SALT, CURRENT_PARAMS);
+    false
+}
+
+#[cfg(test)]
+mod test {
+    use sha2::{Digest, Sha256};
+
+    use super::{hash, reject_unknown, verify};
+    use crate::models::{PasswordParams, User};
+
+    const CHEAP: PasswordParams = PasswordParams::Argon2id {
+        memory_kib: 64,
+        iterations: 1,
+        lanes: 1,
+    };
+
+    #[test]
+    pub fn verifies_argon2_hashes() {
+        let user = hash("kaese", CHEAP).unwrap();
+        let other_salt = User {
+            hash: user.hash.clone(),
+            ..hash("kaese", CHEAP).unwrap()
+        };
+        let empty = User {
+            hash: String::new(),
+            ..user.clone()
+        };
+
+        assert_eq!(user.params, CHEAP);
+        assert!(verify("kaese", &user));
+        assert!(!verify("wurst", &user));
+        assert!(!verify("kaese", &other_salt));
+        assert!(!verify("kaese", &empty));
+        assert!(!reject_unknown("kaese"));
+    }
+
+    #[test]
+    pub fn fails_on_invalid_parameters() {
+        let invalid = PasswordParams::Argon2id {
+            memory_kib: 0,
+            iterations: 0,
+            lanes: 0,
+        };
+        assert!(hash("kaese", invalid).is_err());
+    }
+
+    #[test]
+    pub fn verifies_legacy_sha256_hashes() {
+        let mut hasher = Sha256::new();
+        hasher.update("kaesesalt");
+        let stored = format!(
+            r#"{{"hash": "{}", "salt": "salt"}}"#,
+            base64::encode(hasher.finalize())
+        );
+        let user: User = serde_json::from_str(&stored).unwrap();
+
+        assert_eq!(user.params, PasswordParams::Sha256);
+        assert!(verify("kaese", &user));
+        assert!(!verify("wurst", &user));
+    }
+}
@@ -1,35 +1,54 @@
-use shared::{Kilometer, Kind, UserAuth};
+use std::convert::Infallible;
+
+use serde::Deserialize;
+use shared::{
+    EntriesQuery, HighscoreQuery, KilometerEntryPatch, KindInfo, NewKilometerEntry, UserAuth,
+};
+use uuid::Uuid;
 use warp::{self, Filter};
 
 use crate::db::db::Database;
+use crate::error;
+use crate::middleware::remote_address;
+use crate::openapi;
 use crate::{handlers, middleware::authentication_middleware, middleware::with_database};
 
-pub fn routes(
-    db: Database,
-) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+pub fn routes(db: Database) -> impl Filter<Extract = impl warp::Reply, Error = Infallible> + Clone {
     let static_content = warp::get().and(warp::fs::dir("../jdav_client/dist"));
     let static_content_deployed = warp::get().and(warp::fs::dir("./dist"));
 
-    static_content_deployed
-        .or(static_content)
-        .or(create_user(db.clone()))
+    let api = create_user(db.clone())
         .or(authenticate_user(db.clone()))
-        .or(create_running_entry(db.clone()))
-        .or(create_biking_entry(db.clone()))
-        .or(create_climbing_entry(db.clone()))
-        .or(create_swimming_entry(db.clone()))
-        .or(create_skating_entry(db.clone()))
-        .or(create_hiking_entry(db.clone()))
-        .or(create_nordic_walking_entry(db.clone()))
-        .or(edit_kilometer_entry(db.clone()))
+        .or(logout(db.clone()))
+        .or(create_kilometer_entry(db.clone()))
+        .or(get_kilometer_entry(db.clone()))
+        .or(replace_kilometer_entry(db.clone()))
+        .or(patch_kilometer_entry(db.clone()))
+        .or(delete_kilometer_entry(db.clone()))
         .or(get_entries_for_user(db.clone()))
+        .or(get_stats_for_user(db.clone()))
+        .or(get_trash_for_user(db.clone()))
+        .or(restore_kilometer_entry(db.clone()))
+        .or(get_kinds(db.clone()))
+        .or(create_kind(db.clone()))
+        .or(update_kind(db.clone()))
+        .or(retire_kind(db.clone()))
+        .or(clear_lockout(db.clone()))
+        .or(get_scoring(db.clone()))
         .or(get_highscore(db))
+        .or(get_openapi());
+
+    static_content_deployed
+        .or(static_content)
+        .or(warp::path!("api" / "v1" / ..).and(api))
+        .recover(error::handle_rejection)
 }
 
 fn create_user(
     db: Database,
 ) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
-    warp::path!("createuser")
+    warp::path!("users")
+        .and(warp::post())
         .and(json_auth_user())
         .and(with_database(db))
         .and_then(handlers::create_user)
@@ -39,135 +58,223 @@ fn authenticate_user(
     db: Database,
 ) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
     warp::path!("authenticate")
+        .and(warp::post())
         .and(json_auth_user())
+        .and(remote_address())
         .and(with_database(db))
         .and_then(handlers::authenticate_user)
 }
 
+fn logout(
+    db: Database,
+) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+    warp::path!("logout")
+        .and(warp::post())
+        .and(authentication_middleware())
+        .and(with_database(db))
+        .and_then(handlers::logout)
+}
+
 fn json_auth_user() -> impl Filter<Extract = (UserAuth,), Error = warp::Rejection> + Clone {
     warp::body::content_length_limit(1024 * 16).and(warp::body::json())
 }
 
-fn create_running_entry(
+fn create_kilometer_entry(
     db: Database,
 ) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
-    warp::path!("distanz" / String / "laufen")
-        .and(warp::put())
-        .and(warp::any().map(|| Kind::Running))
+    warp::path!("users" / String / "entries")
+        .and(warp::post())
+        .and(kind())
         .and(authentication_middleware())
         .and(json_kilometer_entry())
         .and(with_database(db))
         .and_then(handlers::create_kilometer_entry)
 }
 
-fn create_biking_entry(
+#[derive(Deserialize)]
+struct KindQuery {
+    kind: String,
+}
+
+fn kind() -> impl Filter<Extract = (String,), Error = warp::Rejection> + Clone {
+    warp::query::<KindQuery>().map(|KindQuery { kind }| kind)
+}
+
+fn get_kinds(
     db: Database,
 ) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
-    warp::path!("distanz" / String / "radfahren")
-        .and(warp::put())
-        .and(warp::any().map(|| Kind::Biking))
-        .and(authentication_middleware())
-        .and(json_kilometer_entry())
+    warp::path!("kinds")
+        .and(warp::get())
         .and(with_database(db))
-        .and_then(handlers::create_kilometer_entry)
+        .and_then(handlers::get_kinds)
 }
 
-fn create_climbing_entry(
+fn create_kind(
     db: Database,
 ) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
-    warp::path!("distanz" / String / "klettern")
-        .and(warp::put())
-        .and(warp::any().map(|| Kind::Climbing))
+    warp::path!("kinds")
+        .and(warp::post())
         .and(authentication_middleware())
-        .and(json_kilometer_entry())
+        .and(json_kind())
         .and(with_database(db))
-        .and_then(handlers::create_kilometer_entry)
+        .and_then(handlers::create_kind)
 }
 
-fn create_swimming_entry(
+fn update_kind(
     db: Database,
 ) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
-    warp::path!("distanz" / String / "schwimmen")
+    warp::path!("kinds" / String)
         .and(warp::put())
-        .and(warp::any().map(|| Kind::Swimming))
         .and(authentication_middleware())
-        .and(json_kilometer_entry())
+        .and(json_kind())
         .and(with_database(db))
-        .and_then(handlers::create_kilometer_entry)
+        .and_then(handlers::update_kind)
 }
 
-fn create_skating_entry(
+fn retire_kind(
     db: Database,
 ) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
-    warp::path!("distanz" / String / "skaten")
-        .and(warp::put())
-        .and(warp::any().map(|| Kind::Skating))
+    warp::path!("kinds" / String)
+        .and(warp::delete())
         .and(authentication_middleware())
-        .and(json_kilometer_entry())
         .and(with_database(db))
-        .and_then(handlers::create_kilometer_entry)
+        .and_then(handlers::retire_kind)
 }
 
-fn create_hiking_entry(
+fn clear_lockout(
     db: Database,
 ) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
-    warp::path!("distanz" / String / "wandern")
-        .and(warp::put())
-        .and(warp::any().map(|| Kind::Hiking))
+    warp::path!("lockouts" / String)
+        .and(warp::delete())
         .and(authentication_middleware())
-        .and(json_kilometer_entry())
         .and(with_database(db))
-        .and_then(handlers::create_kilometer_entry)
+        .and_then(handlers::clear_lockout)
+}
+
+fn json_kind() -> impl Filter<Extract = (KindInfo,), Error = warp::Rejection> + Clone {
+    warp::body::content_length_limit(1024 * 16).and(warp::body::json())
 }
 
-fn create_nordic_walking_entry(
+fn get_scoring(
     db: Database,
 ) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
-    warp::path!("distanz" / String / "nordic_walking")
-        .and(warp::put())
-        .and(warp::any().map(|| Kind::NordicWalking))
-        .and(authentication_middleware())
-        .and(json_kilometer_entry())
+    warp::path!("scoring")
+        .and(warp::get())
         .and(with_database(db))
-        .and_then(handlers::create_kilometer_entry)
+        .and_then(handlers::get_scoring)
 }
 
-fn json_kilometer_entry() -> impl Filter<Extract = (Kilometer,), Error = warp::Rejection> + Clone {
+fn json_kilometer_entry(
+) -> impl Filter<Extract = (NewKilometerEntry,), Error = warp::Rejection> + Clone {
     warp::body::content_length_limit(1024 * 16).and(warp::body::json())
 }
 
-fn edit_kilometer_entry(
+fn json_edit_kilometer_entry(
+) -> impl Filter<Extract = (shared::KilometerEntry,), Error = warp::Rejection> + Clone {
+    warp::body::content_length_limit(1024 * 16).and(warp::body::json())
+}
+
+fn get_entries_for_user(
+    db: Database,
+) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+    warp::path!("users" / String / "entries")
+        .and(warp::get())
+        .and(authentication_middleware())
+        .and(warp::query::<EntriesQuery>())
+        .and(with_database(db))
+        .and_then(handlers::get_entries_for_user)
+}
+
+fn get_kilometer_entry(
     db: Database,
 ) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
-    warp::path!("edit" / String)
+    warp::path!("users" / String / "entries" / Uuid)
+        .and(warp::get())
+        .and(authentication_middleware())
+        .and(with_database(db))
+        .and_then(handlers::get_kilometer_entry)
+}
+
+fn replace_kilometer_entry(
+    db: Database,
+) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+    warp::path!("users" / String / "entries" / Uuid)
         .and(warp::put())
         .and(authentication_middleware())
         .and(json_edit_kilometer_entry())
         .and(with_database(db))
-        .and_then(handlers::edit_kilometer_entry)
+        .and_then(handlers::replace_kilometer_entry)
 }
 
-fn json_edit_kilometer_entry(
-) -> impl Filter<Extract = (shared::KilometerEntry,), Error = warp::Rejection> + Clone {
+fn patch_kilometer_entry(
+    db: Database,
+) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+    warp::path!("users" / String / "entries" / Uuid)
+        .and(warp::patch())
+        .and(authentication_middleware())
+        .and(json_patch_kilometer_entry())
+        .and(with_database(db))
+        .and_then(handlers::patch_kilometer_entry)
+}
+
+fn json_patch_kilometer_entry(
+) -> impl Filter<Extract = (KilometerEntryPatch,), Error = warp::Rejection> + Clone {
     warp::body::content_length_limit(1024 * 16).and(warp::body::json())
 }
 
-fn get_entries_for_user(
+fn delete_kilometer_entry(
     db: Database,
 ) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
-    warp::path!("entries" / String)
+    warp::path!("users" / String / "entries" / Uuid)
+        .and(warp::delete())
+        .and(authentication_middleware())
+        .and(with_database(db))
+        .and_then(handlers::delete_kilometer_entry)
+}
+
+fn get_stats_for_user(
+    db: Database,
+) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+    warp::path!("users" / String / "stats")
         .and(warp::get())
         .and(authentication_middleware())
         .and(with_database(db))
-        .and_then(handlers::get_entries_for_user)
+        .and_then(handlers::get_stats_for_user)
+}
+
+fn get_trash_for_user(
+    db: Database,
+) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+    warp::path!("users" / String / "trash")
+        .and(warp::get())
+        .and(authentication_middleware())
+        .and(with_database(db))
+        .and_then(handlers::get_trash_for_user)
+}
+
+fn restore_kilometer_entry(
+    db: Database,
+) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+    warp::path!("users" / String / "trash" / Uuid / "restore")
+        .and(warp::post())
+        .and(authentication_middleware())
+        .and(with_database(db))
+        .and_then(handlers::restore_kilometer_entry)
 }
 
 fn get_highscore(
     db: Database,
 ) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
     warp::path!("highscore")
-        .and(warp::put())
+        .and(warp::get())
         .and(authentication_middleware())
+        .and(warp::query::<HighscoreQuery>())
         .and(with_database(db))
         .and_then(handlers::get_highscore)
 }
+
+fn get_openapi() -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+    warp::path!("openapi.json")
+        .and(warp::get())
+        .map(|| warp::reply::json(&openapi::document()))
+}
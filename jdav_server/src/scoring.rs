@@ -0,0 +1,203 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use shared::validation::MAX_MULTIPLIER;
+use shared::{Kind, ScoringRules, Unit};
+
+use crate::db::persistence::write_atomically;
+
+pub static SCORING_FILENAME: &str = "./scoring.json";
+
+/// The rules as saved. Files without `climbing` were written while climbing
+/// was measured in kilometers.
+#[derive(Debug, Deserialize, Serialize)]
+struct ScoringFile {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    climbing: Option<Unit>,
+    #[serde(flatten)]
+    rules: ScoringRules,
+}
+
+#[derive(Debug)]
+pub enum ScoringError {
+    Unreadable { path: PathBuf, source: io::Error },
+    Invalid { path: PathBuf, message: String },
+}
+
+impl fmt::Display for ScoringError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScoringError::Unreadable { path, source } => {
+                write!(f, "could not read {}: {}", path.display(), source)
+            }
+            ScoringError::Invalid { path, message } => {
+                write!(
+                    f,
+                    "invalid scoring rules in {}: {}",
+                    path.display(),
+                    message
+                )
+            }
+        }
+    }
+}
+
+/// Loads the scoring rules, falling back to the multipliers of the kind
+/// catalogue when the file does not exist.
+pub fn load(path: &Path) -> Result<ScoringRules, ScoringError> {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(ScoringRules::default()),
+        Err(source) => {
+            return Err(ScoringError::Unreadable {
+                path: path.to_owned(),
+                source,
+            })
+        }
+    };
+    let invalid = |message: String| ScoringError::Invalid {
+        path: path.to_owned(),
+        message,
+    };
+    let mut file: ScoringFile =
+        serde_json::from_str(&content).map_err(|error| invalid(error.to_string()))?;
+    validate(&file.rules).map_err(invalid)?;
+    if file.climbing != Some(Unit::Elevation) {
+        climb_in_metres(&mut file.rules).map_err(invalid)?;
+        file.climbing = Some(Unit::Elevation);
+        let json = serde_json::to_vec_pretty(&file).map_err(|error| invalid(error.to_string()))?;
+        write_atomically(path, &json).map_err(|source| ScoringError::Unreadable {
+            path: path.to_owned(),
+            source,
+        })?;
+    }
+    Ok(file.rules)
+}
+
+/// Climbing used to be measured in kilometers. Its multipliers are converted
+/// like those of the kind catalogue, so that climbing still scores the same
+/// points.
+fn climb_in_metres(rules: &mut ScoringRules) -> Result<(), String> {
+    for rules in &mut rules.versions {
+        let version = rules.version;
+        if let Some(multiplier) = rules.multipliers.get_mut(&Kind::new("Climbing")) {
+            *multiplier = multiplier.exact_thousandth().ok_or_else(|| {
+                format!(
+                    "version {} has a multiplier {} for Climbing that cannot be converted to metres without rounding",
+                    version, multiplier
+                )
+            })?;
+        }
+    }
+    Ok(())
+}
+
+fn validate(rules: &ScoringRules) -> Result<(), String> {
+    for pair in rules.versions.windows(2) {
+        if pair[0].version >= pair[1].version || pair[0].effective_from >= pair[1].effective_from {
+            return Err(format!(
+                "version {} must follow version {} with a later effective date",
+                pair[1].version, pair[0].version
+            ));
+        }
+    }
+    for rules in &rules.versions {
+        for (kind, multiplier) in &rules.multipliers {
+            if multiplier.is_negative() || *multiplier > MAX_MULTIPLIER {
+                return Err(format!(
+                    "version {} has an invalid multiplier {} for {}, it must be from 0 to {}",
+                    rules.version, multiplier, kind, MAX_MULTIPLIER
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::prelude::*;
+    use shared::{Decimal, Kind, RuleSet, ScoringRules};
+
+    use super::{load, validate, MAX_MULTIPLIER};
+    use crate::test_util::TestDirectory;
+
+    fn version(version: u32, effective_from: NaiveDate) -> RuleSet {
+        RuleSet {
+            version,
+            effective_from,
+            multipliers: vec![(Kind::new("Biking"), Decimal::from_thousandths(500))]
+                .into_iter()
+                .collect(),
+        }
+    }
+
+    #[test]
+    pub fn rejects_unordered_or_negative_rules() {
+        assert_eq!(validate(&ScoringRules::default()), Ok(()));
+
+        let mut rules = ScoringRules {
+            versions: vec![version(1, NaiveDate::from_ymd(2021, 1, 1))],
+        };
+        assert_eq!(validate(&rules), Ok(()));
+
+        rules
+            .versions
+            .push(version(2, NaiveDate::from_ymd(1960, 1, 1)));
+        assert!(validate(&rules).is_err());
+
+        let mut negative = version(1, NaiveDate::from_ymd(2021, 1, 1));
+        negative
+            .multipliers
+            .insert(Kind::new("Running"), -Decimal::from(1));
+        assert!(validate(&ScoringRules {
+            versions: vec![negative]
+        })
+        .is_err());
+
+        let mut huge = version(1, NaiveDate::from_ymd(2021, 1, 1));
+        huge.multipliers
+            .insert(Kind::new("Running"), MAX_MULTIPLIER + Decimal::from(1));
+        assert!(validate(&ScoringRules {
+            versions: vec![huge]
+        })
+        .is_err());
+    }
+
+    #[test]
+    pub fn converts_climbing_to_metres_once() {
+        let directory = TestDirectory::create();
+        let path = directory.join("scoring.json");
+        std::fs::write(
+            &path,
+            r#"{"versions": [{"version": 1, "effective_from": "2021-01-01",
+                "multipliers": {"Climbing": 100.0, "Running": 1.0}}]}"#,
+        )
+        .unwrap();
+
+        for _ in 0..2 {
+            let rules = load(&path).unwrap();
+            let multipliers = &rules.versions[0].multipliers;
+            assert_eq!(
+                multipliers[&Kind::new("Climbing")],
+                Decimal::from_thousandths(100)
+            );
+            assert_eq!(multipliers[&Kind::new("Running")], Decimal::from(1));
+        }
+    }
+
+    #[test]
+    pub fn refuses_to_round_climbing_multipliers() {
+        let directory = TestDirectory::create();
+        let path = directory.join("scoring.json");
+        let content = r#"{"versions": [{"version": 1, "effective_from": "2021-01-01",
+                "multipliers": {"Climbing": 150.5}}]}"#;
+        std::fs::write(&path, content).unwrap();
+
+        assert!(load(&path).is_err());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), content);
+    }
+}
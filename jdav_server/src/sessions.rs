@@ -0,0 +1,181 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Duration, Utc};
+use rand::prelude::*;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use shared::Session;
+use tokio::sync::RwLock;
+
+use crate::db::persistence::write_atomically_async;
+use crate::error::ApiError;
+
+pub static SESSIONS_FILENAME: &str = "./sessions.json";
+
+#[derive(Debug)]
+pub enum SessionsError {
+    Unreadable { path: PathBuf, source: io::Error },
+    Invalid { path: PathBuf, message: String },
+}
+
+impl fmt::Display for SessionsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SessionsError::Unreadable { path, source } => {
+                write!(f, "could not read {}: {}", path.display(), source)
+            }
+            SessionsError::Invalid { path, message } => {
+                write!(f, "invalid sessions in {}: {}", path.display(), message)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
+struct StoredSession {
+    user: String,
+    expires_at: DateTime<Utc>,
+}
+
+/// The open sessions, kept in a JSON file next to the database. Only hashes of
+/// the tokens are stored, so the file cannot be used to log in.
+pub struct SessionStore {
+    path: PathBuf,
+    lifetime: Duration,
+    sessions: RwLock<HashMap<String, StoredSession>>,
+}
+
+impl SessionStore {
+    /// Loads the open sessions, starting without any when the file does not
+    /// exist.
+    pub fn load(path: &Path, lifetime: Duration) -> Result<Self, SessionsError> {
+        let sessions = match fs::read_to_string(path) {
+            Ok(content) => {
+                serde_json::from_str(&content).map_err(|error| SessionsError::Invalid {
+                    path: path.to_owned(),
+                    message: error.to_string(),
+                })?
+            }
+            Err(error) if error.kind() == io::ErrorKind::NotFound => HashMap::new(),
+            Err(source) => {
+                return Err(SessionsError::Unreadable {
+                    path: path.to_owned(),
+                    source,
+                })
+            }
+        };
+        Ok(SessionStore {
+            path: path.to_owned(),
+            lifetime,
+            sessions: RwLock::new(sessions),
+        })
+    }
+
+    /// Opens a session for an authenticated user and drops the expired ones.
+    pub async fn create(&self, user: &str, now: DateTime<Utc>) -> Result<Session, ApiError> {
+        let mut token_bytes = [0_u8; 32];
+        rand::thread_rng().fill_bytes(&mut token_bytes);
+        let token = base64::encode_config(token_bytes, base64::URL_SAFE_NO_PAD);
+        let expires_at = now + self.lifetime;
+
+        let mut sessions = self.sessions.write().await;
+        let mut changed = sessions.clone();
+        changed.retain(|_, session| session.expires_at > now);
+        changed.insert(
+            hash(&token),
+            StoredSession {
+                user: user.to_owned(),
+                expires_at,
+            },
+        );
+        self.save(&mut sessions, changed).await?;
+
+        Ok(Session {
+            name: user.to_owned(),
+            token,
+            expires_at,
+        })
+    }
+
+    /// The user the token belongs to, unless it is unknown or expired.
+    pub async fn user(&self, token: &str, now: DateTime<Utc>) -> Option<String> {
+        self.sessions
+            .read()
+            .await
+            .get(&hash(token))
+            .filter(|session| session.expires_at > now)
+            .map(|session| session.user.clone())
+    }
+
+    /// Ends the session of the token, returning whether it was open.
+    pub async fn revoke(&self, token: &str) -> Result<bool, ApiError> {
+        let mut sessions = self.sessions.write().await;
+        let mut changed = sessions.clone();
+        if changed.remove(&hash(token)).is_none() {
+            return Ok(false);
+        }
+        self.save(&mut sessions, changed).await?;
+        Ok(true)
+    }
+
+    async fn save(
+        &self,
+        sessions: &mut HashMap<String, StoredSession>,
+        changed: HashMap<String, StoredSession>,
+    ) -> Result<(), ApiError> {
+        let json = serde_json::to_vec_pretty(&changed)
+            .map_err(|error| ApiError::Internal(error.to_string()))?;
+        write_atomically_async(&self.path, json)
+            .await
+            .map_err(|error| {
+                ApiError::Internal(format!("could not save {}: {}", self.path.display(), error))
+            })?;
+        *sessions = changed;
+        Ok(())
+    }
+}
+
+fn hash(token: &str) -> String {
+    base64::encode(Sha256::digest(token.as_bytes()))
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::{Duration, TimeZone, Utc};
+
+    use super::SessionStore;
+    use crate::test_util::TestDirectory;
+
+    #[tokio::test]
+    pub async fn sessions_expire_and_can_be_revoked() {
+        let directory = TestDirectory::create();
+        let path = directory.join("sessions.json");
+        let now = Utc.ymd(2021, 5, 1).and_hms(12, 0, 0);
+
+        let store = SessionStore::load(&path, Duration::hours(1)).unwrap();
+        let session = store.create("anna", now).await.unwrap();
+        assert_eq!(session.expires_at, now + Duration::hours(1));
+        assert_eq!(
+            store.user(&session.token, now).await,
+            Some("anna".to_owned())
+        );
+        assert_eq!(store.user(&session.token, session.expires_at).await, None);
+        assert_eq!(store.user("guessed", now).await, None);
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert!(!content.contains(&session.token));
+        let reloaded = SessionStore::load(&path, Duration::hours(1)).unwrap();
+        assert_eq!(
+            reloaded.user(&session.token, now).await,
+            Some("anna".to_owned())
+        );
+
+        assert!(reloaded.revoke(&session.token).await.unwrap());
+        assert!(!reloaded.revoke(&session.token).await.unwrap());
+        assert_eq!(reloaded.user(&session.token, now).await, None);
+    }
+}
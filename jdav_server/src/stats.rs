@@ -0,0 +1,188 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use chrono::prelude::*;
+use shared::{KilometerEntry, KindStats, MonthStats, Totals, Unit, UserStats, WeekStats};
+
+fn add(totals: &mut Totals, entry: &KilometerEntry) {
+    totals.kilometers += entry.measurement.kilometers();
+    totals.points += entry.points;
+    totals.entries += 1;
+}
+
+fn totals_by<K: Ord>(
+    entries: &[KilometerEntry],
+    key: impl Fn(&KilometerEntry) -> K,
+) -> BTreeMap<K, Totals> {
+    let mut totals = BTreeMap::new();
+    for entry in entries {
+        add(totals.entry(key(entry)).or_default(), entry);
+    }
+    totals
+}
+
+/// Returns the current and the longest run of consecutive days with at least
+/// one entry. The current streak still counts when nothing was entered today.
+fn streaks(entries: &[KilometerEntry], today: NaiveDate) -> (u32, u32) {
+    let days = entries
+        .iter()
+        .map(|entry| entry.timestamp.date().naive_utc())
+        .collect::<BTreeSet<_>>();
+
+    let mut longest = 0;
+    let mut run = 0;
+    let mut previous: Option<NaiveDate> = None;
+    for day in &days {
+        run = match previous {
+            Some(previous) if previous.succ() == *day => run + 1,
+            _ => 1,
+        };
+        longest = longest.max(run);
+        previous = Some(*day);
+    }
+
+    let current = match previous {
+        Some(last) if last == today || last.succ() == today => run,
+        _ => 0,
+    };
+    (current, longest)
+}
+
+pub fn user_stats(entries: &[KilometerEntry], today: NaiveDate) -> UserStats {
+    let mut total = Totals::default();
+    entries.iter().for_each(|entry| add(&mut total, entry));
+
+    let per_kind = totals_by(entries, |entry| entry.kind.clone())
+        .into_iter()
+        .map(|(kind, totals)| KindStats {
+            amount: entries
+                .iter()
+                .filter(|entry| entry.kind == kind)
+                .map(|entry| entry.measurement.value())
+                .sum(),
+            kind,
+            totals,
+        })
+        .collect();
+
+    let per_week = totals_by(entries, |entry| {
+        let week = entry.timestamp.iso_week();
+        (week.year(), week.week())
+    })
+    .into_iter()
+    .map(|((year, week), totals)| WeekStats { year, week, totals })
+    .collect();
+
+    let per_month = totals_by(entries, |entry| {
+        (entry.timestamp.year(), entry.timestamp.month())
+    })
+    .into_iter()
+    .map(|((year, month), totals)| MonthStats {
+        year,
+        month,
+        totals,
+    })
+    .collect();
+
+    let longest_activity = entries
+        .iter()
+        .filter(|entry| entry.measurement.unit() == Unit::Distance)
+        .max_by_key(|entry| entry.measurement.kilometers())
+        .cloned();
+
+    let (current_streak, longest_streak) = streaks(entries, today);
+
+    UserStats {
+        total,
+        per_kind,
+        per_week,
+        per_month,
+        longest_activity,
+        current_streak,
+        longest_streak,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::prelude::*;
+    use shared::{Decimal, KilometerEntry, Kind, KindCatalogue};
+
+    use super::user_stats;
+
+    fn entry(kind: &str, amount: u32, month: u32, day: u32) -> KilometerEntry {
+        let amount = Decimal::from(amount);
+        let kind = Kind::new(kind);
+        let info = KindCatalogue::default().get(&kind).unwrap().clone();
+        KilometerEntry {
+            points: amount.checked_mul(info.multiplier).unwrap(),
+            kind,
+            measurement: info.unit.measure(amount),
+            timestamp: Utc.ymd(2021, month, day).and_hms(18, 0, 0),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    pub fn aggregates_per_kind_week_and_month() {
+        let entries = vec![
+            entry("Running", 10, 5, 30),
+            entry("Biking", 40, 5, 31),
+            entry("Running", 5, 6, 1),
+            entry("Yoga", 90, 6, 1),
+        ];
+
+        let stats = user_stats(&entries, NaiveDate::from_ymd(2021, 6, 1));
+
+        assert_eq!(stats.total.kilometers, Decimal::from(55));
+        assert_eq!(stats.total.points, Decimal::from(10 + 10 + 5 + 9));
+        assert_eq!(stats.total.entries, 4);
+
+        assert_eq!(stats.per_kind.len(), 3);
+        assert_eq!(stats.per_kind[0].kind, Kind::new("Biking"));
+        assert_eq!(stats.per_kind[0].totals.points, Decimal::from(10));
+        assert_eq!(stats.per_kind[1].totals.kilometers, Decimal::from(15));
+        assert_eq!(stats.per_kind[2].amount, Decimal::from(90));
+        assert_eq!(stats.per_kind[2].totals.kilometers, Decimal::ZERO);
+
+        let weeks = stats
+            .per_week
+            .iter()
+            .map(|week| (week.week, week.totals.entries))
+            .collect::<Vec<_>>();
+        assert_eq!(weeks, vec![(21, 1), (22, 3)]);
+
+        let months = stats
+            .per_month
+            .iter()
+            .map(|month| (month.month, month.totals.kilometers))
+            .collect::<Vec<_>>();
+        assert_eq!(months, vec![(5, Decimal::from(50)), (6, Decimal::from(5))]);
+
+        assert_eq!(stats.longest_activity, Some(entries[1].clone()));
+    }
+
+    #[test]
+    pub fn counts_day_streaks() {
+        let entries = vec![
+            entry("Running", 1, 5, 1),
+            entry("Running", 1, 5, 2),
+            entry("Hiking", 1, 5, 2),
+            entry("Running", 1, 5, 3),
+            entry("Running", 1, 5, 10),
+            entry("Running", 1, 5, 11),
+        ];
+
+        let streak = |day| {
+            let stats = user_stats(&entries, NaiveDate::from_ymd(2021, 5, day));
+            (stats.current_streak, stats.longest_streak)
+        };
+
+        assert_eq!(streak(11), (2, 3));
+        assert_eq!(streak(12), (2, 3));
+        assert_eq!(streak(13), (0, 3));
+        assert_eq!(
+            user_stats(&[], NaiveDate::from_ymd(2021, 5, 1)).longest_streak,
+            0
+        );
+    }
+}
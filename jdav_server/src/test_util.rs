@@ -0,0 +1,29 @@
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+
+use uuid::Uuid;
+
+/// An empty directory for the files of a test, removed when dropped.
+pub struct TestDirectory(PathBuf);
+
+impl TestDirectory {
+    pub fn create() -> Self {
+        let directory = std::env::temp_dir().join(format!("jdav-test-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&directory).unwrap();
+        TestDirectory(directory)
+    }
+}
+
+impl Deref for TestDirectory {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TestDirectory {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
@@ -0,0 +1,356 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::net::IpAddr;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use chrono::{DateTime, Duration, Utc};
+use tokio::sync::{Mutex as AsyncMutex, OwnedMutexGuard};
+use uuid::Uuid;
+
+use crate::db::persistence::write_atomically;
+use crate::error::ApiError;
+
+/// Where `jdav_server unlock` leaves its requests for the running server.
+pub const UNLOCK_DIRECTORY: &str = "unlock";
+const UNLOCK_EXTENSION: &str = "unlock";
+
+/// Where the current time comes from, so that tests can move it.
+pub trait Clock: Send + Sync {
+    fn now(&self) -> DateTime<Utc>;
+}
+
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+/// What failed attempts are counted for.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ThrottleKey {
+    User(String),
+    Address(IpAddr),
+}
+
+impl ThrottleKey {
+    /// The user and, when known, the address of a login attempt.
+    pub fn login(user: &str, address: Option<IpAddr>) -> Vec<ThrottleKey> {
+        let mut keys = vec![ThrottleKey::User(user.to_owned())];
+        keys.extend(address.map(ThrottleKey::Address));
+        keys
+    }
+}
+
+pub struct ThrottlePolicy {
+    /// Failures that are not delayed at all.
+    pub free_failures: u32,
+    /// The delay after the first failure beyond the free ones, doubled with
+    /// every further failure.
+    pub initial_delay: Duration,
+    /// Failures after which the key is locked out.
+    pub lockout_after: u32,
+    pub lockout: Duration,
+    /// Failures are forgotten when there was none for this long.
+    pub forget_after: Duration,
+}
+
+impl Default for ThrottlePolicy {
+    fn default() -> Self {
+        ThrottlePolicy {
+            free_failures: 3,
+            initial_delay: Duration::seconds(1),
+            lockout_after: 10,
+            lockout: Duration::minutes(15),
+            forget_after: Duration::hours(1),
+        }
+    }
+}
+
+impl ThrottlePolicy {
+    fn delay(&self, failures: u32) -> Duration {
+        if failures >= self.lockout_after {
+            return self.lockout;
+        }
+        if failures <= self.free_failures {
+            return Duration::zero();
+        }
+        let doublings = (failures - self.free_failures - 1).min(30);
+        (self.initial_delay * 2_i32.pow(doublings)).min(self.lockout)
+    }
+}
+
+struct Failures {
+    count: u32,
+    last: DateTime<Utc>,
+    blocked_until: DateTime<Utc>,
+}
+
+/// Counts failed logins per user and per address and blocks further attempts
+/// for exponentially growing delays, up to a temporary lockout.
+pub struct Throttle {
+    policy: ThrottlePolicy,
+    clock: Arc<dyn Clock>,
+    failures: Mutex<HashMap<ThrottleKey, Failures>>,
+    checking: Mutex<HashMap<ThrottleKey, Arc<AsyncMutex<()>>>>,
+}
+
+impl Throttle {
+    pub fn new(policy: ThrottlePolicy, clock: Arc<dyn Clock>) -> Self {
+        Throttle {
+            policy,
+            clock,
+            failures: Mutex::new(HashMap::new()),
+            checking: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Rejects an attempt while any of the keys is blocked.
+    pub fn check(&self, keys: &[ThrottleKey]) -> Result<(), ApiError> {
+        let now = self.clock.now();
+        blocked(&self.failures.lock().unwrap(), keys, now)
+    }
+
+    pub fn failed(&self, keys: &[ThrottleKey]) {
+        let now = self.clock.now();
+        let mut failures = self.failures.lock().unwrap();
+        let forget_after = self.policy.forget_after;
+        failures.retain(|_, failures| now - failures.last < forget_after);
+        for key in keys {
+            let failures = failures.entry(key.clone()).or_insert(Failures {
+                count: 0,
+                last: now,
+                blocked_until: now,
+            });
+            failures.count += 1;
+            failures.last = now;
+            failures.blocked_until = now + self.policy.delay(failures.count);
+        }
+    }
+
+    /// Waits until no other password check for the key is running, so that
+    /// parallel guesses for one user see the failures of those before them.
+    pub async fn one_at_a_time(&self, key: &ThrottleKey) -> OwnedMutexGuard<()> {
+        let lock = {
+            let mut checking = self.checking.lock().unwrap();
+            checking.retain(|_, lock| Arc::strong_count(lock) > 1);
+            checking.entry(key.clone()).or_default().clone()
+        };
+        lock.lock_owned().await
+    }
+
+    /// Forgets the failures of a key, returning whether there were any.
+    pub fn clear(&self, key: &ThrottleKey) -> bool {
+        self.failures.lock().unwrap().remove(key).is_some()
+    }
+
+    /// Lifts the lockout of a user or, if the key is one, an address.
+    pub fn lift(&self, key: &str) -> bool {
+        let user = self.clear(&ThrottleKey::User(key.to_owned()));
+        let address = match key.parse::<IpAddr>() {
+            Ok(address) => self.clear(&ThrottleKey::Address(address)),
+            Err(_) => false,
+        };
+        user || address
+    }
+
+    /// Lifts the lockouts requested with [`request_lift`] and removes the
+    /// requests, returning the keys and whether they were locked.
+    pub fn lift_requested(&self, directory: &Path) -> io::Result<Vec<(String, bool)>> {
+        if !directory.exists() {
+            return Ok(Vec::new());
+        }
+        let mut lifted = Vec::new();
+        for file in fs::read_dir(directory)? {
+            let path = file?.path();
+            if path.extension() != Some(UNLOCK_EXTENSION.as_ref()) {
+                continue;
+            }
+            let key = fs::read_to_string(&path)?;
+            fs::remove_file(&path)?;
+            let locked = self.lift(key.trim());
+            lifted.push((key.trim().to_owned(), locked));
+        }
+        Ok(lifted)
+    }
+}
+
+/// Asks the running server to lift the lockout of a user or address. The
+/// failures are only counted in its memory, so another process cannot clear
+/// them directly.
+pub fn request_lift(directory: &Path, key: &str) -> io::Result<()> {
+    fs::create_dir_all(directory)?;
+    let path = directory
+        .join(Uuid::new_v4().to_string())
+        .with_extension(UNLOCK_EXTENSION);
+    write_atomically(&path, key.as_bytes())
+}
+
+fn blocked(
+    failures: &HashMap<ThrottleKey, Failures>,
+    keys: &[ThrottleKey],
+    now: DateTime<Utc>,
+) -> Result<(), ApiError> {
+    let blocked_until = keys
+        .iter()
+        .filter_map(|key| failures.get(key))
+        .map(|failures| failures.blocked_until)
+        .max();
+    match blocked_until {
+        Some(until) if until > now => {
+            let millis = (until - now).num_milliseconds();
+            Err(ApiError::TooManyAttempts {
+                retry_after: ((millis + 999) / 1000) as u64,
+            })
+        }
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::net::IpAddr;
+    use std::sync::{Arc, Mutex};
+
+    use chrono::{DateTime, Duration, TimeZone, Utc};
+
+    use super::{request_lift, Clock, Throttle, ThrottleKey, ThrottlePolicy};
+    use crate::error::ApiError;
+    use crate::test_util::TestDirectory;
+
+    struct ManualClock(Mutex<DateTime<Utc>>);
+
+    impl ManualClock {
+        fn advance(&self, duration: Duration) {
+            let mut now = self.0.lock().unwrap();
+            *now = *now + duration;
+        }
+    }
+
+    impl Clock for ManualClock {
+        fn now(&self) -> DateTime<Utc> {
+            *self.0.lock().unwrap()
+        }
+    }
+
+    fn throttle() -> (Throttle, Arc<ManualClock>) {
+        let clock = Arc::new(ManualClock(Mutex::new(
+            Utc.ymd(2021, 5, 1).and_hms(12, 0, 0),
+        )));
+        (
+            Throttle::new(ThrottlePolicy::default(), clock.clone()),
+            clock,
+        )
+    }
+
+    fn retry_after(throttle: &Throttle, keys: &[ThrottleKey]) -> u64 {
+        match throttle.check(keys) {
+            Ok(()) => 0,
+            Err(ApiError::TooManyAttempts { retry_after }) => retry_after,
+            Err(error) => panic!("unexpected error {:?}", error),
+        }
+    }
+
+    #[test]
+    pub fn backs_off_exponentially_until_lockout() {
+        let (throttle, clock) = throttle();
+        let address: IpAddr = "192.0.2.1".parse().unwrap();
+        let keys = ThrottleKey::login("hase", Some(address));
+
+        let mut delays = Vec::new();
+        for _ in 0..10 {
+            throttle.failed(&keys);
+            let delay = retry_after(&throttle, &keys);
+            delays.push(delay);
+            clock.advance(Duration::seconds(delay as i64));
+        }
+
+        assert_eq!(delays, vec![0, 0, 0, 1, 2, 4, 8, 16, 32, 15 * 60]);
+        assert_eq!(retry_after(&throttle, &keys), 0);
+    }
+
+    #[test]
+    pub fn counts_users_and_addresses_separately() {
+        let (throttle, clock) = throttle();
+        let address: IpAddr = "192.0.2.1".parse().unwrap();
+        for user in &["hase", "igel", "dachs", "fuchs"] {
+            throttle.failed(&ThrottleKey::login(user, Some(address)));
+        }
+
+        assert_eq!(retry_after(&throttle, &ThrottleKey::login("hase", None)), 0);
+        assert_eq!(
+            retry_after(&throttle, &ThrottleKey::login("hase", Some(address))),
+            1
+        );
+
+        assert!(throttle.clear(&ThrottleKey::Address(address)));
+        assert!(!throttle.clear(&ThrottleKey::Address(address)));
+        assert_eq!(
+            retry_after(&throttle, &ThrottleKey::login("hase", Some(address))),
+            0
+        );
+
+        for _ in 0..4 {
+            throttle.failed(&ThrottleKey::login("hase", None));
+        }
+        assert_eq!(retry_after(&throttle, &ThrottleKey::login("hase", None)), 2);
+        clock.advance(Duration::hours(2));
+        throttle.failed(&ThrottleKey::login("hase", None));
+        assert_eq!(
+            retry_after(&throttle, &ThrottleKey::login("hase", None)),
+            0,
+            "old failures are forgotten"
+        );
+    }
+
+    #[test]
+    pub fn lifts_lockouts_requested_by_another_process() {
+        let (throttle, _) = throttle();
+        let directory = TestDirectory::create();
+        for _ in 0..10 {
+            throttle.failed(&ThrottleKey::login("hase", "192.0.2.1".parse().ok()));
+        }
+
+        request_lift(&directory, "hase").unwrap();
+        request_lift(&directory, "igel").unwrap();
+        let mut lifted = throttle.lift_requested(&directory).unwrap();
+        lifted.sort();
+        assert_eq!(
+            lifted,
+            vec![("hase".to_owned(), true), ("igel".to_owned(), false)]
+        );
+        assert_eq!(retry_after(&throttle, &ThrottleKey::login("hase", None)), 0);
+        assert_ne!(
+            retry_after(
+                &throttle,
+                &ThrottleKey::login("igel", "192.0.2.1".parse().ok())
+            ),
+            0
+        );
+
+        request_lift(&directory, "192.0.2.1").unwrap();
+        assert_eq!(throttle.lift_requested(&directory).unwrap().len(), 1);
+        assert!(throttle.lift_requested(&directory).unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    pub async fn checks_one_password_per_user_at_a_time() {
+        let (throttle, _) = throttle();
+        let hase = ThrottleKey::User("hase".to_owned());
+        let checking = throttle.one_at_a_time(&hase).await;
+
+        let waiting = tokio::time::timeout(
+            std::time::Duration::from_millis(50),
+            throttle.one_at_a_time(&hase),
+        );
+        assert!(waiting.await.is_err());
+        let igel = ThrottleKey::User("igel".to_owned());
+        drop(throttle.one_at_a_time(&igel).await);
+
+        drop(checking);
+        drop(throttle.one_at_a_time(&hase).await);
+    }
+}
@@ -0,0 +1,342 @@
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use std::convert::TryFrom;
+use std::fmt;
+use std::iter::Sum;
+use std::ops::{Add, AddAssign, Neg, Sub};
+use std::str::FromStr;
+
+const SCALE: i64 = 1000;
+const DIGITS: usize = 3;
+/// Larger values are rejected when reading, so that sums cannot overflow.
+const LIMIT: i64 = 1_000_000_000_000;
+
+/// A decimal number with three fixed digits after the point, e.g. kilometres
+/// with metre precision. It is kept as an integer number of thousandths, so
+/// sums are exact, and written as a JSON string like `"2.5"`, so that no
+/// client reads it back as a float. Plain JSON numbers are read as well.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Decimal(i64);
+
+/// How numbers are written for users.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Locale {
+    English,
+    German,
+}
+
+impl Locale {
+    pub fn decimal_separator(self) -> char {
+        match self {
+            Locale::English => '.',
+            Locale::German => ',',
+        }
+    }
+
+    pub fn group_separator(self) -> char {
+        match self {
+            Locale::English => ',',
+            Locale::German => '.',
+        }
+    }
+}
+
+/// Divides and rounds half away from zero.
+fn divide(value: i128, divisor: i128) -> i128 {
+    let half = divisor / 2;
+    if value < 0 {
+        (value - half) / divisor
+    } else {
+        (value + half) / divisor
+    }
+}
+
+impl Decimal {
+    pub const ZERO: Decimal = Decimal(0);
+
+    pub const fn from_thousandths(thousandths: i64) -> Self {
+        Decimal(thousandths)
+    }
+
+    pub fn thousandths(self) -> i64 {
+        self.0
+    }
+
+    /// Rounds to the nearest thousandth, or `None` for values that are not
+    /// finite or too large.
+    pub fn from_f64(value: f64) -> Option<Self> {
+        let thousandths = (value * SCALE as f64).round();
+        if thousandths.is_finite() && thousandths.abs() < (LIMIT * SCALE) as f64 {
+            Some(Decimal(thousandths as i64))
+        } else {
+            None
+        }
+    }
+
+    pub fn to_f64(self) -> f64 {
+        self.0 as f64 / SCALE as f64
+    }
+
+    /// Rounds half away from zero to a whole number.
+    pub fn round(self) -> i64 {
+        divide(self.0 as i128, SCALE as i128) as i64
+    }
+
+    /// Multiplies and rounds the product half away from zero to thousandths,
+    /// or `None` when the product does not fit.
+    pub fn checked_mul(self, other: Decimal) -> Option<Decimal> {
+        let product = divide(self.0 as i128 * other.0 as i128, SCALE as i128);
+        i64::try_from(product).ok().map(Decimal)
+    }
+
+    /// Divides by a thousand, or `None` when the result would have to be
+    /// rounded.
+    pub fn exact_thousandth(self) -> Option<Decimal> {
+        if self.0 % SCALE == 0 {
+            Some(Decimal(self.0 / SCALE))
+        } else {
+            None
+        }
+    }
+
+    pub fn is_positive(self) -> bool {
+        self.0 > 0
+    }
+
+    pub fn is_negative(self) -> bool {
+        self.0 < 0
+    }
+
+    /// Writes the number with the given number of decimals (at most three),
+    /// grouping thousands, e.g. `1.234,50` in German.
+    pub fn localized(self, decimals: usize, locale: Locale) -> String {
+        self.write(
+            decimals,
+            locale.decimal_separator(),
+            Some(locale.group_separator()),
+        )
+    }
+
+    fn write(self, decimals: usize, separator: char, group: Option<char>) -> String {
+        let decimals = decimals.min(DIGITS);
+        let divisor = 10_i128.pow((DIGITS - decimals) as u32);
+        let rounded = divide(self.0 as i128, divisor);
+        let scale = 10_i128.pow(decimals as u32);
+        let whole = (rounded.abs() / scale).to_string();
+
+        let mut text = String::new();
+        if rounded < 0 {
+            text.push('-');
+        }
+        for (index, digit) in whole.chars().enumerate() {
+            if let Some(group) =
+                group.filter(|_| index > 0 && (whole.len() - index).is_multiple_of(3))
+            {
+                text.push(group);
+            }
+            text.push(digit);
+        }
+        if decimals > 0 {
+            text.push(separator);
+            text.push_str(&format!(
+                "{:0width$}",
+                rounded.abs() % scale,
+                width = decimals
+            ));
+        }
+        text
+    }
+}
+
+impl From<u32> for Decimal {
+    fn from(value: u32) -> Self {
+        Decimal(value as i64 * SCALE)
+    }
+}
+
+impl Add for Decimal {
+    type Output = Decimal;
+
+    fn add(self, other: Decimal) -> Decimal {
+        Decimal(self.0 + other.0)
+    }
+}
+
+impl AddAssign for Decimal {
+    fn add_assign(&mut self, other: Decimal) {
+        self.0 += other.0;
+    }
+}
+
+impl Sub for Decimal {
+    type Output = Decimal;
+
+    fn sub(self, other: Decimal) -> Decimal {
+        Decimal(self.0 - other.0)
+    }
+}
+
+impl Neg for Decimal {
+    type Output = Decimal;
+
+    fn neg(self) -> Decimal {
+        Decimal(-self.0)
+    }
+}
+
+impl Sum for Decimal {
+    fn sum<I: Iterator<Item = Decimal>>(iter: I) -> Decimal {
+        iter.fold(Decimal::ZERO, Add::add)
+    }
+}
+
+/// Writes all significant decimals, e.g. `2.5`, or exactly as many as the
+/// precision asks for, e.g. `{:.2}` gives `2.50`.
+impl fmt::Display for Decimal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let text = match f.precision() {
+            Some(decimals) => self.write(decimals, '.', None),
+            None => {
+                let text = self.write(DIGITS, '.', None);
+                text.trim_end_matches('0').trim_end_matches('.').to_owned()
+            }
+        };
+        f.pad_integral(true, "", &text)
+    }
+}
+
+/// Reads numbers like `2.5`, `-3` or `0,25`; more than three decimals are
+/// rejected instead of rounded.
+impl FromStr for Decimal {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid number {:?}", input);
+        let (negative, digits) = match input.strip_prefix('-') {
+            Some(digits) => (true, digits),
+            None => (false, input),
+        };
+        let mut parts = digits.splitn(2, ['.', ',']);
+        let whole = parts.next().unwrap_or_default();
+        let fraction = parts.next().unwrap_or_default();
+        let is_digits = |part: &str| part.chars().all(|c| c.is_ascii_digit());
+        if (whole.is_empty() && fraction.is_empty())
+            || !is_digits(whole)
+            || !is_digits(fraction)
+            || fraction.len() > DIGITS
+            || whole.len() > 12
+        {
+            return Err(invalid());
+        }
+        let whole: i64 = if whole.is_empty() {
+            0
+        } else {
+            whole.parse().map_err(|_| invalid())?
+        };
+        let fraction: i64 = format!("{:0<width$}", fraction, width = DIGITS)
+            .parse()
+            .map_err(|_| invalid())?;
+        let value = whole * SCALE + fraction;
+        Ok(Decimal(if negative { -value } else { value }))
+    }
+}
+
+impl Serialize for Decimal {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+struct DecimalVisitor;
+
+impl<'de> de::Visitor<'de> for DecimalVisitor {
+    type Value = Decimal;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a number with at most three decimals")
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<Decimal, E> {
+        value.parse().map_err(E::custom)
+    }
+
+    fn visit_i64<E: de::Error>(self, value: i64) -> Result<Decimal, E> {
+        self.visit_f64(value as f64)
+    }
+
+    fn visit_u64<E: de::Error>(self, value: u64) -> Result<Decimal, E> {
+        self.visit_f64(value as f64)
+    }
+
+    fn visit_f64<E: de::Error>(self, value: f64) -> Result<Decimal, E> {
+        Decimal::from_f64(value).ok_or_else(|| E::custom(format!("{} is out of range", value)))
+    }
+}
+
+/// Reads decimal strings exactly, and any JSON number rounded to thousandths,
+/// which also cleans up values that were stored as `f32`, e.g. `2.3000002`.
+impl<'de> Deserialize<'de> for Decimal {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(DecimalVisitor)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Decimal, Locale};
+
+    fn decimal(input: &str) -> Decimal {
+        input.parse().unwrap()
+    }
+
+    #[test]
+    pub fn sums_without_drift() {
+        let sum: Decimal = std::iter::repeat_n(decimal("0.1"), 1000).sum();
+        assert_eq!(sum, Decimal::from(100));
+        let product = |a: &str, b: &str| decimal(a).checked_mul(decimal(b));
+        assert_eq!(product("2.5", "0.25"), Some(decimal("0.625")));
+        assert_eq!(product("0.005", "0.1"), Some(decimal("0.001")));
+        assert_eq!(product("-0.005", "0.1"), Some(decimal("-0.001")));
+        assert_eq!(product("999999999999", "999999999999"), None);
+        assert_eq!(decimal("2.5").round(), 3);
+        assert_eq!(decimal("150").exact_thousandth(), Some(decimal("0.15")));
+        assert_eq!(decimal("150.5").exact_thousandth(), None);
+        assert_eq!(decimal("0.4").exact_thousandth(), None);
+    }
+
+    #[test]
+    pub fn parses_and_writes_numbers() {
+        assert_eq!(decimal("2,3"), Decimal::from_thousandths(2_300));
+        assert_eq!(decimal(".5"), Decimal::from_thousandths(500));
+        assert_eq!(decimal("-12"), Decimal::from_thousandths(-12_000));
+        assert!("1.2345".parse::<Decimal>().is_err());
+        assert!("1.2.3".parse::<Decimal>().is_err());
+        assert!("".parse::<Decimal>().is_err());
+        assert!("1e3".parse::<Decimal>().is_err());
+
+        assert_eq!(decimal("2.3").to_string(), "2.3");
+        assert_eq!(decimal("2").to_string(), "2");
+        assert_eq!(format!("{:.2}", decimal("2.3")), "2.30");
+        assert_eq!(format!("{:.1}", decimal("-0.05")), "-0.1");
+        assert_eq!(
+            decimal("1234567.891").localized(2, Locale::German),
+            "1.234.567,89"
+        );
+        assert_eq!(decimal("1234.5").localized(0, Locale::English), "1,235");
+    }
+
+    #[test]
+    pub fn reads_strings_and_rounds_json_numbers() {
+        let value: Decimal = serde_json::from_str("2.3000002").unwrap();
+        assert_eq!(value, decimal("2.3"));
+        assert_eq!(serde_json::to_string(&value).unwrap(), r#""2.3""#);
+        assert_eq!(serde_json::to_string(&Decimal::from(5)).unwrap(), r#""5""#);
+        assert_eq!(
+            serde_json::from_str::<Decimal>(r#""-0.125""#).unwrap(),
+            decimal("-0.125")
+        );
+        assert_eq!(serde_json::from_str::<Decimal>("7").unwrap(), decimal("7"));
+        assert!(serde_json::from_str::<Decimal>(r#""0.0001""#).is_err());
+        assert!(serde_json::from_str::<Decimal>("1e20").is_err());
+        assert!(serde_json::from_str::<Decimal>("999999999999.9999").is_err());
+    }
+}
@@ -0,0 +1,52 @@
+use enum_iterator::IntoEnumIterator;
+use serde::{Deserialize, Serialize};
+
+use crate::validation::ValidationError;
+
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone, Copy, IntoEnumIterator)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    Unauthorized,
+    Forbidden,
+    WrongCredentials,
+    UserExists,
+    NotFound,
+    UnknownKind,
+    KindExists,
+    MethodNotAllowed,
+    BadRequest,
+    PayloadTooLarge,
+    UnsupportedMediaType,
+    ValidationFailed,
+    TooManyAttempts,
+    Internal,
+    #[serde(other)]
+    Unknown,
+}
+
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
+pub struct ErrorBody {
+    pub code: ErrorCode,
+    pub message: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<ValidationError>,
+    /// Seconds to wait before trying again, the same as the `Retry-After`
+    /// header, which not every client can read.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retry_after: Option<u64>,
+}
+
+#[cfg(test)]
+mod test {
+    use super::{ErrorBody, ErrorCode};
+
+    #[test]
+    pub fn reads_unknown_codes() {
+        let body: ErrorBody =
+            serde_json::from_str(r#"{"code":"rate_limited","message":"slow down"}"#).unwrap();
+
+        assert_eq!(body.code, ErrorCode::Unknown);
+        assert!(body.errors.is_empty());
+        assert_eq!(body.retry_after, None);
+    }
+}
@@ -1,17 +1,157 @@
 use chrono::prelude::*;
-use enum_iterator::IntoEnumIterator;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::fmt;
 use uuid::Uuid;
 
-#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Default)]
-pub struct Kilometer {
-    pub kilometers: f32,
+pub mod decimal;
+pub mod error;
+pub mod schema;
+pub mod validation;
+
+pub use decimal::{Decimal, Locale};
+
+/// What the entries of a kind are measured in.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Unit {
+    #[default]
+    #[serde(alias = "km")]
+    Distance,
+    Elevation,
+    Duration,
+    Count,
+}
+
+impl Unit {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Unit::Distance => "distance",
+            Unit::Elevation => "elevation",
+            Unit::Duration => "duration",
+            Unit::Count => "count",
+        }
+    }
+
+    /// How many decimals amounts in this unit are shown with.
+    pub fn decimals(self) -> usize {
+        match self {
+            Unit::Distance => 2,
+            Unit::Elevation | Unit::Duration | Unit::Count => 0,
+        }
+    }
+
+    pub fn symbol(self) -> &'static str {
+        match self {
+            Unit::Distance => "km",
+            Unit::Elevation => "Hm",
+            Unit::Duration => "min",
+            Unit::Count => "×",
+        }
+    }
+
+    /// Builds a measurement in this unit, rounding for units that only count
+    /// whole minutes or routes.
+    pub fn measure(self, value: Decimal) -> Measurement {
+        let whole = || value.round().max(0) as u32;
+        match self {
+            Unit::Distance => Measurement::Distance { kilometers: value },
+            Unit::Elevation => Measurement::Elevation { metres: value },
+            Unit::Duration => Measurement::Duration { minutes: whole() },
+            Unit::Count => Measurement::Count { count: whole() },
+        }
+    }
+
+    /// Parses user input, which has to be a whole number for minutes and
+    /// counts.
+    pub fn parse(self, input: &str) -> Option<Measurement> {
+        let input = input.trim();
+        match self {
+            Unit::Distance | Unit::Elevation => input.parse().ok().map(|value| self.measure(value)),
+            Unit::Duration => input
+                .parse()
+                .ok()
+                .map(|minutes| Measurement::Duration { minutes }),
+            Unit::Count => input.parse().ok().map(|count| Measurement::Count { count }),
+        }
+    }
+}
+
+impl std::str::FromStr for Unit {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input {
+            "distance" => Ok(Unit::Distance),
+            "elevation" => Ok(Unit::Elevation),
+            "duration" => Ok(Unit::Duration),
+            "count" => Ok(Unit::Count),
+            _ => Err(format!("unknown unit {}", input)),
+        }
+    }
 }
 
-impl fmt::Display for Kilometer {
+/// The amount of an activity. The name of its field tells the unit, e.g.
+/// `{"kilometers": 2.5}`, `{"metres": 800}`, `{"minutes": 45}` or `{"count": 12}`.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq)]
+#[serde(untagged)]
+pub enum Measurement {
+    Distance { kilometers: Decimal },
+    Elevation { metres: Decimal },
+    Duration { minutes: u32 },
+    Count { count: u32 },
+}
+
+impl Default for Measurement {
+    fn default() -> Self {
+        Measurement::Distance {
+            kilometers: Decimal::ZERO,
+        }
+    }
+}
+
+impl Measurement {
+    pub fn unit(&self) -> Unit {
+        match self {
+            Measurement::Distance { .. } => Unit::Distance,
+            Measurement::Elevation { .. } => Unit::Elevation,
+            Measurement::Duration { .. } => Unit::Duration,
+            Measurement::Count { .. } => Unit::Count,
+        }
+    }
+
+    pub fn value(&self) -> Decimal {
+        match *self {
+            Measurement::Distance { kilometers } => kilometers,
+            Measurement::Elevation { metres } => metres,
+            Measurement::Duration { minutes } => Decimal::from(minutes),
+            Measurement::Count { count } => Decimal::from(count),
+        }
+    }
+
+    /// The distance covered, zero for measurements in other units.
+    pub fn kilometers(&self) -> Decimal {
+        match *self {
+            Measurement::Distance { kilometers } => kilometers,
+            _ => Decimal::ZERO,
+        }
+    }
+
+    /// Writes the amount with the decimals of its unit, e.g. `1.234,50 km`.
+    pub fn localized(&self, locale: Locale) -> String {
+        let unit = self.unit();
+        format!(
+            "{} {}",
+            self.value().localized(unit.decimals(), locale),
+            unit.symbol()
+        )
+    }
+}
+
+impl fmt::Display for Measurement {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{} km", self.kilometers)
+        let unit = self.unit();
+        write!(f, "{:.*} {}", unit.decimals(), self.value(), unit.symbol())
     }
 }
 
@@ -19,87 +159,330 @@ impl fmt::Display for Kilometer {
 pub struct Id {
     pub id: Uuid,
 }
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone, Default)]
+pub struct EntryDetails {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration_minutes: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub elevation_gain: Option<Decimal>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub location: Option<String>,
+}
+
+impl EntryDetails {
+    pub fn or(self, fallback: EntryDetails) -> EntryDetails {
+        EntryDetails {
+            duration_minutes: self.duration_minutes.or(fallback.duration_minutes),
+            elevation_gain: self.elevation_gain.or(fallback.elevation_gain),
+            note: self.note.or(fallback.note),
+            location: self.location.or(fallback.location),
+        }
+    }
+}
+
 #[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
 pub struct KilometerEntry {
     pub id: Id,
-    pub kilometers: Kilometer,
+    pub measurement: Measurement,
     pub kind: Kind,
     pub timestamp: DateTime<Utc>,
+    pub recorded_at: DateTime<Utc>,
+    #[serde(default)]
+    pub points: Decimal,
+    #[serde(flatten)]
+    pub details: EntryDetails,
 }
 
 impl Default for KilometerEntry {
     fn default() -> Self {
         Self {
             id: Id { id: Uuid::nil() },
-            kilometers: Kilometer { kilometers: 0.0 },
-            kind: Kind::Biking,
+            measurement: Measurement::default(),
+            kind: Kind::new("Biking"),
             timestamp: Utc.ymd(1970, 1, 1).and_hms(0, 0, 0),
+            recorded_at: Utc.ymd(1970, 1, 1).and_hms(0, 0, 0),
+            points: Decimal::ZERO,
+            details: EntryDetails::default(),
         }
     }
 }
 
-#[derive(Debug, Deserialize, Serialize, PartialEq, Clone, IntoEnumIterator)]
-pub enum Kind {
-    Running,
-    Biking,
-    Climbing,
-    Skating,
-    Hiking,
-    Swimming,
-    NordicWalking,
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone, Default)]
+pub struct NewKilometerEntry {
+    #[serde(flatten)]
+    pub measurement: Measurement,
+    #[serde(default)]
+    pub timestamp: Option<DateTime<Utc>>,
+    #[serde(flatten)]
+    pub details: EntryDetails,
 }
 
-impl Kind {
-    pub fn get_kind_multiplier(&self) -> f32 {
-        match self {
-            Kind::Running => 1.0,
-            Kind::Biking => 0.25,
-            Kind::Climbing => 100.0,
-            Kind::Skating => 0.75,
-            Kind::Hiking => 2.0,
-            Kind::Swimming => 10.0,
-            Kind::NordicWalking => 1.5,
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone, Default)]
+pub struct KilometerEntryPatch {
+    #[serde(default)]
+    pub measurement: Option<Measurement>,
+    #[serde(default)]
+    pub kind: Option<Kind>,
+    #[serde(default)]
+    pub timestamp: Option<DateTime<Utc>>,
+    #[serde(flatten)]
+    pub details: EntryDetails,
+}
+
+impl KilometerEntryPatch {
+    pub fn apply(self, entry: &mut KilometerEntry) {
+        if let Some(measurement) = self.measurement {
+            entry.measurement = measurement;
+        }
+        if let Some(kind) = self.kind {
+            entry.kind = kind;
+        }
+        if let Some(timestamp) = self.timestamp {
+            entry.timestamp = timestamp;
         }
+        entry.details = self.details.or(entry.details.clone());
     }
+}
 
-    pub fn get_path(&self) -> String {
-        match self {
-            Kind::Running => "laufen",
-            Kind::Biking => "radfahren",
-            Kind::Climbing => "klettern",
-            Kind::Skating => "skaten",
-            Kind::Hiking => "wandern",
-            Kind::Swimming => "schwimmen",
-            Kind::NordicWalking => "nordic_walking",
-        }
-        .to_owned()
+/// Id of an activity kind, e.g. `Running`. What a kind means is looked up in
+/// the [`KindCatalogue`].
+#[derive(Debug, Deserialize, Serialize, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Default)]
+#[serde(transparent)]
+pub struct Kind(pub String);
+
+impl Kind {
+    pub fn new(id: &str) -> Self {
+        Kind(id.to_owned())
     }
 
-    pub fn from_string(input: &str) -> Option<Self> {
-        match input {
-            "laufen" => Some(Kind::Running),
-            "radfahren" => Some(Kind::Biking),
-            "klettern" => Some(Kind::Climbing),
-            "skaten" => Some(Kind::Skating),
-            "schwimmen" => Some(Kind::Swimming),
-            "wandern" => Some(Kind::Hiking),
-            "nordic_walking" => Some(Kind::NordicWalking),
-            _ => None,
-        }
+    pub fn as_str(&self) -> &str {
+        &self.0
     }
 }
 
 impl fmt::Display for Kind {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match *self {
-            Kind::Biking => write!(f, "Radeln"),
-            Kind::Climbing => write!(f, "Klettern"),
-            Kind::Running => write!(f, "Laufen"),
-            Kind::Skating => write!(f, "Skaten"),
-            Kind::Hiking => write!(f, "Wandern"),
-            Kind::Swimming => write!(f, "Schwimmen"),
-            Kind::NordicWalking => write!(f, "Nordic Walking"),
+        write!(f, "{}", self.0)
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
+pub struct KindInfo {
+    pub kind: Kind,
+    pub path: String,
+    pub name: String,
+    pub name_en: String,
+    /// Points per unit.
+    pub multiplier: Decimal,
+    #[serde(alias = "max_kilometers")]
+    pub max_amount: Decimal,
+    pub color: String,
+    #[serde(default)]
+    pub unit: Unit,
+    #[serde(default)]
+    pub retired: bool,
+}
+
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
+pub struct KindCatalogue {
+    pub kinds: Vec<KindInfo>,
+}
+
+impl Default for KindCatalogue {
+    fn default() -> Self {
+        KindCatalogue {
+            kinds: vec![
+                KindInfo {
+                    kind: Kind::new("Running"),
+                    path: "laufen".to_owned(),
+                    name: "Laufen".to_owned(),
+                    name_en: "Running".to_owned(),
+                    multiplier: Decimal::from(1),
+                    max_amount: Decimal::from(100),
+                    color: "#17a2b8".to_owned(),
+                    unit: Unit::Distance,
+                    retired: false,
+                },
+                KindInfo {
+                    kind: Kind::new("Biking"),
+                    path: "radfahren".to_owned(),
+                    name: "Radeln".to_owned(),
+                    name_en: "Cycling".to_owned(),
+                    multiplier: Decimal::from_thousandths(250),
+                    max_amount: Decimal::from(400),
+                    color: "#007bff".to_owned(),
+                    unit: Unit::Distance,
+                    retired: false,
+                },
+                KindInfo {
+                    kind: Kind::new("Climbing"),
+                    path: "klettern".to_owned(),
+                    name: "Klettern".to_owned(),
+                    name_en: "Climbing".to_owned(),
+                    multiplier: Decimal::from_thousandths(100),
+                    max_amount: Decimal::from(5_000),
+                    color: "#28a745".to_owned(),
+                    unit: Unit::Elevation,
+                    retired: false,
+                },
+                KindInfo {
+                    kind: Kind::new("Skating"),
+                    path: "skaten".to_owned(),
+                    name: "Skaten".to_owned(),
+                    name_en: "Skating".to_owned(),
+                    multiplier: Decimal::from_thousandths(750),
+                    max_amount: Decimal::from(150),
+                    color: "#dc3545".to_owned(),
+                    unit: Unit::Distance,
+                    retired: false,
+                },
+                KindInfo {
+                    kind: Kind::new("Hiking"),
+                    path: "wandern".to_owned(),
+                    name: "Wandern".to_owned(),
+                    name_en: "Hiking".to_owned(),
+                    multiplier: Decimal::from(2),
+                    max_amount: Decimal::from(80),
+                    color: "#6c757d".to_owned(),
+                    unit: Unit::Distance,
+                    retired: false,
+                },
+                KindInfo {
+                    kind: Kind::new("Swimming"),
+                    path: "schwimmen".to_owned(),
+                    name: "Schwimmen".to_owned(),
+                    name_en: "Swimming".to_owned(),
+                    multiplier: Decimal::from(10),
+                    max_amount: Decimal::from(20),
+                    color: "#20c997".to_owned(),
+                    unit: Unit::Distance,
+                    retired: false,
+                },
+                KindInfo {
+                    kind: Kind::new("NordicWalking"),
+                    path: "nordic_walking".to_owned(),
+                    name: "Nordic Walking".to_owned(),
+                    name_en: "Nordic walking".to_owned(),
+                    multiplier: Decimal::from_thousandths(1_500),
+                    max_amount: Decimal::from(80),
+                    color: "#6f42c1".to_owned(),
+                    unit: Unit::Distance,
+                    retired: false,
+                },
+                KindInfo {
+                    kind: Kind::new("Yoga"),
+                    path: "yoga".to_owned(),
+                    name: "Yoga".to_owned(),
+                    name_en: "Yoga".to_owned(),
+                    multiplier: Decimal::from_thousandths(100),
+                    max_amount: Decimal::from(300),
+                    color: "#fd7e14".to_owned(),
+                    unit: Unit::Duration,
+                    retired: false,
+                },
+                KindInfo {
+                    kind: Kind::new("Bouldering"),
+                    path: "bouldern".to_owned(),
+                    name: "Bouldern".to_owned(),
+                    name_en: "Bouldering".to_owned(),
+                    multiplier: Decimal::from(2),
+                    max_amount: Decimal::from(200),
+                    color: "#795548".to_owned(),
+                    unit: Unit::Count,
+                    retired: false,
+                },
+            ],
+        }
+    }
+}
+
+impl KindCatalogue {
+    pub fn get(&self, kind: &Kind) -> Option<&KindInfo> {
+        self.kinds.iter().find(|info| info.kind == *kind)
+    }
+
+    pub fn by_path(&self, path: &str) -> Option<&KindInfo> {
+        self.kinds.iter().find(|info| info.path == path)
+    }
+
+    /// The kinds new entries can be created for.
+    pub fn active(&self) -> impl Iterator<Item = &KindInfo> {
+        self.kinds.iter().filter(|info| !info.retired)
+    }
+
+    /// The kind an edited entry may have: its previous kind even when that
+    /// was retired since, or else one new entries can be created for.
+    pub fn for_edit(&self, previous: &Kind, kind: &Kind) -> Option<&KindInfo> {
+        if kind == previous {
+            self.get(kind)
+        } else {
+            self.active().find(|info| info.kind == *kind)
+        }
+    }
+
+    /// The German label of a kind, or its id when the kind is unknown.
+    pub fn name(&self, kind: &Kind) -> String {
+        self.get(kind)
+            .map(|info| info.name.clone())
+            .unwrap_or_else(|| kind.to_string())
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
+pub struct RuleSet {
+    pub version: u32,
+    pub effective_from: NaiveDate,
+    pub multipliers: BTreeMap<Kind, Decimal>,
+}
+
+/// Versioned multipliers. Kinds a version does not mention, and all kinds when
+/// there are no versions, use the multiplier from the [`KindCatalogue`].
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone, Default)]
+pub struct ScoringRules {
+    pub versions: Vec<RuleSet>,
+}
+
+impl ScoringRules {
+    /// Returns the rule set in force on the given day. Days before the first
+    /// rule set are scored with the first one.
+    pub fn in_force(&self, day: NaiveDate) -> Option<&RuleSet> {
+        self.versions
+            .iter()
+            .rev()
+            .find(|rules| rules.effective_from <= day)
+            .or_else(|| self.versions.first())
+    }
+
+    pub fn multiplier(&self, kind: &KindInfo, day: NaiveDate) -> Decimal {
+        self.in_force(day)
+            .and_then(|rules| rules.multipliers.get(&kind.kind))
+            .copied()
+            .unwrap_or(kind.multiplier)
+    }
+
+    /// The amount times the multiplier of the kind. Entries of unknown kinds
+    /// score nothing.
+    pub fn points(&self, entry: &KilometerEntry, catalogue: &KindCatalogue) -> Decimal {
+        catalogue
+            .get(&entry.kind)
+            .map_or(Decimal::ZERO, |kind| self.points_of_kind(entry, kind))
+    }
+
+    /// The amount times the multiplier of the kind. Entries measured in
+    /// another unit than their kind, or with points out of range, score
+    /// nothing.
+    pub fn points_of_kind(&self, entry: &KilometerEntry, kind: &KindInfo) -> Decimal {
+        if kind.unit != entry.measurement.unit() {
+            return Decimal::ZERO;
         }
+        let multiplier = self.multiplier(kind, entry.timestamp.date().naive_utc());
+        entry
+            .measurement
+            .value()
+            .checked_mul(multiplier)
+            .unwrap_or(Decimal::ZERO)
     }
 }
 
@@ -118,11 +501,28 @@ impl Default for UserAuth {
     }
 }
 
-impl UserAuth {
-    pub fn to_basic_auth_header(&self) -> String {
-        let composed = format!("{}:{}", self.name, self.pass);
-        let encoded = base64::encode(composed);
-        format!("Basic {}", encoded)
+/// A login issued by `/authenticate`. Requests send the token instead of the
+/// password until it expires or the user logs out.
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
+pub struct Session {
+    pub name: String,
+    pub token: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+impl Default for Session {
+    fn default() -> Self {
+        Session {
+            name: "".to_string(),
+            token: "".to_string(),
+            expires_at: Utc.timestamp(0, 0),
+        }
+    }
+}
+
+impl Session {
+    pub fn to_bearer_header(&self) -> String {
+        format!("Bearer {}", self.token)
     }
 }
 
@@ -133,47 +533,294 @@ pub struct Highscore {
 #[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
 pub struct HighscoreEntry {
     pub user: String,
-    pub points: f32,
+    pub points: Decimal,
+    #[serde(default)]
+    pub kilometers: Decimal,
+    /// The summed amounts, in the unit of the kind when ranking a single kind.
+    #[serde(default)]
+    pub amount: Decimal,
 }
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone, Copy, Default)]
+pub struct Totals {
+    pub kilometers: Decimal,
+    pub points: Decimal,
+    pub entries: usize,
+}
+
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
+pub struct KindStats {
+    pub kind: Kind,
+    /// The summed amounts in the unit of the kind.
+    pub amount: Decimal,
+    #[serde(flatten)]
+    pub totals: Totals,
+}
+
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
+pub struct WeekStats {
+    pub year: i32,
+    pub week: u32,
+    #[serde(flatten)]
+    pub totals: Totals,
+}
+
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
+pub struct MonthStats {
+    pub year: i32,
+    pub month: u32,
+    #[serde(flatten)]
+    pub totals: Totals,
+}
+
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone, Default)]
+pub struct UserStats {
+    pub total: Totals,
+    pub per_kind: Vec<KindStats>,
+    pub per_week: Vec<WeekStats>,
+    pub per_month: Vec<MonthStats>,
+    pub longest_activity: Option<KilometerEntry>,
+    pub current_streak: u32,
+    pub longest_streak: u32,
+}
+
 #[derive(Debug, Deserialize, Serialize, PartialEq, Clone, Default)]
 pub struct Entries {
     pub list: Vec<KilometerEntry>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone, Copy, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum SortOrder {
+    #[default]
+    Oldest,
+    Newest,
+}
+
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone, Default)]
+pub struct EntriesQuery {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub from: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub to: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kind: Option<String>,
+    #[serde(default)]
+    pub sort: SortOrder,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub limit: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cursor: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone, Copy, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum HighscoreWindow {
+    #[default]
+    All,
+    Week,
+    Month,
+}
+
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone, Default)]
+pub struct HighscoreQuery {
+    #[serde(default)]
+    pub window: HighscoreWindow,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kind: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub from: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub to: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
+pub struct TrashedEntry {
+    pub entry: KilometerEntry,
+    pub deleted_at: DateTime<Utc>,
+}
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone, Default)]
+pub struct Trash {
+    pub list: Vec<TrashedEntry>,
 }
 
 #[cfg(test)]
 mod test {
-    use super::Kind;
+    use chrono::prelude::*;
+
+    use super::{
+        Decimal, EntryDetails, KilometerEntry, KilometerEntryPatch, Kind, KindCatalogue, Locale,
+        Measurement, NewKilometerEntry, RuleSet, ScoringRules, Unit,
+    };
+
+    fn decimal(input: &str) -> Decimal {
+        input.parse().unwrap()
+    }
+
+    fn kilometers(input: &str) -> Measurement {
+        Measurement::Distance {
+            kilometers: decimal(input),
+        }
+    }
 
     #[test]
     pub fn does_conversion() {
+        let catalogue = KindCatalogue::default();
+        for info in &catalogue.kinds {
+            assert_eq!(catalogue.by_path(&info.path), Some(info));
+            assert_eq!(catalogue.get(&info.kind), Some(info));
+        }
         assert_eq!(
-            Some(Kind::Climbing),
-            Kind::from_string(&Kind::Climbing.get_path()),
+            catalogue.by_path("klettern").map(|info| &info.kind),
+            Some(&Kind::new("Climbing"))
         );
+    }
+
+    #[test]
+    pub fn rejects_unknown() {
+        let catalogue = KindCatalogue::default();
+        assert_eq!(None, catalogue.by_path("wurst"));
+        assert_eq!(catalogue.name(&Kind::new("Wurst")), "Wurst");
+    }
+
+    #[test]
+    pub fn keeps_but_does_not_move_into_retired_kinds() {
+        let mut catalogue = KindCatalogue::default();
+        let skating = Kind::new("Skating");
+        let running = Kind::new("Running");
+        for info in &mut catalogue.kinds {
+            info.retired = info.kind == skating;
+        }
+
+        assert!(catalogue.for_edit(&skating, &skating).is_some());
+        assert!(catalogue.for_edit(&running, &skating).is_none());
+        assert!(catalogue.for_edit(&skating, &running).is_some());
+    }
+
+    #[test]
+    pub fn patches_only_given_fields() {
+        let mut entry = KilometerEntry::default();
+        let patch: KilometerEntryPatch =
+            serde_json::from_str(r#"{ "measurement": { "kilometers": 3.5 } }"#).unwrap();
+
+        patch.apply(&mut entry);
+
+        assert_eq!(entry.measurement, kilometers("3.5"));
+        assert_eq!(entry.kind, Kind::new("Biking"));
+    }
+
+    #[test]
+    pub fn reads_entries_without_details() {
+        let json = r#"{
+            "id": { "id": "2f1c1b7e-4e7b-4a43-9d4e-2d3c3f1e0a01" },
+            "measurement": { "kilometers": 2.5 },
+            "kind": "Hiking",
+            "timestamp": "2021-05-01T10:00:00Z",
+            "recorded_at": "2021-05-01T10:00:00Z"
+        }"#;
+
+        let entry: KilometerEntry = serde_json::from_str(json).unwrap();
+
+        assert_eq!(entry.details, EntryDetails::default());
+        assert!(!serde_json::to_string(&entry).unwrap().contains("note"));
+    }
+
+    #[test]
+    pub fn scores_with_the_rules_in_force() {
+        let catalogue = KindCatalogue::default();
+        let running = Kind::new("Running");
+        let mut rules = ScoringRules::default();
+        let run = |day| KilometerEntry {
+            kind: running.clone(),
+            measurement: kilometers("10"),
+            timestamp: Utc.ymd(2021, 5, day).and_hms(23, 0, 0),
+            ..Default::default()
+        };
+        assert_eq!(rules.points(&run(31), &catalogue), decimal("10"));
+
+        rules.versions.push(RuleSet {
+            version: 1,
+            effective_from: NaiveDate::from_ymd(2021, 6, 1),
+            multipliers: vec![(running.clone(), decimal("2"))].into_iter().collect(),
+        });
+        assert_eq!(rules.points(&run(31), &catalogue), decimal("20"));
+        rules.versions.push(RuleSet {
+            version: 2,
+            effective_from: NaiveDate::from_ymd(2021, 6, 2),
+            multipliers: Default::default(),
+        });
+        let june = |day| KilometerEntry {
+            timestamp: Utc.ymd(2021, 6, day).and_hms(0, 0, 0),
+            ..run(1)
+        };
+        assert_eq!(rules.points(&june(1), &catalogue), decimal("20"));
+        assert_eq!(rules.points(&june(2), &catalogue), decimal("10"));
         assert_eq!(
-            Some(Kind::Hiking),
-            Kind::from_string(&Kind::Hiking.get_path()),
+            rules.points(
+                &KilometerEntry {
+                    kind: Kind::new("Wurst"),
+                    ..june(2)
+                },
+                &catalogue
+            ),
+            Decimal::ZERO
         );
+    }
+
+    #[test]
+    pub fn reads_measurements_by_their_field() {
+        let entry: NewKilometerEntry =
+            serde_json::from_str(r#"{ "minutes": 45, "duration_minutes": 50 }"#).unwrap();
+        assert_eq!(entry.measurement, Measurement::Duration { minutes: 45 });
+        assert_eq!(entry.details.duration_minutes, Some(50));
+
+        let entry: NewKilometerEntry = serde_json::from_str(r#"{ "kilometers": 2 }"#).unwrap();
+        assert_eq!(entry.measurement, kilometers("2"));
+        assert!(serde_json::from_str::<NewKilometerEntry>(r#"{ "count": 1.5 }"#).is_err());
+
         assert_eq!(
-            Some(Kind::Running),
-            Kind::from_string(&Kind::Running.get_path()),
+            Unit::Count.parse(" 12 "),
+            Some(Measurement::Count { count: 12 })
         );
+        assert_eq!(Unit::Duration.parse("4.5"), None);
+        assert_eq!(Unit::Distance.parse("2,3"), Some(kilometers("2.3")));
         assert_eq!(
-            Some(Kind::Swimming),
-            Kind::from_string(&Kind::Swimming.get_path()),
+            Unit::Elevation.measure(decimal("800")).to_string(),
+            "800 Hm".to_owned()
         );
+    }
+
+    #[test]
+    pub fn writes_measurements_with_fixed_decimals() {
+        assert_eq!(kilometers("2.3").to_string(), "2.30 km");
         assert_eq!(
-            Some(Kind::Biking),
-            Kind::from_string(&Kind::Biking.get_path()),
+            kilometers("1234.5").localized(Locale::German),
+            "1.234,50 km"
         );
         assert_eq!(
-            Some(Kind::Skating),
-            Kind::from_string(&Kind::Skating.get_path()),
+            Measurement::Duration { minutes: 45 }.localized(Locale::English),
+            "45 min"
         );
     }
 
     #[test]
-    pub fn rejects_unknown() {
-        assert_eq!(None, Kind::from_string("wurst"),);
+    pub fn scores_per_unit() {
+        let catalogue = KindCatalogue::default();
+        let rules = ScoringRules::default();
+        let yoga = |measurement| KilometerEntry {
+            kind: Kind::new("Yoga"),
+            measurement,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            rules.points(&yoga(Measurement::Duration { minutes: 60 }), &catalogue),
+            decimal("6")
+        );
+        assert_eq!(
+            rules.points(&yoga(kilometers("60")), &catalogue),
+            Decimal::ZERO
+        );
     }
 }
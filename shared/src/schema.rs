@@ -0,0 +1,730 @@
+use enum_iterator::IntoEnumIterator;
+use serde_json::{json, Map, Value};
+
+use crate::error::{ErrorBody, ErrorCode};
+use crate::validation::{
+    Field, Problem, ValidationError, MAX_DURATION_MINUTES, MAX_ELEVATION_GAIN, MAX_LOCATION_LENGTH,
+    MAX_MULTIPLIER, MAX_NOTE_LENGTH,
+};
+use crate::{
+    Entries, EntryDetails, Highscore, HighscoreEntry, Id, KilometerEntry, KilometerEntryPatch,
+    Kind, KindInfo, KindStats, Measurement, MonthStats, NewKilometerEntry, RuleSet, ScoringRules,
+    Session, Totals, Trash, TrashedEntry, Unit, UserAuth, UserStats, WeekStats,
+};
+
+pub trait Schema {
+    const NAME: &'static str;
+
+    fn schema() -> Value;
+}
+
+pub fn reference<T: Schema>() -> Value {
+    json!({ "$ref": format!("#/components/schemas/{}", T::NAME) })
+}
+
+pub fn components() -> Map<String, Value> {
+    fn add<T: Schema>(schemas: &mut Map<String, Value>) {
+        schemas.insert(T::NAME.to_owned(), T::schema());
+    }
+
+    let mut schemas = Map::new();
+    add::<Id>(&mut schemas);
+    add::<Unit>(&mut schemas);
+    add::<Measurement>(&mut schemas);
+    add::<Kind>(&mut schemas);
+    add::<KindInfo>(&mut schemas);
+    add::<EntryDetails>(&mut schemas);
+    add::<KilometerEntry>(&mut schemas);
+    add::<NewKilometerEntry>(&mut schemas);
+    add::<KilometerEntryPatch>(&mut schemas);
+    add::<Entries>(&mut schemas);
+    add::<TrashedEntry>(&mut schemas);
+    add::<Trash>(&mut schemas);
+    add::<RuleSet>(&mut schemas);
+    add::<ScoringRules>(&mut schemas);
+    add::<HighscoreEntry>(&mut schemas);
+    add::<Highscore>(&mut schemas);
+    add::<Totals>(&mut schemas);
+    add::<KindStats>(&mut schemas);
+    add::<WeekStats>(&mut schemas);
+    add::<MonthStats>(&mut schemas);
+    add::<UserStats>(&mut schemas);
+    add::<UserAuth>(&mut schemas);
+    add::<Session>(&mut schemas);
+    add::<Field>(&mut schemas);
+    add::<Problem>(&mut schemas);
+    add::<ValidationError>(&mut schemas);
+    add::<ErrorCode>(&mut schemas);
+    add::<ErrorBody>(&mut schemas);
+    schemas
+}
+
+fn object(properties: Value, required: &[&str]) -> Value {
+    json!({
+        "type": "object",
+        "properties": properties,
+        "required": required,
+    })
+}
+
+fn with_details(mut schema: Value) -> Value {
+    if let (Some(properties), Value::Object(details)) = (
+        schema["properties"].as_object_mut(),
+        EntryDetails::schema()["properties"].take(),
+    ) {
+        properties.extend(details);
+    }
+    schema
+}
+
+fn string_enum<T: serde::Serialize>(values: impl Iterator<Item = T>) -> Value {
+    let values = values
+        .map(|value| serde_json::to_value(value).unwrap_or_default())
+        .collect::<Vec<_>>();
+    json!({ "type": "string", "enum": values })
+}
+
+fn nullable(schema: Value) -> Value {
+    json!({ "nullable": true, "allOf": [schema] })
+}
+
+/// A [`crate::Decimal`], i.e. a number with at most three decimals. It is
+/// written as a string, but requests may give a plain number as well.
+fn decimal() -> Value {
+    json!({
+        "type": "string",
+        "pattern": "^-?[0-9]{1,12}(\\.[0-9]{1,3})?$",
+        "example": "2.5",
+        "description": "A decimal number with at most three decimals. \
+            Requests may also give it as a JSON number.",
+    })
+}
+
+fn date_time() -> Value {
+    json!({ "type": "string", "format": "date-time" })
+}
+
+fn array<T: Schema>() -> Value {
+    json!({ "type": "array", "items": reference::<T>() })
+}
+
+impl Schema for Id {
+    const NAME: &'static str = "Id";
+
+    fn schema() -> Value {
+        object(
+            json!({ "id": { "type": "string", "format": "uuid" } }),
+            &["id"],
+        )
+    }
+}
+
+fn measurement_fields() -> Value {
+    json!({
+        "kilometers": decimal(),
+        "metres": decimal(),
+        "minutes": { "type": "integer", "minimum": 0 },
+        "count": { "type": "integer", "minimum": 0 },
+    })
+}
+
+impl Schema for Unit {
+    const NAME: &'static str = "Unit";
+
+    fn schema() -> Value {
+        string_enum([Unit::Distance, Unit::Elevation, Unit::Duration, Unit::Count].iter())
+    }
+}
+
+impl Schema for Measurement {
+    const NAME: &'static str = "Measurement";
+
+    fn schema() -> Value {
+        let variants = measurement_fields()
+            .as_object()
+            .into_iter()
+            .flatten()
+            .map(|(name, field)| object(json!({ name: field }), &[name]))
+            .collect::<Vec<_>>();
+        json!({ "oneOf": variants })
+    }
+}
+
+impl Schema for Kind {
+    const NAME: &'static str = "Kind";
+
+    fn schema() -> Value {
+        json!({ "type": "string", "description": "Id of a kind in the catalogue" })
+    }
+}
+
+impl Schema for KindInfo {
+    const NAME: &'static str = "KindInfo";
+
+    fn schema() -> Value {
+        object(
+            json!({
+                "kind": reference::<Kind>(),
+                "path": { "type": "string" },
+                "name": { "type": "string" },
+                "name_en": { "type": "string" },
+                "multiplier": {
+                    "allOf": [decimal()],
+                    "description": format!("From 0 to {}.", MAX_MULTIPLIER),
+                },
+                "max_amount": { "allOf": [decimal()], "description": "Greater than 0." },
+                "color": { "type": "string", "pattern": "^#[0-9a-fA-F]{6}$" },
+                "unit": reference::<Unit>(),
+                "retired": { "type": "boolean" },
+            }),
+            &[
+                "kind",
+                "path",
+                "name",
+                "name_en",
+                "multiplier",
+                "max_amount",
+                "color",
+                "unit",
+            ],
+        )
+    }
+}
+
+impl Schema for RuleSet {
+    const NAME: &'static str = "RuleSet";
+
+    fn schema() -> Value {
+        object(
+            json!({
+                "version": { "type": "integer", "minimum": 1 },
+                "effective_from": { "type": "string", "format": "date" },
+                "multipliers": {
+                    "type": "object",
+                    "properties": {},
+                    "required": [],
+                    "additionalProperties": decimal(),
+                },
+            }),
+            &["version", "effective_from", "multipliers"],
+        )
+    }
+}
+
+impl Schema for ScoringRules {
+    const NAME: &'static str = "ScoringRules";
+
+    fn schema() -> Value {
+        object(json!({ "versions": array::<RuleSet>() }), &["versions"])
+    }
+}
+
+impl Schema for EntryDetails {
+    const NAME: &'static str = "EntryDetails";
+
+    fn schema() -> Value {
+        object(
+            json!({
+                "duration_minutes": {
+                    "type": "integer",
+                    "minimum": 0,
+                    "maximum": MAX_DURATION_MINUTES,
+                },
+                "elevation_gain": {
+                    "allOf": [decimal()],
+                    "description": format!("Metres, from 0 to {}.", MAX_ELEVATION_GAIN),
+                },
+                "note": { "type": "string", "maxLength": MAX_NOTE_LENGTH },
+                "location": { "type": "string", "maxLength": MAX_LOCATION_LENGTH },
+            }),
+            &[],
+        )
+    }
+}
+
+impl Schema for KilometerEntry {
+    const NAME: &'static str = "KilometerEntry";
+
+    fn schema() -> Value {
+        with_details(object(
+            json!({
+                "id": reference::<Id>(),
+                "measurement": reference::<Measurement>(),
+                "kind": reference::<Kind>(),
+                "timestamp": date_time(),
+                "recorded_at": date_time(),
+                "points": { "allOf": [decimal()], "readOnly": true },
+            }),
+            &[
+                "id",
+                "measurement",
+                "kind",
+                "timestamp",
+                "recorded_at",
+                "points",
+            ],
+        ))
+    }
+}
+
+impl Schema for NewKilometerEntry {
+    const NAME: &'static str = "NewKilometerEntry";
+
+    fn schema() -> Value {
+        let mut properties = measurement_fields();
+        properties["timestamp"] = nullable(date_time());
+        let mut schema = with_details(object(properties, &[]));
+        schema["description"] = json!("Needs exactly one of kilometers, metres, minutes or count");
+        schema
+    }
+}
+
+impl Schema for KilometerEntryPatch {
+    const NAME: &'static str = "KilometerEntryPatch";
+
+    fn schema() -> Value {
+        with_details(object(
+            json!({
+                "measurement": nullable(reference::<Measurement>()),
+                "kind": nullable(reference::<Kind>()),
+                "timestamp": nullable(date_time()),
+            }),
+            &[],
+        ))
+    }
+}
+
+impl Schema for Entries {
+    const NAME: &'static str = "Entries";
+
+    fn schema() -> Value {
+        object(
+            json!({
+                "list": array::<KilometerEntry>(),
+                "next_cursor": { "type": "string" },
+            }),
+            &["list"],
+        )
+    }
+}
+
+impl Schema for TrashedEntry {
+    const NAME: &'static str = "TrashedEntry";
+
+    fn schema() -> Value {
+        object(
+            json!({
+                "entry": reference::<KilometerEntry>(),
+                "deleted_at": date_time(),
+            }),
+            &["entry", "deleted_at"],
+        )
+    }
+}
+
+impl Schema for Trash {
+    const NAME: &'static str = "Trash";
+
+    fn schema() -> Value {
+        object(json!({ "list": array::<TrashedEntry>() }), &["list"])
+    }
+}
+
+impl Schema for HighscoreEntry {
+    const NAME: &'static str = "HighscoreEntry";
+
+    fn schema() -> Value {
+        object(
+            json!({
+                "user": { "type": "string" },
+                "points": decimal(),
+                "kilometers": decimal(),
+                "amount": decimal(),
+            }),
+            &["user", "points", "kilometers", "amount"],
+        )
+    }
+}
+
+impl Schema for Highscore {
+    const NAME: &'static str = "Highscore";
+
+    fn schema() -> Value {
+        object(json!({ "list": array::<HighscoreEntry>() }), &["list"])
+    }
+}
+
+fn with_totals(mut schema: Value) -> Value {
+    if let (Some(properties), Value::Object(totals)) = (
+        schema["properties"].as_object_mut(),
+        Totals::schema()["properties"].take(),
+    ) {
+        properties.extend(totals);
+    }
+    if let Some(required) = schema["required"].as_array_mut() {
+        required.extend(
+            ["kilometers", "points", "entries"]
+                .iter()
+                .map(|name| json!(name)),
+        );
+    }
+    schema
+}
+
+impl Schema for Totals {
+    const NAME: &'static str = "Totals";
+
+    fn schema() -> Value {
+        object(
+            json!({
+                "kilometers": decimal(),
+                "points": decimal(),
+                "entries": { "type": "integer", "minimum": 0 },
+            }),
+            &["kilometers", "points", "entries"],
+        )
+    }
+}
+
+impl Schema for KindStats {
+    const NAME: &'static str = "KindStats";
+
+    fn schema() -> Value {
+        with_totals(object(
+            json!({
+                "kind": reference::<Kind>(),
+                "amount": decimal(),
+            }),
+            &["kind", "amount"],
+        ))
+    }
+}
+
+impl Schema for WeekStats {
+    const NAME: &'static str = "WeekStats";
+
+    fn schema() -> Value {
+        with_totals(object(
+            json!({
+                "year": { "type": "integer" },
+                "week": { "type": "integer", "minimum": 1, "maximum": 53 },
+            }),
+            &["year", "week"],
+        ))
+    }
+}
+
+impl Schema for MonthStats {
+    const NAME: &'static str = "MonthStats";
+
+    fn schema() -> Value {
+        with_totals(object(
+            json!({
+                "year": { "type": "integer" },
+                "month": { "type": "integer", "minimum": 1, "maximum": 12 },
+            }),
+            &["year", "month"],
+        ))
+    }
+}
+
+impl Schema for UserStats {
+    const NAME: &'static str = "UserStats";
+
+    fn schema() -> Value {
+        object(
+            json!({
+                "total": reference::<Totals>(),
+                "per_kind": array::<KindStats>(),
+                "per_week": array::<WeekStats>(),
+                "per_month": array::<MonthStats>(),
+                "longest_activity": nullable(reference::<KilometerEntry>()),
+                "current_streak": { "type": "integer", "minimum": 0 },
+                "longest_streak": { "type": "integer", "minimum": 0 },
+            }),
+            &[
+                "total",
+                "per_kind",
+                "per_week",
+                "per_month",
+                "longest_activity",
+                "current_streak",
+                "longest_streak",
+            ],
+        )
+    }
+}
+
+impl Schema for UserAuth {
+    const NAME: &'static str = "UserAuth";
+
+    fn schema() -> Value {
+        object(
+            json!({
+                "name": { "type": "string" },
+                "pass": { "type": "string", "format": "password" },
+            }),
+            &["name", "pass"],
+        )
+    }
+}
+
+impl Schema for Session {
+    const NAME: &'static str = "Session";
+
+    fn schema() -> Value {
+        object(
+            json!({
+                "name": { "type": "string" },
+                "token": { "type": "string" },
+                "expires_at": date_time(),
+            }),
+            &["name", "token", "expires_at"],
+        )
+    }
+}
+
+impl Schema for Field {
+    const NAME: &'static str = "Field";
+
+    fn schema() -> Value {
+        string_enum(
+            [
+                Field::Measurement,
+                Field::Timestamp,
+                Field::DurationMinutes,
+                Field::ElevationGain,
+                Field::Note,
+                Field::Location,
+            ]
+            .iter(),
+        )
+    }
+}
+
+impl Schema for Problem {
+    const NAME: &'static str = "Problem";
+
+    fn schema() -> Value {
+        let bounded = |name: &str, max: Value| {
+            let max = object(json!({ "max": max }), &["max"]);
+            object(json!({ name: max }), &[name])
+        };
+        json!({
+            "oneOf": [
+                string_enum(
+                    [
+                        Problem::NotANumber,
+                        Problem::NotPositive,
+                        Problem::Negative,
+                        Problem::InFuture,
+                        Problem::BeforeChallenge,
+                        Problem::AfterChallenge,
+                    ]
+                    .iter(),
+                ),
+                bounded("TooLarge", decimal()),
+                bounded("TooLong", json!({ "type": "integer", "format": "int64" })),
+                object(
+                    json!({
+                        "WrongUnit": object(
+                            json!({ "expected": reference::<Unit>() }),
+                            &["expected"],
+                        ),
+                    }),
+                    &["WrongUnit"],
+                ),
+            ]
+        })
+    }
+}
+
+impl Schema for ValidationError {
+    const NAME: &'static str = "ValidationError";
+
+    fn schema() -> Value {
+        object(
+            json!({
+                "field": reference::<Field>(),
+                "problem": reference::<Problem>(),
+            }),
+            &["field", "problem"],
+        )
+    }
+}
+
+impl Schema for ErrorCode {
+    const NAME: &'static str = "ErrorCode";
+
+    fn schema() -> Value {
+        string_enum(ErrorCode::into_enum_iter().filter(|code| *code != ErrorCode::Unknown))
+    }
+}
+
+impl Schema for ErrorBody {
+    const NAME: &'static str = "ErrorBody";
+
+    fn schema() -> Value {
+        object(
+            json!({
+                "code": reference::<ErrorCode>(),
+                "message": { "type": "string" },
+                "errors": array::<ValidationError>(),
+                "retry_after": { "type": "integer", "minimum": 0 },
+            }),
+            &["code", "message"],
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::prelude::*;
+    use serde_json::{Map, Value};
+
+    use super::{components, Schema};
+    use crate::error::{ErrorBody, ErrorCode};
+    use crate::validation::{Field, Problem, ValidationError};
+    use crate::{
+        Decimal, EntryDetails, KilometerEntry, KilometerEntryPatch, Kind, KindCatalogue,
+        Measurement, NewKilometerEntry, RuleSet, ScoringRules, Session, Trash, TrashedEntry, Unit,
+    };
+
+    fn conforms(schema: &Value, value: &Value, schemas: &Map<String, Value>) -> bool {
+        if let Some(reference) = schema["$ref"].as_str() {
+            let name = reference.trim_start_matches("#/components/schemas/");
+            return conforms(&schemas[name], value, schemas);
+        }
+        if let Some(variants) = schema["oneOf"].as_array() {
+            return variants
+                .iter()
+                .any(|variant| conforms(variant, value, schemas));
+        }
+        if value.is_null() {
+            return schema["nullable"] == Value::Bool(true);
+        }
+        if let Some(parts) = schema["allOf"].as_array() {
+            return parts.iter().all(|part| conforms(part, value, schemas));
+        }
+        if let Some(values) = schema["enum"].as_array() {
+            return values.contains(value);
+        }
+        match (schema["type"].as_str(), value) {
+            (Some("object"), Value::Object(fields)) => {
+                let properties = schema["properties"].as_object().unwrap();
+                let required = schema["required"].as_array().unwrap();
+                required
+                    .iter()
+                    .all(|name| fields.contains_key(name.as_str().unwrap()))
+                    && fields.iter().all(|(name, field)| {
+                        properties
+                            .get(name)
+                            .or_else(|| schema.get("additionalProperties"))
+                            .is_some_and(|property| conforms(property, field, schemas))
+                    })
+            }
+            (Some("array"), Value::Array(items)) => items
+                .iter()
+                .all(|item| conforms(&schema["items"], item, schemas)),
+            (Some("string"), Value::String(_)) => true,
+            (Some("number"), Value::Number(_)) => true,
+            (Some("boolean"), Value::Bool(_)) => true,
+            (Some("integer"), Value::Number(number)) => number.is_u64() || number.is_i64(),
+            _ => false,
+        }
+    }
+
+    fn assert_conforms<T: Schema + serde::Serialize>(value: &T) {
+        let schemas = components();
+        let value = serde_json::to_value(value).unwrap();
+        assert!(
+            conforms(&schemas[T::NAME], &value, &schemas),
+            "{} does not match its schema: {}",
+            T::NAME,
+            value
+        );
+    }
+
+    #[test]
+    pub fn schemas_match_serialized_types() {
+        let details = EntryDetails {
+            duration_minutes: Some(30),
+            elevation_gain: Some(Decimal::from(120)),
+            note: Some("note".to_owned()),
+            location: Some("Berlin".to_owned()),
+        };
+        let entry = KilometerEntry {
+            kind: Kind::new("Swimming"),
+            details: details.clone(),
+            ..Default::default()
+        };
+
+        assert_conforms(&entry);
+        assert_conforms(&NewKilometerEntry::default());
+        assert_conforms(&NewKilometerEntry {
+            timestamp: Some(Utc::now()),
+            details: details.clone(),
+            ..Default::default()
+        });
+        assert_conforms(&NewKilometerEntry {
+            measurement: Measurement::Count { count: 12 },
+            ..Default::default()
+        });
+        assert_conforms(&KilometerEntryPatch {
+            measurement: Some(Measurement::Duration { minutes: 45 }),
+            kind: Some(Kind::new("Hiking")),
+            details,
+            ..Default::default()
+        });
+        assert_conforms(&Trash {
+            list: vec![TrashedEntry {
+                entry,
+                deleted_at: Utc::now(),
+            }],
+        });
+        assert_conforms(&Session {
+            name: "anna".to_owned(),
+            token: "token".to_owned(),
+            expires_at: Utc::now(),
+        });
+        for kind in KindCatalogue::default().kinds {
+            assert_conforms(&kind);
+        }
+        assert_conforms(&ScoringRules {
+            versions: vec![RuleSet {
+                version: 1,
+                effective_from: NaiveDate::from_ymd(2021, 5, 1),
+                multipliers: vec![(Kind::new("Running"), Decimal::from(2))]
+                    .into_iter()
+                    .collect(),
+            }],
+        });
+        assert_conforms(&ErrorBody {
+            code: ErrorCode::ValidationFailed,
+            message: "invalid".to_owned(),
+            errors: vec![
+                ValidationError {
+                    field: Field::Note,
+                    problem: Problem::TooLong { max: 500 },
+                },
+                ValidationError {
+                    field: Field::Timestamp,
+                    problem: Problem::InFuture,
+                },
+                ValidationError {
+                    field: Field::Measurement,
+                    problem: Problem::WrongUnit {
+                        expected: Unit::Count,
+                    },
+                },
+            ],
+            retry_after: None,
+        });
+        assert_conforms(&ErrorBody {
+            code: ErrorCode::TooManyAttempts,
+            message: "too many failed attempts".to_owned(),
+            errors: Vec::new(),
+            retry_after: Some(8),
+        });
+    }
+}
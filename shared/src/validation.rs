@@ -0,0 +1,234 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use crate::{Decimal, EntryDetails, KindInfo, Measurement, Unit};
+
+pub const MAX_DURATION_MINUTES: u32 = 48 * 60;
+pub const MAX_ELEVATION_GAIN: Decimal = Decimal::from_thousandths(10_000_000);
+/// Keeps the points of the largest amount of a kind within range.
+pub const MAX_MULTIPLIER: Decimal = Decimal::from_thousandths(1_000_000);
+pub const MAX_NOTE_LENGTH: usize = 500;
+pub const MAX_LOCATION_LENGTH: usize = 100;
+
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone, Copy)]
+pub enum Field {
+    Measurement,
+    Timestamp,
+    DurationMinutes,
+    ElevationGain,
+    Note,
+    Location,
+}
+
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone, Copy)]
+pub enum Problem {
+    NotANumber,
+    NotPositive,
+    Negative,
+    TooLarge { max: Decimal },
+    TooLong { max: usize },
+    InFuture,
+    BeforeChallenge,
+    AfterChallenge,
+    WrongUnit { expected: Unit },
+}
+
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone, Copy)]
+pub struct ValidationError {
+    pub field: Field,
+    pub problem: Problem,
+}
+
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone, Default)]
+pub struct ValidationErrors {
+    pub errors: Vec<ValidationError>,
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}: ", self.field)?;
+        match self.problem {
+            Problem::NotANumber => write!(f, "not a number"),
+            Problem::NotPositive => write!(f, "must be greater than zero"),
+            Problem::Negative => write!(f, "must not be negative"),
+            Problem::TooLarge { max } => write!(f, "must not be larger than {}", max),
+            Problem::TooLong { max } => write!(f, "must not be longer than {} characters", max),
+            Problem::InFuture => write!(f, "is in the future"),
+            Problem::BeforeChallenge => write!(f, "is before the challenge started"),
+            Problem::AfterChallenge => write!(f, "is after the challenge ended"),
+            Problem::WrongUnit { expected } => write!(f, "must be given as {}", expected.as_str()),
+        }
+    }
+}
+
+impl fmt::Display for ValidationErrors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let errors = self
+            .errors
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>();
+        write!(f, "{}", errors.join(", "))
+    }
+}
+
+impl ValidationErrors {
+    pub fn for_field(&self, field: Field) -> Option<&ValidationError> {
+        self.errors.iter().find(|error| error.field == field)
+    }
+}
+
+fn check_number(field: Field, value: Decimal, max: Decimal) -> Option<ValidationError> {
+    let problem = if value.is_negative() {
+        Problem::Negative
+    } else if value > max {
+        Problem::TooLarge { max }
+    } else {
+        return None;
+    };
+    Some(ValidationError { field, problem })
+}
+
+fn check_length(field: Field, value: &Option<String>, max: usize) -> Option<ValidationError> {
+    match value {
+        Some(value) if value.chars().count() > max => Some(ValidationError {
+            field,
+            problem: Problem::TooLong { max },
+        }),
+        _ => None,
+    }
+}
+
+pub fn validate_measurement(kind: &KindInfo, measurement: &Measurement) -> Option<ValidationError> {
+    let problem = if measurement.unit() != kind.unit {
+        Problem::WrongUnit {
+            expected: kind.unit,
+        }
+    } else if measurement.value() == Decimal::ZERO {
+        Problem::NotPositive
+    } else {
+        return check_number(Field::Measurement, measurement.value(), kind.max_amount);
+    };
+    Some(ValidationError {
+        field: Field::Measurement,
+        problem,
+    })
+}
+
+pub fn validate_details(details: &EntryDetails) -> Vec<ValidationError> {
+    vec![
+        details.duration_minutes.and_then(|duration| {
+            check_number(
+                Field::DurationMinutes,
+                Decimal::from(duration),
+                Decimal::from(MAX_DURATION_MINUTES),
+            )
+        }),
+        details.elevation_gain.and_then(|elevation| {
+            check_number(Field::ElevationGain, elevation, MAX_ELEVATION_GAIN)
+        }),
+        check_length(Field::Note, &details.note, MAX_NOTE_LENGTH),
+        check_length(Field::Location, &details.location, MAX_LOCATION_LENGTH),
+    ]
+    .into_iter()
+    .flatten()
+    .collect()
+}
+
+pub fn validate_entry(
+    kind: &KindInfo,
+    measurement: &Measurement,
+    details: &EntryDetails,
+) -> Result<(), ValidationErrors> {
+    let mut errors = validate_details(details);
+    errors.extend(validate_measurement(kind, measurement));
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(ValidationErrors { errors })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{validate_entry, validate_measurement, Field, Problem, ValidationError};
+    use crate::{Decimal, EntryDetails, Kind, KindCatalogue, KindInfo, Measurement, Unit};
+
+    fn kind(id: &str) -> KindInfo {
+        KindCatalogue::default()
+            .get(&Kind::new(id))
+            .unwrap()
+            .clone()
+    }
+
+    fn kilometers(input: &str) -> Measurement {
+        Measurement::Distance {
+            kilometers: input.parse().unwrap(),
+        }
+    }
+
+    fn problem(id: &str, amount: &str) -> Option<Problem> {
+        validate_measurement(&kind(id), &kilometers(amount)).map(|error| error.problem)
+    }
+
+    #[test]
+    pub fn checks_distance_bounds_per_kind() {
+        assert_eq!(problem("Running", "10"), None);
+        assert_eq!(problem("Running", "0"), Some(Problem::NotPositive));
+        assert_eq!(problem("Running", "-1"), Some(Problem::Negative));
+        assert_eq!(problem("Biking", "150"), None);
+        assert_eq!(
+            problem("Swimming", "20.001"),
+            Some(Problem::TooLarge {
+                max: kind("Swimming").max_amount
+            })
+        );
+        assert_eq!(
+            problem("Swimming", "150"),
+            Some(Problem::TooLarge {
+                max: kind("Swimming").max_amount
+            })
+        );
+    }
+
+    #[test]
+    pub fn checks_the_unit_of_the_kind() {
+        let yoga = kind("Yoga");
+        let check =
+            |measurement| validate_measurement(&yoga, &measurement).map(|error| error.problem);
+
+        assert_eq!(check(Measurement::Duration { minutes: 45 }), None);
+        assert_eq!(
+            check(Measurement::Duration { minutes: 0 }),
+            Some(Problem::NotPositive)
+        );
+        assert_eq!(
+            check(kilometers("5")),
+            Some(Problem::WrongUnit {
+                expected: Unit::Duration
+            })
+        );
+    }
+
+    #[test]
+    pub fn collects_errors_for_all_fields() {
+        let details = EntryDetails {
+            elevation_gain: Some(Decimal::from_thousandths(-10_000)),
+            note: Some("x".repeat(1000)),
+            ..Default::default()
+        };
+
+        let errors = validate_entry(&kind("Hiking"), &kilometers("-1"), &details).unwrap_err();
+
+        assert_eq!(errors.errors.len(), 3);
+        assert_eq!(
+            errors.for_field(Field::ElevationGain),
+            Some(&ValidationError {
+                field: Field::ElevationGain,
+                problem: Problem::Negative
+            })
+        );
+        assert!(errors.for_field(Field::Note).is_some());
+        assert!(errors.for_field(Field::Measurement).is_some());
+    }
+}